@@ -7,4 +7,8 @@ cargo +nightly contract build --manifest-path newomegastorage/Cargo.toml
 cargo +nightly contract build --manifest-path newomegarewarder/Cargo.toml
 cargo +nightly contract build --manifest-path newomegagame/Cargo.toml
 cargo +nightly contract build --manifest-path newomegaranked/Cargo.toml
+cargo +nightly contract build --manifest-path newomegatournament/Cargo.toml
+cargo +nightly contract build --manifest-path newomegachallenge/Cargo.toml
+cargo +nightly contract build --manifest-path newomegawar/Cargo.toml
+cargo +nightly contract build --manifest-path newomegamarketplace/Cargo.toml
 cargo +nightly contract build
@@ -23,3 +23,19 @@ cp target/ink/newomegarewarder/metadata.json ../newomega_client/src/ink/newomega
 cp target/ink/newomegastorage/newomegastorage.contract ../newomega_client/src/ink/newomegastorage/
 cp target/ink/newomegastorage/newomegastorage.wasm ../newomega_client/src/ink/newomegastorage/
 cp target/ink/newomegastorage/metadata.json ../newomega_client/src/ink/newomegastorage/
+
+cp target/ink/newomegatournament/newomegatournament.contract ../newomega_client/src/ink/newomegatournament/
+cp target/ink/newomegatournament/newomegatournament.wasm ../newomega_client/src/ink/newomegatournament/
+cp target/ink/newomegatournament/metadata.json ../newomega_client/src/ink/newomegatournament/
+
+cp target/ink/newomegachallenge/newomegachallenge.contract ../newomega_client/src/ink/newomegachallenge/
+cp target/ink/newomegachallenge/newomegachallenge.wasm ../newomega_client/src/ink/newomegachallenge/
+cp target/ink/newomegachallenge/metadata.json ../newomega_client/src/ink/newomegachallenge/
+
+cp target/ink/newomegawar/newomegawar.contract ../newomega_client/src/ink/newomegawar/
+cp target/ink/newomegawar/newomegawar.wasm ../newomega_client/src/ink/newomegawar/
+cp target/ink/newomegawar/metadata.json ../newomega_client/src/ink/newomegawar/
+
+cp target/ink/newomegamarketplace/newomegamarketplace.contract ../newomega_client/src/ink/newomegamarketplace/
+cp target/ink/newomegamarketplace/newomegamarketplace.wasm ../newomega_client/src/ink/newomegamarketplace/
+cp target/ink/newomegamarketplace/metadata.json ../newomega_client/src/ink/newomegamarketplace/
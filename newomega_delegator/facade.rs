@@ -11,13 +11,42 @@ mod newomegadelegator {
     use newomega::FightResult;
     use newomega::Move;
     use newomega::MAX_SHIPS;
+    use newomega::Ability;
+    use newomega::Reserve;
+    use newomega::Commander;
+    use newomega::Terrain;
+    use newomega::Battlefield;
     use newomegagame::NewOmegaGame;
     use newomegaranked::NewOmegaRanked;
     use newomegaranked::PlayerDefence;
+    use newomegaranked::DefencePreset;
+    use newomegaranked::RotationPolicy;
+    use newomegaranked::RankedFight;
     use newomegastorage::NewOmegaStorage;
     use newomegastorage::CommanderData;
+    use newomegastorage::CommanderToken;
     use newomegastorage::PlayerData;
+    use newomegastorage::SeasonRecord;
+    use newomegastorage::season_payouts;
+    use newomegastorage::League;
+    use newomegastorage::Guild;
+    use newomegastorage::GuildRole;
+    use newomegastorage::GuildStanding;
     use newomegarewarder::NewOmegaRewarder;
+    use newomegarewarder::CrateType;
+    use newomegarewarder::LootReward;
+    use newomegarewarder::loot_payout;
+    use newomegatournament::NewOmegaTournament;
+    use newomegatournament::Tournament;
+    use newomegatournament::TournamentFormat;
+    use newomegatournament::TournamentMatch;
+    use newomegachallenge::NewOmegaChallenge;
+    use newomegachallenge::Challenge;
+    use newomegawar::NewOmegaWar;
+    use newomegawar::War;
+    use newomegamarketplace::NewOmegaMarketplace;
+    use newomegamarketplace::Listing;
+    use newomegamarketplace::sale_transfers;
     use ink_prelude::vec::Vec;
     use ink_prelude::string::String;
     use ink_storage::{
@@ -42,6 +71,15 @@ mod newomegadelegator {
         new_omega_game: Lazy<NewOmegaGame>,
         new_omega_ranked: Lazy<NewOmegaRanked>,
         new_omega_rewarder: Lazy<NewOmegaRewarder>,
+        new_omega_tournament: Lazy<NewOmegaTournament>,
+        new_omega_challenge: Lazy<NewOmegaChallenge>,
+        new_omega_war: Lazy<NewOmegaWar>,
+        new_omega_marketplace: Lazy<NewOmegaMarketplace>,
+        season_rewards: Vec<Balance>,
+        /// Funds set aside for the season rewards
+        season_pool: Balance,
+        /// Funds held for players: challenge wagers, marketplace bids, tournament entry fees and war rewards
+        escrowed: Balance,
     }
 
     #[ink(event)]
@@ -50,10 +88,106 @@ mod newomegadelegator {
         attacker: AccountId,
         #[ink(topic)]
         defender: AccountId,
+        #[ink(topic)]
+        guild_lhs: Option<u32>,
+        #[ink(topic)]
+        guild_rhs: Option<u32>,
+        result: FightResult,
+    }
+
+    #[ink(event)]
+    pub struct TournamentRoundComplete {
+        #[ink(topic)]
+        tournament: u32,
+        matches: Vec<TournamentMatch>,
+    }
+
+    #[ink(event)]
+    pub struct ChallengeCreated {
+        #[ink(topic)]
+        challenge: u32,
+        #[ink(topic)]
+        challenger: AccountId,
+        #[ink(topic)]
+        opponent: AccountId,
+        wager: Balance,
+    }
+
+    #[ink(event)]
+    pub struct ChallengeResolved {
+        #[ink(topic)]
+        challenge: u32,
+        winner: Option<AccountId>,
+        result: Option<FightResult>,
+    }
+
+    #[ink(event)]
+    pub struct WarAttackComplete {
+        #[ink(topic)]
+        war: u32,
+        #[ink(topic)]
+        attacker: AccountId,
+        stars: u8,
         result: FightResult,
     }
 
-    const LOOT_CRATE_PRICE: u128 = 1;
+    #[ink(event)]
+    pub struct WarFinished {
+        #[ink(topic)]
+        war: u32,
+        winner: Option<u32>,
+    }
+
+    #[ink(event)]
+    pub struct ListingCreated {
+        #[ink(topic)]
+        listing: u32,
+        #[ink(topic)]
+        seller: AccountId,
+        #[ink(topic)]
+        token: u32,
+        price: Balance,
+    }
+
+    #[ink(event)]
+    pub struct ListingCancelled {
+        #[ink(topic)]
+        listing: u32,
+    }
+
+    #[ink(event)]
+    pub struct BidPlaced {
+        #[ink(topic)]
+        listing: u32,
+        #[ink(topic)]
+        bidder: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct BidCancelled {
+        #[ink(topic)]
+        listing: u32,
+        #[ink(topic)]
+        bidder: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct ListingSold {
+        #[ink(topic)]
+        listing: u32,
+        #[ink(topic)]
+        buyer: AccountId,
+        price: Balance,
+        fee: Balance,
+    }
+
+    #[ink(event)]
+    pub struct SeasonClosed {
+        #[ink(topic)]
+        season: u32,
+        rewards_paid: Balance,
+    }
 
     impl NewOmegaDelegator {
 
@@ -67,6 +201,10 @@ mod newomegadelegator {
         /// * `newomega_game_code_hash` - Contract code hash: NewOmegaGame
         /// * `newomega_ranked_code_hash` - Contract code hash: NewOmegaRanked
         /// * `newomega_rewarder_code_hash` - Contract code hash: NewOmegaRewarder
+        /// * `newomega_tournament_code_hash` - Contract code hash: NewOmegaTournament
+        /// * `newomega_challenge_code_hash` - Contract code hash: NewOmegaChallenge
+        /// * `newomega_war_code_hash` - Contract code hash: NewOmegaWar
+        /// * `newomega_marketplace_code_hash` - Contract code hash: NewOmegaMarketplace
         #[ink(constructor)]
         pub fn new(
             version: u32,
@@ -75,43 +213,73 @@ mod newomegadelegator {
             newomega_game_code_hash: Hash,
             newomega_ranked_code_hash: Hash,
             newomega_rewarder_code_hash: Hash,
+            newomega_tournament_code_hash: Hash,
+            newomega_challenge_code_hash: Hash,
+            newomega_war_code_hash: Hash,
+            newomega_marketplace_code_hash: Hash,
         ) -> Self {
             let total_balance = Self::env().balance();
             let salt = version.to_le_bytes();
             let new_omega = NewOmega::new()
-                .endowment(total_balance / 8)
+                .endowment(total_balance / 10)
                 .code_hash(newomega_code_hash)
                 .salt_bytes(salt)
                 .instantiate()
                 .expect("Failed instantiating NewOmega");
             let new_omega_game = NewOmegaGame::new(new_omega.clone())
-                .endowment(total_balance / 8)
+                .endowment(total_balance / 10)
                 .code_hash(newomega_game_code_hash)
                 .salt_bytes(salt)
                 .instantiate()
                 .expect("Failed instantiating NewOmegaGame");
             let mut new_omega_storage = NewOmegaStorage::new()
-                .endowment(total_balance / 8)
+                .endowment(total_balance / 10)
                 .code_hash(newomega_storage_code_hash)
                 .salt_bytes(salt)
                 .instantiate()
                 .expect("Failed instantiating NewOmegaStorage");
             let new_omega_ranked = NewOmegaRanked::new(new_omega_game.clone(), new_omega_storage.clone())
-                .endowment(total_balance / 8)
+                .endowment(total_balance / 10)
                 .code_hash(newomega_ranked_code_hash)
                 .salt_bytes(salt)
                 .instantiate()
                 .expect("Failed instantiating NewOmegaRanked");
             let new_omega_rewarder = NewOmegaRewarder::new(new_omega_storage.clone())
-                .endowment(total_balance / 8)
+                .endowment(total_balance / 10)
                 .code_hash(newomega_rewarder_code_hash)
                 .salt_bytes(salt)
                 .instantiate()
                 .expect("Failed instantiating NewOmegaRewarder");
+            let new_omega_tournament = NewOmegaTournament::new(new_omega_game.clone())
+                .endowment(total_balance / 10)
+                .code_hash(newomega_tournament_code_hash)
+                .salt_bytes(salt)
+                .instantiate()
+                .expect("Failed instantiating NewOmegaTournament");
+            let new_omega_challenge = NewOmegaChallenge::new(new_omega_game.clone())
+                .endowment(total_balance / 10)
+                .code_hash(newomega_challenge_code_hash)
+                .salt_bytes(salt)
+                .instantiate()
+                .expect("Failed instantiating NewOmegaChallenge");
+            let new_omega_war = NewOmegaWar::new(new_omega_game.clone(), new_omega_storage.clone())
+                .endowment(total_balance / 10)
+                .code_hash(newomega_war_code_hash)
+                .salt_bytes(salt)
+                .instantiate()
+                .expect("Failed instantiating NewOmegaWar");
+            let new_omega_marketplace = NewOmegaMarketplace::new(new_omega_storage.clone())
+                .endowment(total_balance / 10)
+                .code_hash(newomega_marketplace_code_hash)
+                .salt_bytes(salt)
+                .instantiate()
+                .expect("Failed instantiating NewOmegaMarketplace");
 
-            // Authorise the Ranked and Rewarder contracts to use the Storage contract
+            // Authorise the Ranked, Rewarder, War and Marketplace contracts to use the Storage contract
             new_omega_storage.authorise_contract(new_omega_ranked.to_account_id());
             new_omega_storage.authorise_contract(new_omega_rewarder.to_account_id());
+            new_omega_storage.authorise_contract(new_omega_war.to_account_id());
+            new_omega_storage.authorise_contract(new_omega_marketplace.to_account_id());
 
             Self {
                 owner: Self::env().caller(),
@@ -120,9 +288,46 @@ mod newomegadelegator {
                 new_omega_game: Lazy::new(new_omega_game),
                 new_omega_ranked: Lazy::new(new_omega_ranked),
                 new_omega_rewarder: Lazy::new(new_omega_rewarder),
+                new_omega_tournament: Lazy::new(new_omega_tournament),
+                new_omega_challenge: Lazy::new(new_omega_challenge),
+                new_omega_war: Lazy::new(new_omega_war),
+                new_omega_marketplace: Lazy::new(new_omega_marketplace),
+                season_rewards: Vec::new(),
+                season_pool: 0,
+                escrowed: 0,
             }
         }
 
+        /// Holds funds received from a player in escrow, until they are paid out or refunded.
+        ///
+        /// # Arguments
+        ///
+        /// * `amount` - The amount held
+        fn hold_escrow(&mut self, amount: Balance) {
+            self.escrowed += amount;
+        }
+
+        /// Pays out or refunds funds held in escrow.
+        ///
+        /// # Arguments
+        ///
+        /// * `to` - Account id to transfer the funds to
+        /// * `amount` - The amount released
+        fn release_escrow(&mut self, to: AccountId, amount: Balance) {
+            self.escrowed -= amount;
+            assert!(self.env().transfer(to, amount).is_ok());
+        }
+
+        /// Gets the funds of the Delegator which are not owed to anyone: the balance, minus the funds held
+        /// in escrow and the season pool.
+        ///
+        /// # Returns
+        ///
+        /// * `available` - The available funds
+        fn available_balance(&self) -> Balance {
+            self.env().balance().saturating_sub(self.escrowed).saturating_sub(self.season_pool)
+        }
+
         /// Returns a fight replay (detailed fight description).
         ///
         /// # Arguments
@@ -134,20 +339,29 @@ mod newomegadelegator {
         /// * `variants_rhs` - An array that holds variants of the defender fleet
         /// * `commander_lhs` - The attacker commander
         /// * `commander_rhs` - The defender commander
+        /// * `commander_level_lhs` - Level of the attacker commander, 0 for no commander bonus
+        /// * `commander_level_rhs` - Level of the defender commander, 0 for no commander bonus
+        /// * `commander_skills_lhs` - Selected skills of the attacker commander (bitmask of skill ids)
+        /// * `commander_skills_rhs` - Selected skills of the defender commander (bitmask of skill ids)
+        /// * `reserve_lhs` - The attacker reserve
+        /// * `reserve_rhs` - The defender reserve
+        /// * `map` - Id of the map the fight takes place on
         ///
         /// # Returns
         ///
-        /// * `result` - A FightResult structure containing the result
+        /// * `result` - A FightResult structure containing the result, with fight statistics
         /// * `moves_lhs` - Logged moves of the attacker
         /// * `moves_rhs` - Logged moves of the defender
         #[ink(message)]
         pub fn replay(&self, seed: u64, selection_lhs: [u8; MAX_SHIPS],
             selection_rhs: [u8; MAX_SHIPS], variants_lhs: [u8; MAX_SHIPS],
-            variants_rhs: [u8; MAX_SHIPS], commander_lhs: u8, commander_rhs: u8) -> (FightResult, Option<Vec<Move>>,
-                Option<Vec<Move>>) {
+            variants_rhs: [u8; MAX_SHIPS], commander_lhs: u8, commander_rhs: u8, commander_level_lhs: u8,
+            commander_level_rhs: u8, commander_skills_lhs: u32, commander_skills_rhs: u32, reserve_lhs: Reserve,
+            reserve_rhs: Reserve, map: u8) -> (FightResult, Option<Vec<Move>>, Option<Vec<Move>>) {
 
-            self.new_omega_game.fight(seed, true, selection_lhs, selection_rhs,
-                variants_lhs, variants_rhs, commander_lhs, commander_rhs)
+            self.new_omega_game.fight(seed, true, true, selection_lhs, selection_rhs,
+                variants_lhs, variants_rhs, commander_lhs, commander_rhs, commander_level_lhs, commander_level_rhs,
+                commander_skills_lhs, commander_skills_rhs, reserve_lhs, reserve_rhs, map)
         }
 
         /// Returns a fight result (without detailed fight description).
@@ -161,20 +375,109 @@ mod newomegadelegator {
         /// * `variants_rhs` - An array that holds variants of the defender fleet
         /// * `commander_lhs` - The attacker commander
         /// * `commander_rhs` - The defender commander
+        /// * `commander_level_lhs` - Level of the attacker commander, 0 for no commander bonus
+        /// * `commander_level_rhs` - Level of the defender commander, 0 for no commander bonus
+        /// * `commander_skills_lhs` - Selected skills of the attacker commander (bitmask of skill ids)
+        /// * `commander_skills_rhs` - Selected skills of the defender commander (bitmask of skill ids)
+        /// * `reserve_lhs` - The attacker reserve
+        /// * `reserve_rhs` - The defender reserve
+        /// * `map` - Id of the map the fight takes place on
         ///
         /// # Returns
         ///
-        /// * `result` - A FightResult structure containing the result
+        /// * `result` - A FightResult structure containing the result, with fight statistics
         /// * `moves_lhs` - Always returning None
         /// * `moves_rhs` - Always returning None
         #[ink(message)]
         pub fn replay_result(&self, seed: u64, selection_lhs: [u8; MAX_SHIPS],
             selection_rhs: [u8; MAX_SHIPS], variants_lhs: [u8; MAX_SHIPS],
-            variants_rhs: [u8; MAX_SHIPS], commander_lhs: u8, commander_rhs: u8) -> (FightResult, Option<Vec<Move>>,
-                Option<Vec<Move>>) {
+            variants_rhs: [u8; MAX_SHIPS], commander_lhs: u8, commander_rhs: u8, commander_level_lhs: u8,
+            commander_level_rhs: u8, commander_skills_lhs: u32, commander_skills_rhs: u32, reserve_lhs: Reserve,
+            reserve_rhs: Reserve, map: u8) -> (FightResult, Option<Vec<Move>>, Option<Vec<Move>>) {
+
+            self.new_omega_game.fight(seed, false, true, selection_lhs, selection_rhs,
+                variants_lhs, variants_rhs, commander_lhs, commander_rhs, commander_level_lhs, commander_level_rhs,
+                commander_skills_lhs, commander_skills_rhs, reserve_lhs, reserve_rhs, map)
+        }
+
+        /// Returns a fight replay in the compact, versioned replay format.
+        /// The replay can be decoded with `newomega::decode_replay`.
+        ///
+        /// # Arguments
+        ///
+        /// * `seed` - Seed used to generate randomness
+        /// * `selection_lhs` - Attacker fleet composition (array with ship quantities)
+        /// * `selection_rhs` - Defender fleet composition (array with ship quantities)
+        /// * `variants_lhs` - An array that holds variants of the attacker fleet
+        /// * `variants_rhs` - An array that holds variants of the defender fleet
+        /// * `commander_lhs` - The attacker commander
+        /// * `commander_rhs` - The defender commander
+        /// * `commander_level_lhs` - Level of the attacker commander, 0 for no commander bonus
+        /// * `commander_level_rhs` - Level of the defender commander, 0 for no commander bonus
+        /// * `commander_skills_lhs` - Selected skills of the attacker commander (bitmask of skill ids)
+        /// * `commander_skills_rhs` - Selected skills of the defender commander (bitmask of skill ids)
+        /// * `reserve_lhs` - The attacker reserve
+        /// * `reserve_rhs` - The defender reserve
+        /// * `map` - Id of the map the fight takes place on
+        ///
+        /// # Returns
+        ///
+        /// * `replay` - The encoded replay bytes
+        #[ink(message)]
+        pub fn replay_compact(&self, seed: u64, selection_lhs: [u8; MAX_SHIPS],
+            selection_rhs: [u8; MAX_SHIPS], variants_lhs: [u8; MAX_SHIPS],
+            variants_rhs: [u8; MAX_SHIPS], commander_lhs: u8, commander_rhs: u8, commander_level_lhs: u8,
+            commander_level_rhs: u8, commander_skills_lhs: u32, commander_skills_rhs: u32, reserve_lhs: Reserve,
+            reserve_rhs: Reserve, map: u8) -> Vec<u8> {
 
-            self.new_omega_game.fight(seed, false, selection_lhs, selection_rhs,
-                variants_lhs, variants_rhs, commander_lhs, commander_rhs)
+            let (result, moves_lhs, moves_rhs) = self.new_omega_game.fight(seed, true, false,
+                selection_lhs, selection_rhs, variants_lhs, variants_rhs, commander_lhs, commander_rhs,
+                commander_level_lhs, commander_level_rhs, commander_skills_lhs, commander_skills_rhs,
+                reserve_lhs, reserve_rhs, map);
+            let ships_hash: [u8; 32] = newomega::hash_ships(&self.new_omega_game.get_ships());
+
+            newomega::encode_replay(ships_hash, &result, &moves_lhs.unwrap_or_default(),
+                &moves_rhs.unwrap_or_default())
+        }
+
+        /// Verifies that a set of fight inputs corresponds to a recorded fight, by rerunning
+        /// the fight and comparing its commitment hash with the expected one
+        /// (for instance, taken from a RankedFightComplete event).
+        ///
+        /// # Arguments
+        ///
+        /// * `seed` - Seed used to generate randomness
+        /// * `selection_lhs` - Attacker fleet composition (array with ship quantities)
+        /// * `selection_rhs` - Defender fleet composition (array with ship quantities)
+        /// * `variants_lhs` - An array that holds variants of the attacker fleet
+        /// * `variants_rhs` - An array that holds variants of the defender fleet
+        /// * `commander_lhs` - The attacker commander
+        /// * `commander_rhs` - The defender commander
+        /// * `commander_level_lhs` - Level of the attacker commander, 0 for no commander bonus
+        /// * `commander_level_rhs` - Level of the defender commander, 0 for no commander bonus
+        /// * `commander_skills_lhs` - Selected skills of the attacker commander (bitmask of skill ids)
+        /// * `commander_skills_rhs` - Selected skills of the defender commander (bitmask of skill ids)
+        /// * `reserve_lhs` - The attacker reserve
+        /// * `reserve_rhs` - The defender reserve
+        /// * `map` - Id of the map the fight takes place on
+        /// * `expected_hash` - The recorded fight commitment hash
+        ///
+        /// # Returns
+        ///
+        /// * `is_valid` - Whether the fight generated from the inputs matches the expected hash
+        #[ink(message)]
+        pub fn verify_replay(&self, seed: u64, selection_lhs: [u8; MAX_SHIPS],
+            selection_rhs: [u8; MAX_SHIPS], variants_lhs: [u8; MAX_SHIPS],
+            variants_rhs: [u8; MAX_SHIPS], commander_lhs: u8, commander_rhs: u8, commander_level_lhs: u8,
+            commander_level_rhs: u8, commander_skills_lhs: u32, commander_skills_rhs: u32, reserve_lhs: Reserve,
+            reserve_rhs: Reserve, map: u8, expected_hash: [u8; 32]) -> bool {
+
+            let (result, _moves_lhs, _moves_rhs) = self.new_omega_game.fight(seed, false, false,
+                selection_lhs, selection_rhs, variants_lhs, variants_rhs, commander_lhs, commander_rhs,
+                commander_level_lhs, commander_level_rhs, commander_skills_lhs, commander_skills_rhs,
+                reserve_lhs, reserve_rhs, map);
+
+            result.hash == expected_hash
         }
 
         /// Adds ship to the ship definitions
@@ -196,21 +499,209 @@ mod newomegadelegator {
             self.new_omega_game.add_ship(cp, hp, attack_base, attack_variable, defence, speed, range);
         }
 
-        /// Registers a fleet for Ranked Defence.
+        /// Sets the special abilities of a ship, replacing the previous ones
+        ///
+        /// # Arguments
+        ///
+        /// * `ship` - Index of the ship in the ship definitions
+        /// * `abilities` - The abilities of the ship
+        #[ink(message)]
+        pub fn set_ship_abilities(&mut self, ship: u8, abilities: Vec<Ability>) {
+            assert_eq!(self.env().caller(), self.owner);
+            self.new_omega_game.set_ship_abilities(ship, abilities);
+        }
+
+        /// Sets the morale rules applied to all fights. A threshold of 0 disables the rule.
+        ///
+        /// # Arguments
+        ///
+        /// * `rout_threshold` - Percentage of its starting HP a stack has to lose to rout
+        /// * `retreat_threshold` - Percentage of its starting HP a fleet has to lose to retreat
+        #[ink(message)]
+        pub fn set_morale(&mut self, rout_threshold: u8, retreat_threshold: u8) {
+            assert_eq!(self.env().caller(), self.owner);
+            self.new_omega_game.set_morale(rout_threshold, retreat_threshold);
+        }
+
+        /// Sets the precision stats of a ship.
+        ///
+        /// # Arguments
+        ///
+        /// * `ship` - Index of the ship in the ship definitions
+        /// * `accuracy` - Chance to hit, in percent
+        /// * `evasion` - Chance to evade, in percent
+        /// * `crit_chance` - Chance of a critical hit, in percent
+        /// * `crit_multiplier` - Damage of a critical hit, in percent of the regular damage, up to 1000
+        #[ink(message)]
+        pub fn set_ship_precision(&mut self, ship: u8, accuracy: u8, evasion: u8, crit_chance: u8,
+            crit_multiplier: u16) {
+
+            assert_eq!(self.env().caller(), self.owner);
+            self.new_omega_game.set_ship_precision(ship, accuracy, evasion, crit_chance, crit_multiplier);
+        }
+
+        /// Adds a map to the map definitions
+        ///
+        /// # Arguments
+        ///
+        /// * `name` - Name of the map
+        /// * `terrain` - Terrain features of the map
+        #[ink(message)]
+        pub fn add_map(&mut self, name: String, terrain: Vec<Terrain>) {
+            assert_eq!(self.env().caller(), self.owner);
+            self.new_omega_game.add_map(name, terrain);
+        }
+
+        /// Returns all the registered maps
+        ///
+        /// # Returns
+        ///
+        /// * `maps` - A Vector containing the registered maps
+        #[ink(message)]
+        pub fn get_maps(&self) -> Vec<Battlefield> {
+            self.new_omega_game.get_maps()
+        }
+
+        /// Returns the commander definitions (rarity, bonuses per level and skill trees)
+        ///
+        /// # Returns
+        ///
+        /// * `commanders` - A Vector containing the commander definitions
+        #[ink(message)]
+        pub fn get_commander_definitions(&self) -> Vec<Commander> {
+            self.new_omega_game.get_commanders()
+        }
+
+        /// Registers a fleet for Ranked Defence, as the only defence preset of the caller.
         ///
         /// # Arguments
         ///
         /// * `selection` - The fleet composition of the defence
         /// * `variants` - The variants (fittings) of the defence
         /// * `commander` - Index of the commander leading the defence
+        /// * `reserve` - The reserve of the defence, entering the battle later
         /// * `name` - The defender name
         #[ink(message)]
         pub fn register_defence(&mut self, selection: [u8; MAX_SHIPS],
-            variants: [u8; MAX_SHIPS], commander: u8, name: String) {
+            variants: [u8; MAX_SHIPS], commander: u8, reserve: Reserve, name: String) {
 
             let caller: AccountId = self.env().caller();
             self.new_omega_ranked.register_defence(caller, selection,
-                variants, commander, name);
+                variants, commander, reserve, name);
+        }
+
+        /// Unregisters the defence of the caller, withdrawing from ranked.
+        #[ink(message)]
+        pub fn unregister_defence(&mut self) {
+            let caller: AccountId = self.env().caller();
+            self.new_omega_ranked.unregister_defence(caller);
+        }
+
+        /// Pauses the defence of the caller (vacation mode). A paused defence cannot be attacked,
+        /// the pause expires after the configured number of blocks, or when the caller attacks.
+        #[ink(message)]
+        pub fn pause_defence(&mut self) {
+            let caller: AccountId = self.env().caller();
+            self.new_omega_ranked.pause_defence(caller);
+        }
+
+        /// Resumes the paused defence of the caller, before the pause expires.
+        #[ink(message)]
+        pub fn resume_defence(&mut self) {
+            let caller: AccountId = self.env().caller();
+            self.new_omega_ranked.resume_defence(caller);
+        }
+
+        /// Sets the number of blocks a defence pause lasts for.
+        ///
+        /// # Arguments
+        ///
+        /// * `pause_duration` - Duration of the pause, in blocks
+        #[ink(message)]
+        pub fn set_pause_duration(&mut self, pause_duration: BlockNumber) {
+            assert_eq!(self.env().caller(), self.owner);
+            self.new_omega_ranked.set_pause_duration(pause_duration);
+        }
+
+        /// Gets the number of blocks a defence pause lasts for.
+        ///
+        /// # Returns
+        ///
+        /// * `pause_duration` - Duration of the pause, in blocks
+        #[ink(message)]
+        pub fn get_pause_duration(&self) -> BlockNumber {
+            self.new_omega_ranked.get_pause_duration()
+        }
+
+        /// Sets a defence preset of the caller, replacing the preset in the slot,
+        /// or adding a new one if the slot is right after the last preset.
+        ///
+        /// # Arguments
+        ///
+        /// * `slot` - Index of the preset
+        /// * `label` - The preset name
+        /// * `selection` - The fleet composition of the preset
+        /// * `variants` - The variants (fittings) of the preset
+        /// * `commander` - Index of the commander leading the preset
+        /// * `reserve` - The reserve of the preset
+        #[ink(message)]
+        pub fn set_defence_preset(&mut self, slot: u8, label: String, selection: [u8; MAX_SHIPS],
+            variants: [u8; MAX_SHIPS], commander: u8, reserve: Reserve) {
+
+            let caller: AccountId = self.env().caller();
+            self.new_omega_ranked.set_defence_preset(caller, slot, label, selection,
+                variants, commander, reserve);
+        }
+
+        /// Sets how the defence preset of the caller is picked for each attack.
+        ///
+        /// # Arguments
+        ///
+        /// * `policy` - The rotation policy (fixed, round-robin or random)
+        /// * `active` - Index of the active preset (the next one, for round-robin)
+        #[ink(message)]
+        pub fn set_defence_policy(&mut self, policy: RotationPolicy, active: u8) {
+            let caller: AccountId = self.env().caller();
+            self.new_omega_ranked.set_defence_policy(caller, policy, active);
+        }
+
+        /// Gets the defence presets of the caller.
+        ///
+        /// # Returns
+        ///
+        /// * `presets` - The defence presets
+        #[ink(message)]
+        pub fn get_defence_presets(&self) -> Vec<DefencePreset> {
+            self.new_omega_ranked.get_defence_presets(self.env().caller())
+        }
+
+        /// Gets the recorded ranked fights of a player, with the defence preset used in each.
+        ///
+        /// # Arguments
+        ///
+        /// * `player` - The account id of the player
+        ///
+        /// # Returns
+        ///
+        /// * `fights` - The recorded fights, oldest first
+        #[ink(message)]
+        pub fn get_ranked_fights(&self, player: AccountId) -> Vec<RankedFight> {
+            self.new_omega_ranked.get_fights(player)
+        }
+
+        /// Gets the defence preset used in a recorded ranked fight, as long as the defender
+        /// did not replace or remove it since.
+        ///
+        /// # Arguments
+        ///
+        /// * `fight` - The recorded fight
+        ///
+        /// # Returns
+        ///
+        /// * `preset` - The preset used, None if it was replaced or removed since
+        #[ink(message)]
+        pub fn get_ranked_fight_preset(&self, fight: RankedFight) -> Option<DefencePreset> {
+            self.new_omega_ranked.get_fight_preset(fight)
         }
 
         /// Gets the registered defence of a player.
@@ -234,7 +725,23 @@ mod newomegadelegator {
             self.new_omega_ranked.get_all_defenders()
         }
 
-        /// Calculates a ranked fight between caller and another player.
+        /// Suggests fair opponents for the caller: active defenders closest to the caller's rating,
+        /// excluding targets attacked recently. Deterministic for a given block.
+        ///
+        /// # Arguments
+        ///
+        /// * `count` - Maximum number of opponents to return
+        ///
+        /// # Returns
+        ///
+        /// * `opponents` - The suggested defenders, fairest first
+        #[ink(message)]
+        pub fn find_opponents(&self, count: u8) -> Vec<(AccountId, PlayerDefence)> {
+            self.new_omega_ranked.find_opponents(self.env().caller(), count)
+        }
+
+        /// Calculates a ranked fight between caller and another player. The fight event is tagged
+        /// with the guilds of both players, if any.
         ///
         /// # Arguments
         ///
@@ -242,21 +749,24 @@ mod newomegadelegator {
         /// * `selection` - Attacker fleet composition (array with ship quantities)
         /// * `variants` - An array that holds variants of the attacker fleet
         /// * `commander` - The attacker commander
+        /// * `reserve` - The attacker reserve, entering the battle later
         ///
         /// # Events
         ///
         /// * RankedFightComplete - when fight is complete
         #[ink(message)]
         pub fn attack(&mut self, target: AccountId, selection: [u8; MAX_SHIPS],
-            variants: [u8; MAX_SHIPS], commander: u8) {
+            variants: [u8; MAX_SHIPS], commander: u8, reserve: Reserve) {
 
             let caller: AccountId = self.env().caller();
             let result: FightResult = self.new_omega_ranked.attack(
-                caller, target, selection, variants, commander);
+                caller, target, selection, variants, commander, reserve);
 
             self.env().emit_event(RankedFightComplete {
                 attacker: caller,
                 defender: target,
+                guild_lhs: self.new_omega_storage.get_player_guild(caller),
+                guild_rhs: self.new_omega_storage.get_player_guild(target),
                 result,
             });
         }
@@ -271,37 +781,1262 @@ mod newomegadelegator {
             self.new_omega_storage.get_leaderboard()
         }
 
-        /// Gets all the owned commanders for the caller.
+        /// Gets the current ranked league of a player.
+        ///
+        /// # Arguments
+        ///
+        /// * `player` - The account id of the player
         ///
         /// # Returns
         ///
-        /// * `commanders` - A Vec containing a tuple of (commander id, commander data)
+        /// * `league` - The league of the player, Bronze if the player didn't fight yet
         #[ink(message)]
-        pub fn get_commanders(&self) -> Vec<(u8, CommanderData)> {
-            self.new_omega_storage.get_commanders(self.env().caller())
+        pub fn get_league(&self, player: AccountId) -> League {
+            self.new_omega_storage.get_league(player)
         }
 
-        /// Generates a loot crate for the caller.
+        /// Creates a guild, led by the caller.
+        ///
+        /// # Arguments
+        ///
+        /// * `name` - Name of the guild, has to be unique
         ///
         /// # Returns
         ///
-        /// * `commander` - Id of the commander received from the loot crate
-        #[ink(message, payable)]
-        pub fn buy_loot_crate(&mut self) -> u8 {
-            assert!(self.env().transferred_balance() >= LOOT_CRATE_PRICE);
+        /// * `guild` - Id of the created guild
+        #[ink(message)]
+        pub fn create_guild(&mut self, name: String) -> u32 {
+            let caller: AccountId = self.env().caller();
+            self.new_omega_storage.create_guild(caller, name)
+        }
+
+        /// Invites a player to the guild of the caller, who has to be an officer or the leader.
+        ///
+        /// # Arguments
+        ///
+        /// * `player` - The account id of the invited player
+        #[ink(message)]
+        pub fn invite_to_guild(&mut self, player: AccountId) {
+            let caller: AccountId = self.env().caller();
+            self.new_omega_storage.invite_to_guild(caller, player);
+        }
+
+        /// Joins a guild. Invited players join right away, otherwise an application
+        /// is filed, waiting for the approval of an officer.
+        ///
+        /// # Arguments
+        ///
+        /// * `guild` - Id of the guild
+        ///
+        /// # Returns
+        ///
+        /// * `joined` - Whether the caller joined, false if applied
+        #[ink(message)]
+        pub fn join_guild(&mut self, guild: u32) -> bool {
             let caller: AccountId = self.env().caller();
-            self.new_omega_rewarder.buy_loot_crate(caller)
+            self.new_omega_storage.join_guild(caller, guild)
+        }
+
+        /// Approves the application of a player to the guild of the caller, who has to be
+        /// an officer or the leader.
+        ///
+        /// # Arguments
+        ///
+        /// * `player` - The account id of the applicant
+        #[ink(message)]
+        pub fn approve_guild_member(&mut self, player: AccountId) {
+            let caller: AccountId = self.env().caller();
+            self.new_omega_storage.approve_guild_member(caller, player);
+        }
+
+        /// Leaves the guild of the caller. A leaving leader is succeeded by an officer,
+        /// and the last member leaving disbands the guild.
+        #[ink(message)]
+        pub fn leave_guild(&mut self) {
+            let caller: AccountId = self.env().caller();
+            self.new_omega_storage.leave_guild(caller);
+        }
+
+        /// Kicks a member out of the guild of the caller. Officers can kick members,
+        /// the leader can kick officers as well.
+        ///
+        /// # Arguments
+        ///
+        /// * `player` - The account id of the kicked member
+        #[ink(message)]
+        pub fn kick_guild_member(&mut self, player: AccountId) {
+            let caller: AccountId = self.env().caller();
+            self.new_omega_storage.kick_guild_member(caller, player);
+        }
+
+        /// Sets the role of a member of the guild of the caller, who has to be the leader.
+        /// Appointing another leader hands over the leadership.
+        ///
+        /// # Arguments
+        ///
+        /// * `player` - The account id of the member
+        /// * `role` - The new role of the member
+        #[ink(message)]
+        pub fn set_guild_role(&mut self, player: AccountId, role: GuildRole) {
+            let caller: AccountId = self.env().caller();
+            self.new_omega_storage.set_guild_role(caller, player, role);
+        }
+
+        /// Gets a guild, with its members and pending invites and applications.
+        ///
+        /// # Arguments
+        ///
+        /// * `guild` - Id of the guild
+        ///
+        /// # Returns
+        ///
+        /// * `guild` - The guild
+        #[ink(message)]
+        pub fn get_guild(&self, guild: u32) -> Guild {
+            self.new_omega_storage.get_guild(guild)
+        }
+
+        /// Gets all the guilds.
+        ///
+        /// # Returns
+        ///
+        /// * `guilds` - A Vec containing a tuple of (guild id, guild)
+        #[ink(message)]
+        pub fn get_guilds(&self) -> Vec<(u32, Guild)> {
+            self.new_omega_storage.get_guilds()
+        }
+
+        /// Gets the guild of a player.
+        ///
+        /// # Arguments
+        ///
+        /// * `player` - The account id of the player
+        ///
+        /// # Returns
+        ///
+        /// * `guild` - Id of the guild, None if the player is not in a guild
+        #[ink(message)]
+        pub fn get_player_guild(&self, player: AccountId) -> Option<u32> {
+            self.new_omega_storage.get_player_guild(player)
+        }
+
+        /// Gets the guild leaderboard, aggregated from the ranked results of the members.
+        ///
+        /// # Returns
+        ///
+        /// * `leaderboard` - The guild standings, most points first
+        #[ink(message)]
+        pub fn get_guild_leaderboard(&self) -> Vec<GuildStanding> {
+            self.new_omega_storage.get_guild_leaderboard()
+        }
+
+        /// Schedules a war between two guilds, with the transferred amount as the reward.
+        /// The teams are the members of both guilds at the time of scheduling.
+        ///
+        /// # Arguments
+        ///
+        /// * `guild_lhs` - Id of the first guild
+        /// * `guild_rhs` - Id of the second guild
+        /// * `slots` - Number of defences each guild can nominate
+        /// * `start` - Block the battle window opens in
+        /// * `end` - Block the battle window closes in
+        ///
+        /// # Returns
+        ///
+        /// * `id` - Id of the scheduled war
+        #[ink(message, payable)]
+        pub fn schedule_guild_war(&mut self, guild_lhs: u32, guild_rhs: u32, slots: u8, start: BlockNumber,
+            end: BlockNumber) -> u32 {
+
+            assert_eq!(self.env().caller(), self.owner);
+            // Panics if either guild does not exist
+            let members_lhs: Vec<AccountId> = self.new_omega_storage.get_guild(guild_lhs).members
+                .iter()
+                .map(|&(member, _)| member)
+                .collect();
+            let members_rhs: Vec<AccountId> = self.new_omega_storage.get_guild(guild_rhs).members
+                .iter()
+                .map(|&(member, _)| member)
+                .collect();
+
+            let reward: Balance = self.env().transferred_balance();
+            self.hold_escrow(reward);
+            self.new_omega_war.schedule_war(guild_lhs, members_lhs, guild_rhs, members_rhs, slots, start,
+                end, reward)
+        }
+
+        /// Nominates the defence of the caller for a war of their guild, before the battle window opens.
+        /// Replaces the previous nomination of the caller.
+        ///
+        /// # Arguments
+        ///
+        /// * `war` - Id of the war
+        /// * `selection` - The fleet composition of the defence
+        /// * `variants` - The variants (fittings) of the defence
+        /// * `commander` - Index of the commander leading the defence
+        /// * `reserve` - The reserve of the defence
+        #[ink(message)]
+        pub fn nominate_war_defence(&mut self, war: u32, selection: [u8; MAX_SHIPS],
+            variants: [u8; MAX_SHIPS], commander: u8, reserve: Reserve) {
+
+            let caller: AccountId = self.env().caller();
+            self.new_omega_war.nominate_defence(caller, war, selection, variants, commander, reserve);
+        }
+
+        /// Attacks a defence of the opposing guild in a war, during the battle window.
+        ///
+        /// # Arguments
+        ///
+        /// * `war` - Id of the war
+        /// * `defence` - Index of the attacked defence
+        /// * `selection` - Attacker fleet composition (array with ship quantities)
+        /// * `variants` - An array that holds variants of the attacker fleet
+        /// * `commander` - The attacker commander
+        /// * `reserve` - The attacker reserve
+        ///
+        /// # Events
+        ///
+        /// * WarAttackComplete - when the attack is over
+        #[ink(message)]
+        pub fn war_attack(&mut self, war: u32, defence: u8, selection: [u8; MAX_SHIPS],
+            variants: [u8; MAX_SHIPS], commander: u8, reserve: Reserve) {
+
+            let caller: AccountId = self.env().caller();
+            let (stars, result) = self.new_omega_war.attack(caller, war, defence, selection, variants,
+                commander, reserve);
+
+            self.env().emit_event(WarAttackComplete {
+                war,
+                attacker: caller,
+                stars,
+                result,
+            });
+        }
+
+        /// Finishes a war after its battle window, paying out the reward to the winning guild.
+        /// Anybody can finish a war. Whatever is left of the reward is refunded to the owner,
+        /// who scheduled the war.
+        ///
+        /// # Arguments
+        ///
+        /// * `war` - Id of the war
+        ///
+        /// # Events
+        ///
+        /// * WarFinished - when the war is finished
+        #[ink(message)]
+        pub fn finish_guild_war(&mut self, war: u32) {
+            let (winner, payouts, refund) = self.new_omega_war.finish_war(war);
+            for (player, amount) in payouts.into_iter() {
+                self.release_escrow(player, amount);
+            }
+            if refund > 0 {
+                self.release_escrow(self.owner, refund);
+            }
+
+            self.env().emit_event(WarFinished {
+                war,
+                winner,
+            });
+        }
+
+        /// Gets a guild war.
+        ///
+        /// # Arguments
+        ///
+        /// * `war` - Id of the war
+        ///
+        /// # Returns
+        ///
+        /// * `war` - The war
+        #[ink(message)]
+        pub fn get_guild_war(&self, war: u32) -> War {
+            self.new_omega_war.get_war(war)
+        }
+
+        /// Gets all the wars of a guild.
+        ///
+        /// # Arguments
+        ///
+        /// * `guild` - Id of the guild
+        ///
+        /// # Returns
+        ///
+        /// * `wars` - A Vec containing a tuple of (war id, war)
+        #[ink(message)]
+        pub fn get_guild_wars(&self, guild: u32) -> Vec<(u32, War)> {
+            self.new_omega_war.get_wars(guild)
+        }
+
+        /// Gets the commanders the caller fights with (one equipped token per commander),
+        /// with their XP, level and selected skills.
+        ///
+        /// # Returns
+        ///
+        /// * `commanders` - A Vec containing a tuple of (commander id, commander data, commander level)
+        #[ink(message)]
+        pub fn get_commanders(&self) -> Vec<(u8, CommanderData, u8)> {
+            self.new_omega_storage.get_commanders(self.env().caller())
+        }
+
+        /// Selects a skill from the skill tree of a commander of the caller. Commanders get a skill point
+        /// every 5 levels, and skills need a minimum level and possibly another skill selected first.
+        ///
+        /// # Arguments
+        ///
+        /// * `commander` - Id of the commander
+        /// * `skill` - Id of the skill in the skill tree of the commander (see `get_commander_definitions`)
+        #[ink(message)]
+        pub fn select_commander_skill(&mut self, commander: u8, skill: u8) {
+            let caller: AccountId = self.env().caller();
+            self.new_omega_storage.select_commander_skill(caller, commander, skill);
+        }
+
+        /// Picks the commander token the caller fights with, among the instances of a commander they own.
+        ///
+        /// # Arguments
+        ///
+        /// * `token` - Id of the commander token
+        #[ink(message)]
+        pub fn equip_commander(&mut self, token: u32) {
+            let caller: AccountId = self.env().caller();
+            self.new_omega_storage.equip_commander(caller, token);
+        }
+
+        /// Gets the owner of a commander token (PSP34).
+        ///
+        /// # Arguments
+        ///
+        /// * `token` - Id of the commander token
+        ///
+        /// # Returns
+        ///
+        /// * `owner` - The account id of the owner, None if the token does not exist
+        #[ink(message)]
+        pub fn owner_of(&self, token: u32) -> Option<AccountId> {
+            self.new_omega_storage.owner_of(token)
+        }
+
+        /// Gets the number of commander tokens owned by an account (PSP34).
+        ///
+        /// # Arguments
+        ///
+        /// * `owner` - The account id of the owner
+        ///
+        /// # Returns
+        ///
+        /// * `balance` - The number of tokens owned
+        #[ink(message)]
+        pub fn balance_of(&self, owner: AccountId) -> u32 {
+            self.new_omega_storage.balance_of(owner)
+        }
+
+        /// Gets the account approved to transfer a commander token (PSP34).
+        ///
+        /// # Arguments
+        ///
+        /// * `token` - Id of the commander token
+        ///
+        /// # Returns
+        ///
+        /// * `spender` - The approved account id, None if there is none
+        #[ink(message)]
+        pub fn get_approved(&self, token: u32) -> Option<AccountId> {
+            self.new_omega_storage.get_approved(token)
+        }
+
+        /// Gets a commander token, with its metadata (commander id, owner, XP, level and skills).
+        ///
+        /// # Arguments
+        ///
+        /// * `token` - Id of the commander token
+        ///
+        /// # Returns
+        ///
+        /// * `commander_token` - The commander token, None if it does not exist
+        #[ink(message)]
+        pub fn get_commander_token(&self, token: u32) -> Option<CommanderToken> {
+            self.new_omega_storage.get_commander_token(token)
+        }
+
+        /// Gets all the commander tokens owned by an account.
+        ///
+        /// # Arguments
+        ///
+        /// * `owner` - The account id of the owner
+        ///
+        /// # Returns
+        ///
+        /// * `tokens` - A Vec containing a tuple of (token id, commander token)
+        #[ink(message)]
+        pub fn get_commander_tokens(&self, owner: AccountId) -> Vec<(u32, CommanderToken)> {
+            self.new_omega_storage.get_commander_tokens(owner)
+        }
+
+        /// Approves an account to transfer a commander token of the caller (PSP34).
+        ///
+        /// # Arguments
+        ///
+        /// * `spender` - The account id to approve, None to clear the approval
+        /// * `token` - Id of the commander token
+        #[ink(message)]
+        pub fn approve_commander(&mut self, spender: Option<AccountId>, token: u32) {
+            let caller: AccountId = self.env().caller();
+            self.new_omega_storage.approve(caller, spender, token);
+        }
+
+        /// Transfers a commander token, along with its progress (PSP34).
+        /// The caller has to own the token, or be approved for it.
+        ///
+        /// # Arguments
+        ///
+        /// * `to` - The account id of the receiver
+        /// * `token` - Id of the commander token
+        #[ink(message)]
+        pub fn transfer_commander(&mut self, to: AccountId, token: u32) {
+            let caller: AccountId = self.env().caller();
+            self.new_omega_storage.transfer(caller, to, token);
+        }
+
+        /// Lists a commander token of the caller on the marketplace, at a fixed price.
+        /// The token is held in escrow by the marketplace until sold, or the listing is cancelled.
+        ///
+        /// # Arguments
+        ///
+        /// * `token` - Id of the commander token
+        /// * `price` - Fixed price the token can be bought for
+        ///
+        /// # Returns
+        ///
+        /// * `id` - Id of the created listing
+        ///
+        /// # Events
+        ///
+        /// * ListingCreated - when the token is listed
+        #[ink(message)]
+        pub fn create_listing(&mut self, token: u32, price: Balance) -> u32 {
+            let caller: AccountId = self.env().caller();
+            let id: u32 = self.new_omega_marketplace.create_listing(caller, token, price);
+
+            self.env().emit_event(ListingCreated {
+                listing: id,
+                seller: caller,
+                token,
+                price,
+            });
+
+            id
+        }
+
+        /// Buys a listed commander token at its fixed price, which has to be the transferred amount.
+        /// The seller is paid the price minus the marketplace fee, which goes to the Delegator owner,
+        /// and the highest bid, if any, is refunded.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the listing
+        ///
+        /// # Events
+        ///
+        /// * ListingSold - when the token is sold
+        #[ink(message, payable)]
+        pub fn buy_listing(&mut self, id: u32) {
+            let caller: AccountId = self.env().caller();
+            let price: Balance = self.env().transferred_balance();
+            let (payouts, fee) = self.new_omega_marketplace.buy(caller, id, price);
+            self.hold_escrow(price);
+            self.pay_out_sale(payouts, fee);
+
+            self.env().emit_event(ListingSold {
+                listing: id,
+                buyer: caller,
+                price,
+                fee,
+            });
+        }
+
+        /// Bids on a listed commander token, with the transferred amount held in escrow.
+        /// The bid has to top the highest bid, which is refunded, and stay below the price.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the listing
+        ///
+        /// # Events
+        ///
+        /// * BidPlaced - when the bid is placed
+        #[ink(message, payable)]
+        pub fn place_bid(&mut self, id: u32) {
+            let caller: AccountId = self.env().caller();
+            let amount: Balance = self.env().transferred_balance();
+            self.hold_escrow(amount);
+            match self.new_omega_marketplace.place_bid(caller, id, amount) {
+                Some((bidder, refund)) => {
+                    self.release_escrow(bidder, refund);
+                },
+                _ => (),
+            }
+
+            self.env().emit_event(BidPlaced {
+                listing: id,
+                bidder: caller,
+                amount,
+            });
+        }
+
+        /// Withdraws the highest bid of the caller on a listing, and refunds it.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the listing
+        ///
+        /// # Events
+        ///
+        /// * BidCancelled - when the bid is withdrawn
+        #[ink(message)]
+        pub fn cancel_bid(&mut self, id: u32) {
+            let caller: AccountId = self.env().caller();
+            let (bidder, refund) = self.new_omega_marketplace.cancel_bid(caller, id);
+            self.release_escrow(bidder, refund);
+
+            self.env().emit_event(BidCancelled {
+                listing: id,
+                bidder,
+            });
+        }
+
+        /// Accepts the highest bid on a listing of the caller, selling the token to the bidder.
+        /// The caller is paid the bid minus the marketplace fee, which goes to the Delegator owner.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the listing
+        ///
+        /// # Events
+        ///
+        /// * ListingSold - when the token is sold
+        #[ink(message)]
+        pub fn accept_bid(&mut self, id: u32) {
+            let caller: AccountId = self.env().caller();
+            let (payouts, fee) = self.new_omega_marketplace.accept_bid(caller, id);
+            self.pay_out_sale(payouts, fee);
+
+            let listing: Listing = self.new_omega_marketplace.get_listing(id);
+            self.env().emit_event(ListingSold {
+                listing: id,
+                buyer: listing.buyer.unwrap(),
+                price: listing.sold_for,
+                fee,
+            });
+        }
+
+        /// Cancels a listing of the caller, returning the token and refunding the highest bid.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the listing
+        ///
+        /// # Events
+        ///
+        /// * ListingCancelled - when the listing is cancelled
+        #[ink(message)]
+        pub fn cancel_listing(&mut self, id: u32) {
+            let caller: AccountId = self.env().caller();
+            let refunds: Vec<(AccountId, Balance)> = self.new_omega_marketplace.cancel_listing(caller, id);
+            for (bidder, refund) in refunds.into_iter() {
+                self.release_escrow(bidder, refund);
+            }
+
+            self.env().emit_event(ListingCancelled {
+                listing: id,
+            });
+        }
+
+        /// Pays out a marketplace sale: the seller (and refunded bidder), and the fee to the Delegator owner.
+        ///
+        /// # Arguments
+        ///
+        /// * `payouts` - A Vec containing a tuple of (player account id, amount to pay out)
+        /// * `fee` - Marketplace fee
+        fn pay_out_sale(&mut self, payouts: Vec<(AccountId, Balance)>, fee: Balance) {
+            for (account, amount) in sale_transfers(payouts, fee, self.owner).into_iter() {
+                self.release_escrow(account, amount);
+            }
+        }
+
+        /// Gets a marketplace listing.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the listing
+        ///
+        /// # Returns
+        ///
+        /// * `listing` - The listing
+        #[ink(message)]
+        pub fn get_listing(&self, id: u32) -> Listing {
+            self.new_omega_marketplace.get_listing(id)
+        }
+
+        /// Gets all the active marketplace listings.
+        ///
+        /// # Returns
+        ///
+        /// * `listings` - A Vec containing a tuple of (listing id, listing)
+        #[ink(message)]
+        pub fn get_listings(&self) -> Vec<(u32, Listing)> {
+            self.new_omega_marketplace.get_listings()
+        }
+
+        /// Gets all the marketplace listings of a player, including the closed ones.
+        ///
+        /// # Arguments
+        ///
+        /// * `seller` - The account id of the seller
+        ///
+        /// # Returns
+        ///
+        /// * `listings` - A Vec containing a tuple of (listing id, listing)
+        #[ink(message)]
+        pub fn get_player_listings(&self, seller: AccountId) -> Vec<(u32, Listing)> {
+            self.new_omega_marketplace.get_player_listings(seller)
+        }
+
+        /// Sets the marketplace fee, kept from each sale and paid to the Delegator owner.
+        ///
+        /// # Arguments
+        ///
+        /// * `fee` - Marketplace fee, in percent
+        #[ink(message)]
+        pub fn set_marketplace_fee(&mut self, fee: u8) {
+            assert_eq!(self.env().caller(), self.owner);
+            self.new_omega_marketplace.set_fee(fee);
+        }
+
+        /// Gets the marketplace fee, kept from each sale and paid to the Delegator owner.
+        ///
+        /// # Returns
+        ///
+        /// * `fee` - Marketplace fee, in percent
+        #[ink(message)]
+        pub fn get_marketplace_fee(&self) -> u8 {
+            self.new_omega_marketplace.get_fee()
+        }
+
+        /// Generates a loot crate for the caller. The transferred amount has to cover the price of the crate type,
+        /// any excess is refunded.
+        ///
+        /// # Arguments
+        ///
+        /// * `crate_type` - Id of the crate type
+        ///
+        /// # Returns
+        ///
+        /// * `reward` - The reward received from the loot crate
+        #[ink(message, payable)]
+        pub fn buy_loot_crate(&mut self, crate_type: u32) -> LootReward {
+            self.open_loot_crates(crate_type, 1)[0]
+        }
+
+        /// Generates several loot crates of the same type for the caller, with a single payment.
+        /// The transferred amount has to cover the price of all the crates, any excess is refunded.
+        ///
+        /// # Arguments
+        ///
+        /// * `crate_type` - Id of the crate type
+        /// * `count` - Number of crates, up to 10
+        ///
+        /// # Returns
+        ///
+        /// * `rewards` - The rewards received from the loot crates, in order
+        #[ink(message, payable)]
+        pub fn buy_loot_crates(&mut self, crate_type: u32, count: u32) -> Vec<LootReward> {
+            self.open_loot_crates(crate_type, count)
+        }
+
+        /// Takes the payment for loot crates, refunding any excess, and pays out the funds rewards.
+        ///
+        /// # Arguments
+        ///
+        /// * `crate_type` - Id of the crate type
+        /// * `count` - Number of crates
+        ///
+        /// # Returns
+        ///
+        /// * `rewards` - The rewards received from the loot crates, in order
+        fn open_loot_crates(&mut self, crate_type: u32, count: u32) -> Vec<LootReward> {
+            let price: Balance = self.new_omega_rewarder.get_crate_type(crate_type).price;
+            let payment: Balance = self.env().transferred_balance();
+            let caller: AccountId = self.env().caller();
+            let rewards: Vec<LootReward> = self.new_omega_rewarder.buy_loot_crates(caller, crate_type, count);
+
+            let payout: Balance = loot_payout(price, count, payment, &rewards);
+            assert!(payout <= self.available_balance());
+            if payout > 0 {
+                assert!(self.env().transfer(caller, payout).is_ok());
+            }
+
+            rewards
+        }
+
+        /// Adds a new loot crate type, with its price and drop table.
+        ///
+        /// # Arguments
+        ///
+        /// * `crate_type` - The crate type
+        ///
+        /// # Returns
+        ///
+        /// * `id` - Id of the added crate type
+        #[ink(message)]
+        pub fn add_crate_type(&mut self, crate_type: CrateType) -> u32 {
+            assert_eq!(self.env().caller(), self.owner);
+            self.new_omega_rewarder.add_crate_type(crate_type)
+        }
+
+        /// Replaces a loot crate type, e.g. to change its price or drop table, or disable it.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the crate type
+        /// * `crate_type` - The crate type
+        #[ink(message)]
+        pub fn update_crate_type(&mut self, id: u32, crate_type: CrateType) {
+            assert_eq!(self.env().caller(), self.owner);
+            self.new_omega_rewarder.update_crate_type(id, crate_type);
+        }
+
+        /// Gets all the loot crate types, with their prices and drop tables.
+        ///
+        /// # Returns
+        ///
+        /// * `crate_types` - A Vec containing a tuple of (crate type id, crate type)
+        #[ink(message)]
+        pub fn get_crate_types(&self) -> Vec<(u32, CrateType)> {
+            self.new_omega_rewarder.get_crate_types()
+        }
+
+        /// Gets the drop rates of a loot crate type.
+        ///
+        /// # Arguments
+        ///
+        /// * `crate_type` - Id of the crate type
+        ///
+        /// # Returns
+        ///
+        /// * `rates` - A Vec containing a tuple of (reward, drop rate in basis points)
+        #[ink(message)]
+        pub fn get_drop_rates(&self, crate_type: u32) -> Vec<(LootReward, u32)> {
+            self.new_omega_rewarder.get_drop_rates(crate_type)
+        }
+
+        /// Sets the number of loot crates of a type within which a commander of the top rarity
+        /// of the crate is guaranteed.
+        ///
+        /// # Arguments
+        ///
+        /// * `threshold` - Number of crates
+        #[ink(message)]
+        pub fn set_pity_threshold(&mut self, threshold: u32) {
+            assert_eq!(self.env().caller(), self.owner);
+            self.new_omega_rewarder.set_pity_threshold(threshold);
+        }
+
+        /// Gets the number of loot crates of a type within which a commander of the top rarity
+        /// of the crate is guaranteed.
+        ///
+        /// # Returns
+        ///
+        /// * `threshold` - Number of crates
+        #[ink(message)]
+        pub fn get_pity_threshold(&self) -> u32 {
+            self.new_omega_rewarder.get_pity_threshold()
+        }
+
+        /// Gets the number of loot crates of a type the caller opened since getting a commander of its top rarity.
+        ///
+        /// # Arguments
+        ///
+        /// * `crate_type` - Id of the crate type
+        ///
+        /// # Returns
+        ///
+        /// * `crates` - The number of crates
+        #[ink(message)]
+        pub fn get_loot_pity(&self, crate_type: u32) -> u32 {
+            self.new_omega_storage.get_loot_pity(self.env().caller(), crate_type)
+        }
+
+        /// Gets the commander shards of the caller, received for duplicate commanders from loot crates.
+        ///
+        /// # Returns
+        ///
+        /// * `shards` - The amount of shards
+        #[ink(message)]
+        pub fn get_commander_shards(&self) -> u32 {
+            self.new_omega_storage.get_commander_shards(self.env().caller())
+        }
+
+        /// Spends commander shards of the caller on a chosen commander, received as a new commander token.
+        /// The amount of shards needed depends on the rarity of the commander.
+        ///
+        /// # Arguments
+        ///
+        /// * `commander` - Id of the chosen commander
+        ///
+        /// # Returns
+        ///
+        /// * `token` - Id of the received commander token
+        #[ink(message)]
+        pub fn redeem_commander_shards(&mut self, commander: u8) -> u32 {
+            let caller: AccountId = self.env().caller();
+            self.new_omega_storage.redeem_commander_shards(caller, commander)
+        }
+
+        /// Creates a tournament, open for signups.
+        ///
+        /// # Arguments
+        ///
+        /// * `format` - Bracket format (single elimination or Swiss)
+        /// * `entry_fee` - Entry fee, paid at signup
+        /// * `max_entries` - Maximum number of entries
+        /// * `prize_shares` - Percentages of the prize pool paid out, by final standing
+        ///
+        /// # Returns
+        ///
+        /// * `id` - Id of the created tournament
+        #[ink(message)]
+        pub fn create_tournament(&mut self, format: TournamentFormat, entry_fee: Balance,
+            max_entries: u8, prize_shares: Vec<u8>) -> u32 {
+
+            assert_eq!(self.env().caller(), self.owner);
+            self.new_omega_tournament.create_tournament(format, entry_fee, max_entries, prize_shares)
+        }
+
+        /// Signs the caller up for a tournament, paying the entry fee.
+        /// The fleet, and the current level and skills of its commander, are locked for the whole tournament.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the tournament
+        /// * `selection` - Fleet composition (array with ship quantities)
+        /// * `variants` - An array that holds variants of the fleet
+        /// * `commander` - Index of the commander leading the fleet
+        /// * `reserve` - The reserve of the fleet
+        #[ink(message, payable)]
+        pub fn join_tournament(&mut self, id: u32, selection: [u8; MAX_SHIPS],
+            variants: [u8; MAX_SHIPS], commander: u8, reserve: Reserve) {
+
+            let tournament: Tournament = self.new_omega_tournament.get_tournament(id);
+            assert_eq!(self.env().transferred_balance(), tournament.entry_fee);
+            self.hold_escrow(tournament.entry_fee);
+
+            let caller: AccountId = self.env().caller();
+            let commander_level: u8 = self.new_omega_storage.get_commander_level(caller, commander);
+            let commander_skills: u32 = self.new_omega_storage.get_commander_skills(caller, commander);
+            self.new_omega_tournament.sign_up(id, caller, selection, variants, commander, commander_level,
+                commander_skills, reserve);
+        }
+
+        /// Closes the signups and starts a tournament.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the tournament
+        #[ink(message)]
+        pub fn start_tournament(&mut self, id: u32) {
+            assert_eq!(self.env().caller(), self.owner);
+            self.new_omega_tournament.start_tournament(id);
+        }
+
+        /// Plays the next round of a tournament, and pays out the prizes after the last round.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the tournament
+        ///
+        /// # Events
+        ///
+        /// * TournamentRoundComplete - when the round is played
+        #[ink(message)]
+        pub fn play_tournament_round(&mut self, id: u32) {
+            assert_eq!(self.env().caller(), self.owner);
+
+            let (matches, payouts) = self.new_omega_tournament.play_round(id);
+            for (player, prize) in payouts.into_iter() {
+                self.release_escrow(player, prize);
+            }
+
+            self.env().emit_event(TournamentRoundComplete {
+                tournament: id,
+                matches,
+            });
+        }
+
+        /// Cancels a tournament during signup, and refunds the entry fees.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the tournament
+        #[ink(message)]
+        pub fn cancel_tournament(&mut self, id: u32) {
+            assert_eq!(self.env().caller(), self.owner);
+
+            let refunds: Vec<(AccountId, Balance)> = self.new_omega_tournament.cancel_tournament(id);
+            for (player, entry_fee) in refunds.into_iter() {
+                self.release_escrow(player, entry_fee);
+            }
+        }
+
+        /// Gets a tournament, with its entries, matches and standings.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the tournament
+        ///
+        /// # Returns
+        ///
+        /// * `tournament` - The tournament
+        #[ink(message)]
+        pub fn get_tournament(&self, id: u32) -> Tournament {
+            self.new_omega_tournament.get_tournament(id)
+        }
+
+        /// Gets all the tournaments, with their ids.
+        ///
+        /// # Returns
+        ///
+        /// * `tournaments` - A Vec containing a tuple of (tournament id, tournament)
+        #[ink(message)]
+        pub fn get_tournaments(&self) -> Vec<(u32, Tournament)> {
+            self.new_omega_tournament.get_tournaments()
+        }
+
+        /// Computes the commitment to a fleet, for challenges.
+        /// Only to be called via RPC, never in a transaction, as that would publish the salt.
+        ///
+        /// # Arguments
+        ///
+        /// * `selection` - Fleet composition (array with ship quantities)
+        /// * `variants` - An array that holds variants of the fleet
+        /// * `commander` - Index of the commander leading the fleet
+        /// * `reserve` - The reserve of the fleet
+        /// * `salt` - Secret salt, random
+        ///
+        /// # Returns
+        ///
+        /// * `commitment` - The fleet commitment
+        #[ink(message)]
+        pub fn commit_fleet(&self, selection: [u8; MAX_SHIPS], variants: [u8; MAX_SHIPS], commander: u8,
+            reserve: Reserve, salt: [u8; 32]) -> [u8; 32] {
+
+            newomega::commit_fleet(selection, variants, commander, reserve, salt)
+        }
+
+        /// Challenges another player to a fight, with a wager held in escrow until the challenge
+        /// is resolved or cancelled. The wager is the transferred amount, and can be zero.
+        /// The fleet stays hidden until both players have committed to their fleets.
+        ///
+        /// # Arguments
+        ///
+        /// * `opponent` - Account id of the challenged player
+        /// * `commitment` - Fleet commitment (see `commit_fleet`)
+        ///
+        /// # Returns
+        ///
+        /// * `id` - Id of the created challenge
+        ///
+        /// # Events
+        ///
+        /// * ChallengeCreated - when the challenge is created
+        #[ink(message, payable)]
+        pub fn challenge(&mut self, opponent: AccountId, commitment: [u8; 32]) -> u32 {
+            let caller: AccountId = self.env().caller();
+            let wager: Balance = self.env().transferred_balance();
+            let commanders: Vec<(u8, u8, u32)> = self.new_omega_storage.get_commander_loadouts(caller);
+            let id: u32 = self.new_omega_challenge.create_challenge(caller, opponent, commitment, wager, commanders);
+            self.hold_escrow(wager);
+
+            self.env().emit_event(ChallengeCreated {
+                challenge: id,
+                challenger: caller,
+                opponent,
+                wager,
+            });
+
+            id
+        }
+
+        /// Accepts a challenge, matching the wager and committing to a fleet.
+        /// Both players then have to reveal their fleets before the deadline.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the challenge
+        /// * `commitment` - Fleet commitment (see `commit_fleet`)
+        #[ink(message, payable)]
+        pub fn accept_challenge(&mut self, id: u32, commitment: [u8; 32]) {
+            let challenge: Challenge = self.new_omega_challenge.get_challenge(id);
+            assert_eq!(self.env().transferred_balance(), challenge.wager);
+            self.hold_escrow(challenge.wager);
+
+            let caller: AccountId = self.env().caller();
+            let commanders: Vec<(u8, u8, u32)> = self.new_omega_storage.get_commander_loadouts(caller);
+            self.new_omega_challenge.accept_challenge(caller, id, commitment, commanders);
+        }
+
+        /// Reveals the fleet committed to in a challenge. Once both fleets are revealed, the fight
+        /// is resolved: the winner receives both wagers, a draw returns them.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the challenge
+        /// * `selection` - Fleet composition (array with ship quantities)
+        /// * `variants` - An array that holds variants of the fleet
+        /// * `commander` - Index of the commander leading the fleet, fighting at its level and skills as of the commitment
+        /// * `reserve` - The reserve of the fleet
+        /// * `salt` - Salt the fleet was committed with
+        ///
+        /// # Events
+        ///
+        /// * ChallengeResolved - when the fight is over
+        #[ink(message)]
+        pub fn reveal_fleet(&mut self, id: u32, selection: [u8; MAX_SHIPS], variants: [u8; MAX_SHIPS],
+            commander: u8, reserve: Reserve, salt: [u8; 32]) {
+
+            let caller: AccountId = self.env().caller();
+            match self.new_omega_challenge.reveal_fleet(caller, id, selection, variants, commander, reserve, salt) {
+                Some((result, winner, payouts)) => {
+                    for (player, amount) in payouts.into_iter() {
+                        self.release_escrow(player, amount);
+                    }
+
+                    self.env().emit_event(ChallengeResolved {
+                        challenge: id,
+                        winner,
+                        result: Some(result),
+                    });
+                },
+                _ => (),
+            }
+        }
+
+        /// Settles a challenge in which a fleet was not revealed before the deadline.
+        /// The player who failed to reveal forfeits both wagers; if neither fleet was revealed,
+        /// the wagers are returned. Anybody can settle a challenge past its deadline.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the challenge
+        ///
+        /// # Events
+        ///
+        /// * ChallengeResolved - when the challenge is settled
+        #[ink(message)]
+        pub fn claim_forfeit(&mut self, id: u32) {
+            let (winner, payouts) = self.new_omega_challenge.claim_forfeit(id);
+            for (player, amount) in payouts.into_iter() {
+                self.release_escrow(player, amount);
+            }
+
+            self.env().emit_event(ChallengeResolved {
+                challenge: id,
+                winner,
+                result: None,
+            });
+        }
+
+        /// Cancels a challenge which has not been accepted, and refunds the wager. The challenger can withdraw and the opponent
+        /// can decline at any time, anybody can cancel an expired challenge.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the challenge
+        #[ink(message)]
+        pub fn cancel_challenge(&mut self, id: u32) {
+            let caller: AccountId = self.env().caller();
+            let refunds: Vec<(AccountId, Balance)> = self.new_omega_challenge.cancel_challenge(caller, id);
+            for (player, wager) in refunds.into_iter() {
+                self.release_escrow(player, wager);
+            }
+        }
+
+        /// Sets the number of blocks a challenge stays open for, and the time to reveal the fleets once accepted.
+        ///
+        /// # Arguments
+        ///
+        /// * `timeout` - Challenge timeout, in blocks
+        #[ink(message)]
+        pub fn set_challenge_timeout(&mut self, timeout: BlockNumber) {
+            assert_eq!(self.env().caller(), self.owner);
+            self.new_omega_challenge.set_challenge_timeout(timeout);
+        }
+
+        /// Gets the number of blocks a challenge stays open for, and the time to reveal the fleets once accepted.
+        ///
+        /// # Returns
+        ///
+        /// * `timeout` - Challenge timeout, in blocks
+        #[ink(message)]
+        pub fn get_challenge_timeout(&self) -> BlockNumber {
+            self.new_omega_challenge.get_challenge_timeout()
+        }
+
+        /// Gets a challenge.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the challenge
+        ///
+        /// # Returns
+        ///
+        /// * `challenge` - The challenge
+        #[ink(message)]
+        pub fn get_challenge(&self, id: u32) -> Challenge {
+            self.new_omega_challenge.get_challenge(id)
+        }
+
+        /// Gets all the challenges a player took part in.
+        ///
+        /// # Arguments
+        ///
+        /// * `player` - Account id of the player
+        ///
+        /// # Returns
+        ///
+        /// * `challenges` - A Vec containing a tuple of (challenge id, challenge)
+        #[ink(message)]
+        pub fn get_challenges(&self, player: AccountId) -> Vec<(u32, Challenge)> {
+            self.new_omega_challenge.get_challenges(player)
+        }
+
+        /// Sets the length of a ranked season, after which anyone can close it.
+        /// A length of 0 means seasons are only closed by the owner.
+        ///
+        /// # Arguments
+        ///
+        /// * `season_length` - Length of a season, in blocks
+        #[ink(message)]
+        pub fn set_season_length(&mut self, season_length: BlockNumber) {
+            assert_eq!(self.env().caller(), self.owner);
+            self.new_omega_storage.set_season_length(season_length);
+        }
+
+        /// Sets the rewards paid out to the best players when a season closes.
+        ///
+        /// # Arguments
+        ///
+        /// * `rewards` - Rewards, indexed by final leaderboard position
+        #[ink(message)]
+        pub fn set_season_rewards(&mut self, rewards: Vec<Balance>) {
+            assert_eq!(self.env().caller(), self.owner);
+            self.season_rewards = rewards;
+        }
+
+        /// Gets the rewards paid out to the best players when a season closes.
+        ///
+        /// # Returns
+        ///
+        /// * `rewards` - Rewards, indexed by final leaderboard position
+        #[ink(message)]
+        pub fn get_season_rewards(&self) -> Vec<Balance> {
+            self.season_rewards.clone()
+        }
+
+        /// Adds the transferred amount to the pool the season rewards are paid from.
+        #[ink(message, payable)]
+        pub fn fund_season_pool(&mut self) {
+            assert_eq!(self.env().caller(), self.owner);
+            self.season_pool += self.env().transferred_balance();
+        }
+
+        /// Gets the funds held in escrow for players: challenge wagers, marketplace bids,
+        /// tournament entry fees and war rewards.
+        ///
+        /// # Returns
+        ///
+        /// * `escrowed` - The funds held in escrow
+        #[ink(message)]
+        pub fn get_escrowed_funds(&self) -> Balance {
+            self.escrowed
+        }
+
+        /// Gets the funds left in the pool the season rewards are paid from.
+        ///
+        /// # Returns
+        ///
+        /// * `pool` - The funds left for the season rewards
+        #[ink(message)]
+        pub fn get_season_pool(&self) -> Balance {
+            self.season_pool
+        }
+
+        /// Gets the current season number, and the block it started in.
+        ///
+        /// # Returns
+        ///
+        /// * `season` - The current season number
+        /// * `started` - The block the current season started in
+        #[ink(message)]
+        pub fn get_season(&self) -> (u32, BlockNumber) {
+            self.new_omega_storage.get_season()
+        }
+
+        /// Gets an archived season record, with the final leaderboard.
+        ///
+        /// # Arguments
+        ///
+        /// * `season` - The season number
+        ///
+        /// # Returns
+        ///
+        /// * `record` - The archived season record
+        #[ink(message)]
+        pub fn get_season_record(&self, season: u32) -> SeasonRecord {
+            self.new_omega_storage.get_season_record(season)
+        }
+
+        /// Closes the current ranked season, and pays out the season rewards.
+        /// The owner can close a season at any time. Once the season is due, closing it is
+        /// deliberately permissionless, so the schedule does not depend on the owner: anyone
+        /// can trigger it, and the rewards always go to the archived final leaderboard.
+        /// Rewards are paid from the season pool, rewards which can't be paid out
+        /// (e.g. insufficient funds in the pool) are skipped.
+        ///
+        /// # Events
+        ///
+        /// * SeasonClosed - when the season is closed
+        #[ink(message)]
+        pub fn close_season(&mut self) {
+            assert!(self.env().caller() == self.owner || self.new_omega_storage.is_season_due());
+
+            let record: SeasonRecord = self.new_omega_ranked.close_season();
+            let mut rewards_paid: Balance = 0;
+
+            let funds: Balance = self.season_pool.min(self.env().balance().saturating_sub(self.escrowed));
+            for (player, reward) in season_payouts(&record, &self.season_rewards, funds) {
+                if self.env().transfer(player, reward).is_ok() {
+                    rewards_paid += reward;
+                }
+            }
+            self.season_pool -= rewards_paid;
+
+            self.env().emit_event(SeasonClosed {
+                season: record.season,
+                rewards_paid,
+            });
         }
 
-        /// Withdraws funds from the Rewarder contract to the Delegator contract owner
+        /// Withdraws funds from the Rewarder contract to the Delegator contract owner.
+        /// Funds held in escrow for players and the season pool can't be withdrawn.
         ///
         /// # Arguments
         ///
-        /// * `value` - Balance to withdraw. Panic if greater than available balance.
+        /// * `value` - Balance to withdraw. Fails if greater than available balance.
         #[ink(message)]
         pub fn admin_withdraw_funds(&mut self, value: Balance) -> Result<(), RewardWithdrawError> {
             assert_eq!(self.env().caller(), self.owner);
-            if value > self.env().balance() {
+            if value > self.available_balance() {
                 return Err(RewardWithdrawError::InsufficientFunds)
             }
             self.env()
@@ -5,10 +5,39 @@
 use ink_lang as ink;
 pub use self::newomega::NewOmega;
 pub use self::newomega::Ship;
+pub use self::newomega::Ability;
 pub use self::newomega::Move;
 pub use self::newomega::FightResult;
+pub use self::newomega::FightStats;
+pub use self::newomega::Morale;
+pub use self::newomega::Reserve;
+pub use self::newomega::Commander;
+pub use self::newomega::Skill;
+pub use self::newomega::SkillEffect;
+pub use self::newomega::Terrain;
+pub use self::newomega::Battlefield;
+pub use self::newomega::prepare_maps;
 pub use self::newomega::MAX_SHIPS;
+pub use self::newomega::DEFAULT_ACCURACY;
+pub use self::newomega::DEFAULT_EVASION;
+pub use self::newomega::DEFAULT_CRIT_CHANCE;
+pub use self::newomega::DEFAULT_CRIT_MULTIPLIER;
+pub use self::newomega::MAX_CRIT_MULTIPLIER;
 pub use self::newomega::prepare_ships;
+pub use self::newomega::prepare_commanders;
+pub use self::newomega::commander_level;
+pub use self::newomega::max_commander_level;
+pub use self::newomega::skill_points;
+pub use self::newomega::can_select_skill;
+pub use self::newomega::are_skills_valid;
+pub use self::newomega::is_reserve_valid;
+pub use self::newomega::hash_ships;
+pub use self::newomega::fight_hash;
+pub use self::newomega::commit_fleet;
+pub use self::newomega::encode_replay;
+pub use self::newomega::decode_replay;
+pub use self::newomega::Replay;
+pub use self::newomega::RULESET_VERSION;
 
 /// This contract has no storage, and all its methods are pure (stateless).
 /// It is able to simulate fights, given a set of input parameters,
@@ -25,10 +54,46 @@ mod newomega {
     pub struct NewOmega {}
 
     pub const MAX_SHIPS: usize = 4;
+    /// Version of the fight rules, to be bumped whenever the outcome of a fight changes
+    pub const RULESET_VERSION: u8 = 10;
     const MAX_ROUNDS: usize = 50;
     const FIT_TO_STAT: u16 = 20;
+    const REPLAY_FORMAT_VERSION: u8 = 6;
+    /// Maximum commander level, per rarity (common, rare, epic, legendary)
+    const MAX_COMMANDER_LEVELS: [u8; 4] = [10, 15, 20, 25];
+    /// Experience Points needed to reach level 2, every next level needs that much more than the previous one
+    const XP_PER_COMMANDER_LEVEL: u32 = 10;
+    /// Commander levels needed per skill point
+    const LEVELS_PER_SKILL_POINT: u8 = 5;
+    /// Maximum number of skills of a commander, selected skills are kept in a u32 bitmask
+    pub const MAX_COMMANDER_SKILLS: usize = 32;
+    /// Precision stats of a ship which always hits, with regular damage, as ships did before the stats existed
+    pub const DEFAULT_ACCURACY: u8 = 100;
+    pub const DEFAULT_EVASION: u8 = 0;
+    pub const DEFAULT_CRIT_CHANCE: u8 = 0;
+    pub const DEFAULT_CRIT_MULTIPLIER: u16 = 100;
+    /// Damage of a critical hit is capped at 10 times the regular damage
+    pub const MAX_CRIT_MULTIPLIER: u16 = 1000;
+
+    /// Move types, as logged in the fight moves
+    pub const MOVE_SHOOT: u8 = 1;
+    pub const MOVE_REPOSITION: u8 = 2;
+    pub const MOVE_SHIELD: u8 = 3;
+    pub const MOVE_SHIELD_REGENERATION: u8 = 4;
+    pub const MOVE_ARMOR_PIERCING: u8 = 5;
+    pub const MOVE_SPLASH: u8 = 6;
+    pub const MOVE_REPAIR: u8 = 7;
+    pub const MOVE_MISS: u8 = 8;
+    pub const MOVE_CRITICAL: u8 = 9;
+    pub const MOVE_ROUT: u8 = 10;
+    pub const MOVE_RETREAT: u8 = 11;
+    pub const MOVE_MINE: u8 = 12;
+    pub const MOVE_REINFORCE: u8 = 13;
 
     use ink_prelude::vec::Vec;
+    use ink_prelude::string::String;
+    use ink_env::hash::Blake2x256;
+    use scale::Encode;
     use ink_storage::{
         traits::{
             PackedLayout,
@@ -50,23 +115,47 @@ mod newomega {
         )
     )]
     pub struct Move {
-        /// Shoot, Reposition
+        /// Shoot, Reposition, or one of the abilities (see MOVE_* constants)
         move_type: u8,
         /// Round the move took place in
         round: u8,
         /// Source ship id
         source: u8,
-        /// Target ship id, in the case of shoot
+        /// Target ship id, in the case of shoot or ability
         target: u8,
         /// Position to move to, if needed
         target_position: i16,
-        /// Damage of the shot, if needed
+        /// Damage of the shot, or amount of the ability effect, if needed
         damage: u32
     }
 
+    /// Describes a special ability of a ship.
+    /// Amounts are given per single ship, and scale with the size of the stack.
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Copy, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            Debug,
+            PartialEq,
+            Eq,
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub enum Ability {
+        /// Shields absorb damage before the hull, and regenerate at the start of every round
+        Shield { capacity: u16, regeneration: u16 },
+        /// Shots ignore a percentage of the target defence
+        ArmorPiercing { percent: u8 },
+        /// Shots deal a percentage of their damage to the stacks adjacent to the target
+        Splash { percent: u8 },
+        /// Repairs the most damaged friendly stack at the start of every round
+        Repair { amount: u16 },
+    }
+
     /// Describes a single Ship on the board
     /// A move can be pure reposition, shoot, or reposition with shoot.
-    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Copy, Clone)]
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Clone)]
     #[cfg_attr(
         feature = "std",
         derive(
@@ -91,7 +180,195 @@ mod newomega {
         /// Speed, number of fields the ship can move in a round
         pub speed: u8,
         /// Range, number of fields in front of it the ship can shoot to in a round
-        pub range: u8
+        pub range: u8,
+        /// Special abilities of the ship
+        pub abilities: Vec<Ability>,
+        /// Chance to hit, in percent (100 always hits an unevasive target)
+        pub accuracy: u8,
+        /// Chance to evade, in percent, subtracted from the accuracy of the shooter
+        pub evasion: u8,
+        /// Chance of a critical hit, in percent
+        pub crit_chance: u8,
+        /// Damage of a critical hit, in percent of the regular damage
+        pub crit_multiplier: u16
+    }
+
+    /// Describes the morale rules of a fight. A threshold of 0 disables the rule.
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Copy, Clone, Default)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            Debug,
+            PartialEq,
+            Eq,
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub struct Morale {
+        /// Percentage of its starting HP a stack has to lose to rout (leave the battlefield)
+        pub rout_threshold: u8,
+        /// Percentage of its starting HP a fleet has to lose, destroyed or routed, to retreat
+        pub retreat_threshold: u8,
+    }
+
+    /// Describes a reserve group of a fleet, held back from the start of the fight.
+    /// The reserve enters the battle at the start of a chosen round, or as soon as the
+    /// front line falls below a percentage of its starting HP, whichever comes first.
+    /// A trigger of 0 is disabled, so a reserve with both triggers disabled never enters the battle.
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Copy, Clone, Default)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            Debug,
+            PartialEq,
+            Eq,
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub struct Reserve {
+        /// Reserve fleet composition (array with ship quantities)
+        pub selection: [u8; MAX_SHIPS],
+        /// Round at the start of which the reserve enters the battle
+        pub round: u8,
+        /// Percentage of its starting HP the front line has to fall below for the reserve to enter
+        pub threshold: u8,
+    }
+
+    /// Describes the effect of a commander skill
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Copy, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            Debug,
+            PartialEq,
+            Eq,
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub enum SkillEffect {
+        /// Increases the range of a ship type
+        Range { ship: u8, bonus: u8 },
+        /// Increases the evasion of the whole fleet in the first round, in percent
+        FirstRoundEvasion { bonus: u8 },
+        /// Increases the attack of the whole fleet against a ship type, in percent
+        BonusDamage { target: u8, percent: u8 },
+    }
+
+    /// Describes a commander skill, which a player can select once the commander is levelled up enough
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Copy, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            Debug,
+            PartialEq,
+            Eq,
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub struct Skill {
+        /// Commander level required to select the skill
+        pub level: u8,
+        /// Index of the skill which has to be selected first, if any
+        pub requires: Option<u8>,
+        /// Effect of the skill
+        pub effect: SkillEffect,
+    }
+
+    /// Describes a commander leading a fleet. The bonuses are given per level of the commander,
+    /// and the level is capped according to the rarity of the commander.
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Clone, Default)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            Debug,
+            PartialEq,
+            Eq,
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub struct Commander {
+        /// Rarity of the commander (0=common, 1=rare, 2=epic, 3=legendary)
+        pub rarity: u8,
+        /// Attack bonus of the whole fleet, in percent per level
+        pub attack: u8,
+        /// Defence bonus of the whole fleet, in percent per level
+        pub defence: u8,
+        /// Skill tree of the commander, indexed by skill id
+        pub skills: Vec<Skill>,
+    }
+
+    /// Describes a terrain feature of a battlefield, spanning the positions from `from` to `to`
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Copy, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            Debug,
+            PartialEq,
+            Eq,
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub enum Terrain {
+        /// Ships starting their move inside the asteroid field have their speed reduced
+        Asteroids { from: i16, to: i16, slowdown: u8 },
+        /// Ships inside the nebula have their range reduced
+        Nebula { from: i16, to: i16, range_penalty: u8 },
+        /// Ships moving into or through the minefield take damage, per ship
+        Minefield { from: i16, to: i16, damage: u16 },
+    }
+
+    /// Describes a battlefield (map) a fight takes place on.
+    /// The attacker starts on the positive side of the board, the defender on the negative side.
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            Debug,
+            PartialEq,
+            Eq,
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub struct Battlefield {
+        /// Name of the map
+        pub name: String,
+        /// Terrain features of the map
+        pub terrain: Vec<Terrain>,
+    }
+
+    /// Aggregate statistics of one side of a fight.
+    /// Damage dealt and received only counts HP actually removed, excess damage is overkill.
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Copy, Clone, Default)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            Debug,
+            PartialEq,
+            Eq,
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub struct FightStats {
+        /// Damage dealt, per ship type
+        pub damage_dealt: [u32; MAX_SHIPS],
+        /// Damage received, per ship type
+        pub damage_received: [u32; MAX_SHIPS],
+        /// Shots fired (by whole stacks), per ship type
+        pub shots_fired: [u32; MAX_SHIPS],
+        /// Damage dealt in excess of the HP left in the targeted stacks
+        pub overkill_damage: u32,
+        /// Round in which the first enemy ship was destroyed, if any
+        pub first_blood_round: Option<u8>,
+        /// Ship type which dealt the most damage
+        pub most_valuable_ship: u8,
     }
 
     #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
@@ -118,10 +395,30 @@ mod newomega {
         commander_lhs: u8,
         /// Defender commander id
         commander_rhs: u8,
+        /// Attacker commander level
+        commander_level_lhs: u8,
+        /// Defender commander level
+        commander_level_rhs: u8,
+        /// Attacker commander skills (bitmask of skill ids)
+        commander_skills_lhs: u32,
+        /// Defender commander skills (bitmask of skill ids)
+        commander_skills_rhs: u32,
+        /// Attacker reserve
+        reserve_lhs: Reserve,
+        /// Defender reserve
+        reserve_rhs: Reserve,
         /// Did the attacker die?
         pub lhs_dead: bool,
         /// Did the defender die?
         pub rhs_dead: bool,
+        /// Did the attacker retreat?
+        pub lhs_retreated: bool,
+        /// Did the defender retreat?
+        pub rhs_retreated: bool,
+        /// Morale rules the fight was generated with
+        morale: Morale,
+        /// Id of the map the fight took place on
+        pub map: u8,
         /// Length of the fight in rounds
         rounds: u8,
         /// Random seed the fight was generated with
@@ -129,7 +426,13 @@ mod newomega {
         /// Attackers ships lost
         ships_lost_lhs: [u8; MAX_SHIPS],
         /// Defenders ships lost
-        ships_lost_rhs: [u8; MAX_SHIPS]
+        ships_lost_rhs: [u8; MAX_SHIPS],
+        /// Commitment hash over the ruleset, fight inputs and result
+        pub hash: [u8; 32],
+        /// Attacker fight statistics, if requested
+        pub stats_lhs: Option<FightStats>,
+        /// Defender fight statistics, if requested
+        pub stats_rhs: Option<FightStats>
     }
 
     pub fn prepare_ships() -> Vec<Ship> {
@@ -144,6 +447,11 @@ mod newomega {
             defence: 20,
             speed: 4,
             range: 4,
+            abilities: Vec::new(),
+            accuracy: DEFAULT_ACCURACY,
+            evasion: DEFAULT_EVASION,
+            crit_chance: DEFAULT_CRIT_CHANCE,
+            crit_multiplier: DEFAULT_CRIT_MULTIPLIER,
         });
         ships.push(Ship {
             cp: 3,
@@ -153,6 +461,11 @@ mod newomega {
             defence: 30,
             speed: 3,
             range: 8,
+            abilities: Vec::new(),
+            accuracy: DEFAULT_ACCURACY,
+            evasion: DEFAULT_EVASION,
+            crit_chance: DEFAULT_CRIT_CHANCE,
+            crit_multiplier: DEFAULT_CRIT_MULTIPLIER,
         });
         ships.push(Ship {
             cp: 4,
@@ -162,6 +475,11 @@ mod newomega {
             defence: 35,
             speed: 2,
             range: 15,
+            abilities: Vec::new(),
+            accuracy: DEFAULT_ACCURACY,
+            evasion: DEFAULT_EVASION,
+            crit_chance: DEFAULT_CRIT_CHANCE,
+            crit_multiplier: DEFAULT_CRIT_MULTIPLIER,
         });
         ships.push(Ship {
             cp: 10,
@@ -171,11 +489,623 @@ mod newomega {
             defence: 40,
             speed: 1,
             range: 30,
+            abilities: Vec::new(),
+            accuracy: DEFAULT_ACCURACY,
+            evasion: DEFAULT_EVASION,
+            crit_chance: DEFAULT_CRIT_CHANCE,
+            crit_multiplier: DEFAULT_CRIT_MULTIPLIER,
         });
 
         ships
     }
 
+    pub fn prepare_commanders() -> Vec<Commander> {
+        let mut commanders: Vec<Commander> = Vec::new();
+
+        // Initialize default commanders, ordered by rarity
+        commanders.push(Commander {
+            rarity: 0,
+            attack: 1,
+            defence: 0,
+            skills: [
+                Skill { level: 5, requires: None, effect: SkillEffect::Range { ship: 0, bonus: 2 } },
+                Skill { level: 5, requires: None, effect: SkillEffect::FirstRoundEvasion { bonus: 20 } },
+                Skill { level: 10, requires: Some(0), effect: SkillEffect::BonusDamage { target: 0, percent: 10 } },
+            ].to_vec(),
+        });
+        commanders.push(Commander {
+            rarity: 1,
+            attack: 0,
+            defence: 1,
+            skills: [
+                Skill { level: 5, requires: None, effect: SkillEffect::FirstRoundEvasion { bonus: 20 } },
+                Skill { level: 5, requires: None, effect: SkillEffect::BonusDamage { target: 1, percent: 10 } },
+                Skill { level: 10, requires: Some(0), effect: SkillEffect::FirstRoundEvasion { bonus: 20 } },
+                Skill { level: 15, requires: Some(1), effect: SkillEffect::BonusDamage { target: 2, percent: 15 } },
+            ].to_vec(),
+        });
+        commanders.push(Commander {
+            rarity: 2,
+            attack: 1,
+            defence: 1,
+            skills: [
+                Skill { level: 5, requires: None, effect: SkillEffect::Range { ship: 1, bonus: 2 } },
+                Skill { level: 5, requires: None, effect: SkillEffect::BonusDamage { target: 3, percent: 10 } },
+                Skill { level: 10, requires: Some(0), effect: SkillEffect::Range { ship: 2, bonus: 3 } },
+                Skill { level: 15, requires: Some(1), effect: SkillEffect::BonusDamage { target: 3, percent: 15 } },
+                Skill { level: 20, requires: None, effect: SkillEffect::FirstRoundEvasion { bonus: 25 } },
+            ].to_vec(),
+        });
+        commanders.push(Commander {
+            rarity: 3,
+            attack: 1,
+            defence: 1,
+            skills: [
+                Skill { level: 5, requires: None, effect: SkillEffect::Range { ship: 0, bonus: 2 } },
+                Skill { level: 5, requires: None, effect: SkillEffect::FirstRoundEvasion { bonus: 20 } },
+                Skill { level: 10, requires: Some(0), effect: SkillEffect::Range { ship: 3, bonus: 5 } },
+                Skill { level: 15, requires: Some(1), effect: SkillEffect::BonusDamage { target: 2, percent: 15 } },
+                Skill { level: 20, requires: None, effect: SkillEffect::BonusDamage { target: 3, percent: 20 } },
+                Skill { level: 25, requires: Some(2), effect: SkillEffect::Range { ship: 1, bonus: 3 } },
+            ].to_vec(),
+        });
+
+        commanders
+    }
+
+    /// Gets the maximum level a commander of a given rarity can reach
+    ///
+    /// # Arguments
+    ///
+    /// * `rarity` - Rarity of the commander
+    ///
+    /// # Returns
+    ///
+    /// * `max_level` - The maximum level, the highest one for unknown rarities
+    pub fn max_commander_level(rarity: u8) -> u8 {
+        let index: usize = if (rarity as usize) < MAX_COMMANDER_LEVELS.len() {
+            rarity as usize
+        } else {
+            MAX_COMMANDER_LEVELS.len() - 1
+        };
+
+        MAX_COMMANDER_LEVELS[index]
+    }
+
+    /// Calculates the level of a commander from its Experience Points.
+    /// An owned commander starts at level 1, and every level needs XP_PER_COMMANDER_LEVEL
+    /// more than the previous one (10 XP for level 2, 30 XP for level 3, 60 XP for level 4, and so on).
+    ///
+    /// # Arguments
+    ///
+    /// * `xp` - Experience Points of the commander
+    /// * `rarity` - Rarity of the commander, which caps the level
+    ///
+    /// # Returns
+    ///
+    /// * `level` - The level of the commander
+    pub fn commander_level(xp: u32, rarity: u8) -> u8 {
+        let max_level: u8 = max_commander_level(rarity);
+        let mut level: u8 = 1;
+        let mut threshold: u32 = XP_PER_COMMANDER_LEVEL;
+
+        while level < max_level && xp >= threshold {
+            level += 1;
+            threshold += XP_PER_COMMANDER_LEVEL * level as u32;
+        }
+
+        level
+    }
+
+    /// Gets the number of skills a commander of a given level can have selected
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - Level of the commander
+    ///
+    /// # Returns
+    ///
+    /// * `points` - The number of skill points, one every LEVELS_PER_SKILL_POINT levels
+    pub fn skill_points(level: u8) -> u8 {
+        level / LEVELS_PER_SKILL_POINT
+    }
+
+    /// Checks whether a skill can be added to the selected skills of a commander.
+    /// The skill has to be unlocked by the level, its prerequisite selected, and a skill point left.
+    ///
+    /// # Arguments
+    ///
+    /// * `commander` - The commander definition
+    /// * `level` - Level of the commander
+    /// * `selected` - Already selected skills (bitmask of skill ids)
+    /// * `skill` - Id of the skill to select
+    ///
+    /// # Returns
+    ///
+    /// * `can_select` - Whether the skill can be selected
+    pub fn can_select_skill(commander: &Commander, level: u8, selected: u32, skill: u8) -> bool {
+        let definition: &Skill = match commander.skills.get(skill as usize) {
+            Some(definition) if (skill as usize) < MAX_COMMANDER_SKILLS => definition,
+            _ => return false,
+        };
+        let prerequisite_selected: bool = match definition.requires {
+            Some(requires) => (requires as usize) < MAX_COMMANDER_SKILLS && selected & (1 << requires) != 0,
+            None => true,
+        };
+
+        selected & (1 << skill) == 0 &&
+            prerequisite_selected &&
+            level >= definition.level &&
+            selected.count_ones() < skill_points(level) as u32
+    }
+
+    /// Checks whether the selected skills of a commander could have been selected at its level (capped by
+    /// its rarity): every skill unlocked by the level and with its prerequisite selected, within the skill points.
+    ///
+    /// # Arguments
+    ///
+    /// * `commanders` - A Vec that holds the definition of all the commanders
+    /// * `commander` - Id of the commander
+    /// * `level` - Level of the commander
+    /// * `skills` - Selected skills (bitmask of skill ids)
+    ///
+    /// # Returns
+    ///
+    /// * `valid` - Whether the skills can be fought with, always true without skills
+    pub fn are_skills_valid(commanders: &Vec<Commander>, commander: u8, level: u8, skills: u32) -> bool {
+        if skills == 0 {
+            return true;
+        }
+        let definition: &Commander = match commanders.get(commander as usize) {
+            Some(definition) => definition,
+            None => return false,
+        };
+        let capped_level: u8 = level.min(max_commander_level(definition.rarity));
+        let defined: usize = definition.skills.len().min(MAX_COMMANDER_SKILLS);
+
+        skills.checked_shr(defined as u32).unwrap_or(0) == 0 &&
+            skills.count_ones() <= skill_points(capped_level) as u32 &&
+            definition.skills
+                .iter()
+                .take(defined)
+                .enumerate()
+                .filter(|&(id, _)| skills & (1 << id) != 0)
+                .all(|(_, skill)| capped_level >= skill.level && match skill.requires {
+                    Some(requires) => (requires as usize) < defined && skills & (1 << requires) != 0,
+                    None => true,
+                })
+    }
+
+    /// Checks whether a reserve fits a fleet. Once the reserve arrives, every stack
+    /// still has to be countable in a u8, as the fleet selection is.
+    ///
+    /// # Arguments
+    ///
+    /// * `selection` - Fleet composition (array with ship quantities)
+    /// * `reserve` - The reserve of the fleet
+    ///
+    /// # Returns
+    ///
+    /// * `valid` - Whether the fleet and its reserve can be fought with
+    pub fn is_reserve_valid(selection: [u8; MAX_SHIPS], reserve: Reserve) -> bool {
+        (0..MAX_SHIPS).all(|i| selection[i].checked_add(reserve.selection[i]).is_some())
+    }
+
+    pub fn prepare_maps() -> Vec<Battlefield> {
+        let mut maps: Vec<Battlefield> = Vec::new();
+
+        // Initialize default maps, the first one being the empty battlefield
+        maps.push(Battlefield {
+            name: String::from("Open Space"),
+            terrain: Vec::new(),
+        });
+        maps.push(Battlefield {
+            name: String::from("Asteroid Belt"),
+            terrain: [Terrain::Asteroids { from: -4, to: 4, slowdown: 1 }].to_vec(),
+        });
+        maps.push(Battlefield {
+            name: String::from("Nebula"),
+            terrain: [Terrain::Nebula { from: -6, to: 6, range_penalty: 2 }].to_vec(),
+        });
+        maps.push(Battlefield {
+            name: String::from("Minefield"),
+            terrain: [Terrain::Minefield { from: -2, to: 2, damage: 10 }].to_vec(),
+        });
+
+        maps
+    }
+
+    /// A fight replay, as decoded from the compact replay format
+    #[cfg_attr(feature = "std", derive(Debug, PartialEq, Eq))]
+    pub struct Replay {
+        /// Version of the fight rules the replay was generated with
+        pub ruleset_version: u8,
+        /// Hash of the ship definitions the replay was generated with
+        pub ships_hash: [u8; 32],
+        /// Fight inputs (seed, fleets, commanders) and result
+        pub result: FightResult,
+        /// Logged moves of the attacker
+        pub moves_lhs: Vec<Move>,
+        /// Logged moves of the defender
+        pub moves_rhs: Vec<Move>,
+    }
+
+    /// Checks whether a ship has the default precision stats, with which it fights
+    /// exactly as ships did before the stats were introduced.
+    ///
+    /// # Arguments
+    ///
+    /// * `ship` - The ship definition
+    ///
+    /// # Returns
+    ///
+    /// * `default` - Whether all the precision stats are the defaults
+    pub fn has_default_precision(ship: &Ship) -> bool {
+        ship.accuracy == DEFAULT_ACCURACY &&
+            ship.evasion == DEFAULT_EVASION &&
+            ship.crit_chance == DEFAULT_CRIT_CHANCE &&
+            ship.crit_multiplier == DEFAULT_CRIT_MULTIPLIER
+    }
+
+    /// Hashes the ship definitions, in order to identify the ruleset a fight was generated with.
+    /// The precision stats are only hashed for ships which do not have the defaults,
+    /// so ship definitions without them keep the hash they had before the stats were introduced.
+    ///
+    /// # Arguments
+    ///
+    /// * `ships` - A Vec that holds the definiton of all the ships
+    ///
+    /// # Returns
+    ///
+    /// * `hash` - Blake2x256 hash of the encoded ships
+    pub fn hash_ships(ships: &Vec<Ship>) -> [u8; 32] {
+        let mut encoded: Vec<u8> = Vec::new();
+        scale::Compact(ships.len() as u32).encode_to(&mut encoded);
+
+        for ship in ships.iter() {
+            (ship.cp, ship.hp, ship.attack_base, ship.attack_variable, ship.defence, ship.speed,
+                ship.range, &ship.abilities).encode_to(&mut encoded);
+
+            if !has_default_precision(ship) {
+                (ship.accuracy, ship.evasion, ship.crit_chance, ship.crit_multiplier).encode_to(&mut encoded);
+            }
+        }
+
+        let mut hash: [u8; 32] = [0; 32];
+        ink_env::hash_bytes::<Blake2x256>(&encoded, &mut hash);
+        hash
+    }
+
+    /// Commits to a fleet without revealing it, for simultaneous fleet submission (commit-reveal).
+    /// The salt keeps the small space of possible fleets from being searched for a matching commitment,
+    /// and should be random and kept secret until the reveal.
+    ///
+    /// # Arguments
+    ///
+    /// * `selection` - Fleet composition (array with ship quantities)
+    /// * `variants` - An array that holds variants of the fleet
+    /// * `commander` - Index of the commander leading the fleet
+    /// * `reserve` - The reserve of the fleet
+    /// * `salt` - Secret salt
+    ///
+    /// # Returns
+    ///
+    /// * `commitment` - Blake2x256 hash of the encoded fleet and salt
+    pub fn commit_fleet(selection: [u8; MAX_SHIPS], variants: [u8; MAX_SHIPS], commander: u8,
+        reserve: Reserve, salt: [u8; 32]) -> [u8; 32] {
+
+        let mut commitment: [u8; 32] = [0; 32];
+        ink_env::hash_encoded::<Blake2x256, _>(&(selection, variants, commander, reserve, salt), &mut commitment);
+        commitment
+    }
+
+    /// Writes an unsigned LEB128 varint
+    fn write_varint(output: &mut Vec<u8>, value: u64) {
+        let mut remaining: u64 = value;
+
+        loop {
+            let byte: u8 = (remaining & 0x7f) as u8;
+            remaining >>= 7;
+
+            if remaining == 0 {
+                output.push(byte);
+                break;
+            }
+
+            output.push(byte | 0x80);
+        }
+    }
+
+    /// Reads an unsigned LEB128 varint, advancing the offset. None if malformed.
+    fn read_varint(input: &[u8], offset: &mut usize) -> Option<u64> {
+        let mut value: u64 = 0;
+        let mut shift: u32 = 0;
+
+        loop {
+            let byte: u8 = read_byte(input, offset)?;
+
+            if shift >= 64 {
+                return None;
+            }
+
+            value |= ((byte & 0x7f) as u64) << shift;
+            shift += 7;
+
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+    }
+
+    /// Reads a single byte, advancing the offset. None if out of input.
+    fn read_byte(input: &[u8], offset: &mut usize) -> Option<u8> {
+        let byte: u8 = *input.get(*offset)?;
+        *offset += 1;
+        Some(byte)
+    }
+
+    /// Reads a fixed size byte array, advancing the offset. None if out of input.
+    fn read_array(input: &[u8], offset: &mut usize) -> Option<[u8; MAX_SHIPS]> {
+        let mut array: [u8; MAX_SHIPS] = [0; MAX_SHIPS];
+
+        for i in 0..MAX_SHIPS {
+            array[i] = read_byte(input, offset)?;
+        }
+
+        Some(array)
+    }
+
+    /// Encodes the moves of one side. Rounds are delta-encoded against the previous move,
+    /// positions against the previous position of the same ship, and the rest is varint packed.
+    fn write_moves(output: &mut Vec<u8>, moves: &Vec<Move>) {
+        let mut last_round: u8 = 0;
+        let mut last_positions: [i16; MAX_SHIPS] = [0; MAX_SHIPS];
+
+        write_varint(output, moves.len() as u64);
+
+        for m in moves.iter() {
+            let source_usize: usize = m.source as usize % MAX_SHIPS;
+            let position_delta: i16 = m.target_position.wrapping_sub(last_positions[source_usize]);
+            // Zigzag, so that small negative deltas stay small
+            let position_zigzag: u64 = ((position_delta << 1) ^ (position_delta >> 15)) as u16 as u64;
+
+            output.push(m.move_type);
+            output.push(m.round.wrapping_sub(last_round));
+            output.push(m.source);
+            output.push(m.target);
+            write_varint(output, position_zigzag);
+            write_varint(output, m.damage as u64);
+
+            last_round = m.round;
+            last_positions[source_usize] = m.target_position;
+        }
+    }
+
+    /// Decodes the moves of one side, reversing `write_moves`. None if malformed.
+    fn read_moves(input: &[u8], offset: &mut usize) -> Option<Vec<Move>> {
+        let mut moves: Vec<Move> = Vec::new();
+        let mut last_round: u8 = 0;
+        let mut last_positions: [i16; MAX_SHIPS] = [0; MAX_SHIPS];
+        let count: u64 = read_varint(input, offset)?;
+
+        for _ in 0..count {
+            let move_type: u8 = read_byte(input, offset)?;
+            let round: u8 = last_round.wrapping_add(read_byte(input, offset)?);
+            let source: u8 = read_byte(input, offset)?;
+            let target: u8 = read_byte(input, offset)?;
+            let position_zigzag: u64 = read_varint(input, offset)?;
+            let damage: u64 = read_varint(input, offset)?;
+
+            if position_zigzag > u16::MAX as u64 || damage > u32::MAX as u64 {
+                return None;
+            }
+
+            let source_usize: usize = source as usize % MAX_SHIPS;
+            let position_delta: i16 = ((position_zigzag >> 1) as i16) ^ -((position_zigzag & 1) as i16);
+            let target_position: i16 = last_positions[source_usize].wrapping_add(position_delta);
+
+            moves.push(Move {
+                move_type,
+                round,
+                source,
+                target,
+                target_position,
+                damage: damage as u32,
+            });
+
+            last_round = round;
+            last_positions[source_usize] = target_position;
+        }
+
+        Some(moves)
+    }
+
+    /// Encodes the ruleset, fight inputs and result, in canonical form.
+    /// This is both the header of a compact replay, and the preimage of the fight hash.
+    fn write_header(output: &mut Vec<u8>, ships_hash: [u8; 32], result: &FightResult) {
+        output.push(RULESET_VERSION);
+        output.extend_from_slice(&ships_hash);
+        write_varint(output, result.seed);
+        output.extend_from_slice(&result.selection_lhs);
+        output.extend_from_slice(&result.selection_rhs);
+        output.extend_from_slice(&result.variants_lhs);
+        output.extend_from_slice(&result.variants_rhs);
+        output.push(result.commander_lhs);
+        output.push(result.commander_rhs);
+        output.push(result.commander_level_lhs);
+        output.push(result.commander_level_rhs);
+        write_varint(output, result.commander_skills_lhs as u64);
+        write_varint(output, result.commander_skills_rhs as u64);
+        for reserve in [result.reserve_lhs, result.reserve_rhs].iter() {
+            output.extend_from_slice(&reserve.selection);
+            output.push(reserve.round);
+            output.push(reserve.threshold);
+        }
+        output.push(result.morale.rout_threshold);
+        output.push(result.morale.retreat_threshold);
+        output.push(result.map);
+        output.push((result.lhs_dead as u8) | ((result.rhs_dead as u8) << 1) |
+            ((result.lhs_retreated as u8) << 2) | ((result.rhs_retreated as u8) << 3));
+        output.push(result.rounds);
+        output.extend_from_slice(&result.ships_lost_lhs);
+        output.extend_from_slice(&result.ships_lost_rhs);
+    }
+
+    /// Calculates the commitment hash of a fight.
+    /// Anyone holding the fight inputs can rerun the fight and compare the hash
+    /// with the one recorded on chain.
+    ///
+    /// # Arguments
+    ///
+    /// * `ships_hash` - Hash of the ship definitions the fight was generated with
+    /// * `result` - The FightResult, holding the fight inputs and result
+    ///
+    /// # Returns
+    ///
+    /// * `hash` - Blake2x256 hash of the canonical fight header
+    pub fn fight_hash(ships_hash: [u8; 32], result: &FightResult) -> [u8; 32] {
+        let mut header: Vec<u8> = Vec::new();
+        let mut hash: [u8; 32] = [0; 32];
+
+        write_header(&mut header, ships_hash, result);
+        ink_env::hash_bytes::<Blake2x256>(&header, &mut hash);
+
+        hash
+    }
+
+    /// Encodes a fight into the compact, versioned replay format.
+    /// Replays can be shared in this form, and verified later by rerunning the fight.
+    ///
+    /// # Arguments
+    ///
+    /// * `ships_hash` - Hash of the ship definitions the fight was generated with
+    /// * `result` - The FightResult, holding the fight inputs and result
+    /// * `moves_lhs` - Logged moves of the attacker
+    /// * `moves_rhs` - Logged moves of the defender
+    ///
+    /// # Returns
+    ///
+    /// * `replay` - The encoded replay bytes
+    pub fn encode_replay(ships_hash: [u8; 32], result: &FightResult, moves_lhs: &Vec<Move>,
+        moves_rhs: &Vec<Move>) -> Vec<u8> {
+
+        let mut output: Vec<u8> = Vec::new();
+
+        output.push(REPLAY_FORMAT_VERSION);
+        write_header(&mut output, ships_hash, result);
+        write_moves(&mut output, moves_lhs);
+        write_moves(&mut output, moves_rhs);
+
+        output
+    }
+
+    /// Decodes a replay from the compact replay format.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The encoded replay bytes
+    ///
+    /// # Returns
+    ///
+    /// * `replay` - The decoded Replay. None if the input is malformed or of an unknown format version.
+    pub fn decode_replay(input: &[u8]) -> Option<Replay> {
+        let mut offset: usize = 0;
+
+        if read_byte(input, &mut offset)? != REPLAY_FORMAT_VERSION {
+            return None;
+        }
+
+        let ruleset_version: u8 = read_byte(input, &mut offset)?;
+        let mut ships_hash: [u8; 32] = [0; 32];
+        for i in 0..32 {
+            ships_hash[i] = read_byte(input, &mut offset)?;
+        }
+
+        let seed: u64 = read_varint(input, &mut offset)?;
+        let selection_lhs: [u8; MAX_SHIPS] = read_array(input, &mut offset)?;
+        let selection_rhs: [u8; MAX_SHIPS] = read_array(input, &mut offset)?;
+        let variants_lhs: [u8; MAX_SHIPS] = read_array(input, &mut offset)?;
+        let variants_rhs: [u8; MAX_SHIPS] = read_array(input, &mut offset)?;
+        let commander_lhs: u8 = read_byte(input, &mut offset)?;
+        let commander_rhs: u8 = read_byte(input, &mut offset)?;
+        let commander_level_lhs: u8 = read_byte(input, &mut offset)?;
+        let commander_level_rhs: u8 = read_byte(input, &mut offset)?;
+        let commander_skills_lhs: u64 = read_varint(input, &mut offset)?;
+        let commander_skills_rhs: u64 = read_varint(input, &mut offset)?;
+        let reserve_lhs: Reserve = Reserve {
+            selection: read_array(input, &mut offset)?,
+            round: read_byte(input, &mut offset)?,
+            threshold: read_byte(input, &mut offset)?,
+        };
+        let reserve_rhs: Reserve = Reserve {
+            selection: read_array(input, &mut offset)?,
+            round: read_byte(input, &mut offset)?,
+            threshold: read_byte(input, &mut offset)?,
+        };
+        let rout_threshold: u8 = read_byte(input, &mut offset)?;
+        let retreat_threshold: u8 = read_byte(input, &mut offset)?;
+        let map: u8 = read_byte(input, &mut offset)?;
+        let dead_flags: u8 = read_byte(input, &mut offset)?;
+        let rounds: u8 = read_byte(input, &mut offset)?;
+        let ships_lost_lhs: [u8; MAX_SHIPS] = read_array(input, &mut offset)?;
+        let ships_lost_rhs: [u8; MAX_SHIPS] = read_array(input, &mut offset)?;
+        let moves_lhs: Vec<Move> = read_moves(input, &mut offset)?;
+        let moves_rhs: Vec<Move> = read_moves(input, &mut offset)?;
+
+        if commander_skills_lhs > u32::MAX as u64 || commander_skills_rhs > u32::MAX as u64 {
+            return None;
+        }
+
+        // Trailing bytes mean the replay was not produced by this format version
+        if offset != input.len() {
+            return None;
+        }
+
+        let mut result: FightResult = FightResult {
+            selection_lhs,
+            selection_rhs,
+            variants_lhs,
+            variants_rhs,
+            commander_lhs,
+            commander_rhs,
+            commander_level_lhs,
+            commander_level_rhs,
+            commander_skills_lhs: commander_skills_lhs as u32,
+            commander_skills_rhs: commander_skills_rhs as u32,
+            reserve_lhs,
+            reserve_rhs,
+            lhs_dead: dead_flags & 1 != 0,
+            rhs_dead: dead_flags & 2 != 0,
+            lhs_retreated: dead_flags & 4 != 0,
+            rhs_retreated: dead_flags & 8 != 0,
+            morale: Morale {
+                rout_threshold,
+                retreat_threshold,
+            },
+            map,
+            rounds,
+            seed,
+            ships_lost_lhs,
+            ships_lost_rhs,
+            hash: [0; 32],
+            stats_lhs: None,
+            stats_rhs: None,
+        };
+
+        // Only meaningful if the replay was generated with the current ruleset
+        if ruleset_version == RULESET_VERSION {
+            result.hash = fight_hash(ships_hash, &result);
+        }
+
+        Some(Replay {
+            ruleset_version,
+            ships_hash,
+            result,
+            moves_lhs,
+            moves_rhs,
+        })
+    }
+
     impl NewOmega {
         #[ink(constructor)]
         pub fn new() -> Self {
@@ -284,8 +1214,9 @@ mod newomega {
         ///
         /// # Arguments
         ///
-        /// * `ships` - A Vec that holds the definiton of all the ships
         /// * `current_ship` - Index of the ship to pick target for
+        /// * `range` - Range of the ship, modified by the terrain
+        /// * `speed` - Speed of the ship, modified by the terrain
         /// * `ship_positions_own` - An array of fleet positions of the player performing the move
         /// * `ship_positions_enemy` - An array of fleet positions of the player NOT performing the move
         /// * `ship_hps_own` - An array of fleet HPs of the player performing the move
@@ -300,7 +1231,7 @@ mod newomega {
         /// # Algorithm rules:
         ///     1. To be considered in range, target ship must be within range+speed from source ship
         ///     2. Targets are picked according to their size, ie bigger ships first
-        fn get_target(&self, ships: &Vec<Ship>, current_ship: u8,
+        fn get_target(&self, current_ship: u8, range: u8, speed: u8,
             ship_positions_own: [i16; MAX_SHIPS], ship_positions_enemy: [i16; MAX_SHIPS],
             ship_hps_enemy: [i32; MAX_SHIPS]) -> (bool, u8, u8) {
 
@@ -315,14 +1246,14 @@ mod newomega {
                 let position_diff:i16 = position as i16 - ship_positions_enemy[enemy_ship_usize] as i16;
                 let delta:u8 = position_diff.abs() as u8;
 
-                if (delta <= ships[current_ship_usize].range + ships[current_ship_usize].speed) &&
+                if (delta <= range + speed) &&
                     ship_hps_enemy[enemy_ship_usize] > 0 {
 
                     // We have found a target
                     min_distance_index = enemy_ship;
                     // Do we need to move?
-                    if delta > ships[current_ship_usize].range {
-                        proposed_move = delta - ships[current_ship_usize].range;
+                    if delta > range {
+                        proposed_move = delta - range;
                     } else {
                         proposed_move = 0;
                     }
@@ -343,7 +1274,7 @@ mod newomega {
             }
         }
 
-        /// Calculate damage done by a ship to another ship.
+        /// Calculate damage done by a ship to another ship. A defence above the attack blocks all the damage.
         ///
         /// # Arguments
         ///
@@ -354,85 +1285,846 @@ mod newomega {
         /// * `source` - Index of the ship shooting
         /// * `target` - Index of the ship being shot at
         /// * `source_hp` - HPs left, of the shooting ship
+        /// * `attack_bonus` - Commander attack bonus of the player shooting, in percent
+        /// * `defence_bonus` - Commander defence bonus of the player NOT shooting, in percent
         ///
         /// # Returns
         ///
         /// * `damage` - The calculated damage
         fn calculate_damage(&self, variables: [u16; MAX_SHIPS], variants_source: [u8; MAX_SHIPS],
             variants_target: [u8; MAX_SHIPS], ships: &Vec<Ship>, source: u8,
-            target: u8, source_hp: u32) -> u32 {
+            target: u8, source_hp: u32, attack_bonus: u16, defence_bonus: u16) -> u32 {
 
             let source_usize: usize = source as usize;
             let target_usize: usize = target as usize;
-            let attack: u16 = self.get_attack_stat(ships[source_usize].attack_base,
-                variants_source[source_usize]) + variables[source_usize];
+            let attack: u16 = self.apply_bonus(self.get_attack_stat(ships[source_usize].attack_base,
+                variants_source[source_usize]) + variables[source_usize], attack_bonus);
             let source_ships_count: u16 = self.get_number_of_ships_from_hp(source_hp, ships[source_usize].hp);
             let cap_damage: u32 = (source_ships_count as u32) * (ships[target_usize].hp as u32);
-            let defence: u16 = self.get_defence_stat(ships[target_usize].defence, variants_target[target_usize]);
-            let mut damage: u32 = (attack - defence) as u32 * (source_ships_count as u32);
+            let base_defence: u16 = self.apply_bonus(self.get_defence_stat(ships[target_usize].defence,
+                variants_target[target_usize]), defence_bonus);
+            let defence: u16 = base_defence - (base_defence as u32 *
+                self.get_armor_piercing(&ships[source_usize]) as u32 / 100) as u16;
+            let damage: u32 = attack.saturating_sub(defence) as u32 * (source_ships_count as u32);
 
             return self.min(self.max(0, damage as i32), cap_damage as i32) as u32;
         }
 
-        /// Logs the Shoot move into the moves array.
+        /// Increases a stat by a percentage bonus
         ///
         /// # Arguments
         ///
-        /// * `round` - Round in which the move took place
-        /// * `moves` - The Moves array to modify (mutable)
-        /// * `source` - Index of the ship performing the move
-        /// * `target` - Index of the target ship
-        /// * `damage` - Damage inflicted
-        /// * `position` - New ship position (can be unchanged)
-        fn log_shoot(&self, round: u8, moves: &mut Vec<Move>,
-            source: u8, target: u8, damage: u32, position: i16) {
-
-            moves.push(Move {
-                move_type: 1,
-                round: round,
-                source: source,
-                target: target,
-                damage: damage,
-                target_position: position
-            });
+        /// * `stat` - The stat to increase
+        /// * `bonus` - The bonus, in percent
+        ///
+        /// # Returns
+        ///
+        /// * `final_stat` - The increased stat
+        fn apply_bonus(&self, stat: u16, bonus: u16) -> u16 {
+            (stat as u32 * (100 + bonus as u32) / 100) as u16
         }
 
-        /// Logs the Reposition move into the moves array.
+        /// Gets the attack and defence bonuses of a commander, scaled by its level.
+        /// The level is capped according to the rarity, and unknown commanders give no bonus.
         ///
         /// # Arguments
         ///
-        /// * `round` - Round in which the move took place
-        /// * `moves` - The Moves array to modify (mutable)
-        /// * `source` - Index of the ship performing the move
-        /// * `target` - Index of the target ship
-        /// * `damage` - Damage inflicted
-        /// * `position` - New ship position (can be unchanged)
-        fn log_move(&self, round: u8, moves: &mut Vec<Move>,
-            source: u8, target_position: i16) {
-
-            moves.push(Move {
-                move_type: 2,
-                round: round,
-                source: source,
-                target_position: target_position,
-                target: 0,
-                damage: 0
-            });
+        /// * `commanders` - A Vec that holds the definition of all the commanders
+        /// * `commander` - Id of the commander
+        /// * `level` - Level of the commander, 0 for no bonus
+        ///
+        /// # Returns
+        ///
+        /// * `attack_bonus` - The attack bonus, in percent
+        /// * `defence_bonus` - The defence bonus, in percent
+        fn get_commander_bonus(&self, commanders: &Vec<Commander>, commander: u8, level: u8) -> (u16, u16) {
+            match commanders.get(commander as usize) {
+                Some(definition) => {
+                    let capped_level: u16 = self.min(level as i32,
+                        max_commander_level(definition.rarity) as i32) as u16;
+                    (definition.attack as u16 * capped_level, definition.defence as u16 * capped_level)
+                },
+                None => (0, 0),
+            }
         }
 
-        /// Calculates a fight.
+        /// Gets the bonuses of the selected skills of a commander.
+        /// The skills are expected to be valid for the level of the commander (see `are_skills_valid`).
         ///
         /// # Arguments
         ///
-        /// * `seed` - Seed used to generate randomness
-        /// * `log_moves` - Whether to return a detailed fight log
-        /// * `ships` - A Vec that holds the definiton of all the ships
-        /// * `selection_lhs` - Attacker fleet composition (array with ship quantities)
-        /// * `selection_rhs` - Defender fleet composition (array with ship quantities)
+        /// * `commanders` - A Vec that holds the definition of all the commanders
+        /// * `commander` - Id of the commander
+        /// * `skills` - Selected skills (bitmask of skill ids)
+        ///
+        /// # Returns
+        ///
+        /// * `range_bonus` - Range bonus, per ship type
+        /// * `evasion_bonus` - Evasion bonus of the whole fleet in the first round, in percent
+        /// * `damage_bonus` - Attack bonus against each ship type, in percent
+        fn get_skill_bonuses(&self, commanders: &Vec<Commander>, commander: u8, skills: u32)
+            -> ([u8; MAX_SHIPS], u8, [u16; MAX_SHIPS]) {
+
+            let mut range_bonus: [u8; MAX_SHIPS] = [0; MAX_SHIPS];
+            let mut evasion_bonus: u8 = 0;
+            let mut damage_bonus: [u16; MAX_SHIPS] = [0; MAX_SHIPS];
+
+            match commanders.get(commander as usize) {
+                Some(definition) => {
+                    for (id, skill) in definition.skills.iter().enumerate().take(MAX_COMMANDER_SKILLS) {
+                        if skills & (1 << id) == 0 {
+                            continue;
+                        }
+
+                        match skill.effect {
+                            SkillEffect::Range { ship, bonus } if (ship as usize) < MAX_SHIPS =>
+                                range_bonus[ship as usize] = range_bonus[ship as usize].saturating_add(bonus),
+                            SkillEffect::FirstRoundEvasion { bonus } =>
+                                evasion_bonus = evasion_bonus.saturating_add(bonus),
+                            SkillEffect::BonusDamage { target, percent } if (target as usize) < MAX_SHIPS =>
+                                damage_bonus[target as usize] += percent as u16,
+                            _ => (),
+                        }
+                    }
+                },
+                None => (),
+            }
+
+            (range_bonus, evasion_bonus, damage_bonus)
+        }
+
+        /// Gets the shield capacity and regeneration of a ship, summed over its Shield abilities
+        fn get_shield(&self, ship: &Ship) -> (u32, u32) {
+            let mut capacity: u32 = 0;
+            let mut regeneration: u32 = 0;
+
+            for ability in ship.abilities.iter() {
+                match ability {
+                    Ability::Shield { capacity: c, regeneration: r } => {
+                        capacity += *c as u32;
+                        regeneration += *r as u32;
+                    },
+                    _ => (),
+                }
+            }
+
+            (capacity, regeneration)
+        }
+
+        /// Gets the percentage of target defence ignored by a ship, capped at 100
+        fn get_armor_piercing(&self, ship: &Ship) -> u8 {
+            let mut percent: u32 = 0;
+
+            for ability in ship.abilities.iter() {
+                match ability {
+                    Ability::ArmorPiercing { percent: p } => percent += *p as u32,
+                    _ => (),
+                }
+            }
+
+            self.min(percent as i32, 100) as u8
+        }
+
+        /// Gets the percentage of shot damage a ship deals to the adjacent stacks
+        fn get_splash(&self, ship: &Ship) -> u32 {
+            let mut percent: u32 = 0;
+
+            for ability in ship.abilities.iter() {
+                match ability {
+                    Ability::Splash { percent: p } => percent += *p as u32,
+                    _ => (),
+                }
+            }
+
+            percent
+        }
+
+        /// Gets the amount of HP a ship repairs per round
+        fn get_repair(&self, ship: &Ship) -> u32 {
+            let mut amount: u32 = 0;
+
+            for ability in ship.abilities.iter() {
+                match ability {
+                    Ability::Repair { amount: a } => amount += *a as u32,
+                    _ => (),
+                }
+            }
+
+            amount
+        }
+
+        /// Gets the speed of a ship, modified by the asteroid fields it is in
+        ///
+        /// # Arguments
+        ///
+        /// * `battlefield` - The battlefield the fight takes place on
+        /// * `speed` - Base speed of the ship
+        /// * `position` - Current position of the ship
+        ///
+        /// # Returns
+        ///
+        /// * `speed` - The modified speed, at least 1
+        fn get_speed(&self, battlefield: &Battlefield, speed: u8, position: i16) -> u8 {
+            let mut final_speed: i32 = speed as i32;
+
+            for terrain in battlefield.terrain.iter() {
+                match terrain {
+                    Terrain::Asteroids { from, to, slowdown } =>
+                        if position >= *from && position <= *to {
+                            final_speed -= *slowdown as i32;
+                        },
+                    _ => (),
+                }
+            }
+
+            self.max(final_speed, 1) as u8
+        }
+
+        /// Gets the range of a ship, modified by the nebulae it is in
+        ///
+        /// # Arguments
+        ///
+        /// * `battlefield` - The battlefield the fight takes place on
+        /// * `range` - Base range of the ship
+        /// * `position` - Current position of the ship
+        ///
+        /// # Returns
+        ///
+        /// * `range` - The modified range, at least 1
+        fn get_range(&self, battlefield: &Battlefield, range: u8, position: i16) -> u8 {
+            let mut final_range: i32 = range as i32;
+
+            for terrain in battlefield.terrain.iter() {
+                match terrain {
+                    Terrain::Nebula { from, to, range_penalty } =>
+                        if position >= *from && position <= *to {
+                            final_range -= *range_penalty as i32;
+                        },
+                    _ => (),
+                }
+            }
+
+            self.max(final_range, 1) as u8
+        }
+
+        /// Applies the damage of the minefields a stack moved into or through, and logs it.
+        /// Mines hit the hull directly, bypassing shields.
+        ///
+        /// # Arguments
+        ///
+        /// * `ships` - A Vec that holds the definiton of all the ships
+        /// * `battlefield` - The battlefield the fight takes place on
+        /// * `round` - The current round
+        /// * `ship` - Index of the moving ship
+        /// * `position_from` - Position before the move
+        /// * `position_to` - Position after the move
+        /// * `hps` - An array of fleet HPs of the player moving (mutable)
+        /// * `moves` - Logged moves of the player moving (mutable)
+        fn apply_minefields(&self, ships: &Vec<Ship>, battlefield: &Battlefield, round: u8, ship: u8,
+            position_from: i16, position_to: i16, hps: &mut [i32; MAX_SHIPS], moves: &mut Option<Vec<Move>>) {
+
+            let ship_usize: usize = ship as usize;
+
+            if position_from == position_to || hps[ship_usize] <= 0 {
+                return;
+            }
+
+            // Positions entered during the move, excluding the starting one
+            let (entered_min, entered_max) = if position_to > position_from {
+                (position_from + 1, position_to)
+            } else {
+                (position_to, position_from - 1)
+            };
+
+            for terrain in battlefield.terrain.iter() {
+                match terrain {
+                    Terrain::Minefield { from, to, damage } =>
+                        if entered_min <= *to && entered_max >= *from && hps[ship_usize] > 0 {
+                            let ship_count: u32 = self.get_number_of_ships_from_hp(hps[ship_usize] as u32,
+                                ships[ship_usize].hp) as u32;
+                            let mine_damage: u32 = *damage as u32 * ship_count;
+
+                            hps[ship_usize] -= mine_damage as i32;
+
+                            // Log the move, if required
+                            match moves {
+                                Some(ref mut moves) =>
+                                    self.log_ability(round, moves, MOVE_MINE, ship, ship, mine_damage),
+                                _ => ()
+                            }
+                        },
+                    _ => (),
+                }
+            }
+        }
+
+        /// Advances the deterministic fight PRNG (xorshift64*).
+        ///
+        /// # Arguments
+        ///
+        /// * `state` - The PRNG state, seeded from the fight seed (mutable)
+        ///
+        /// # Returns
+        ///
+        /// * `random` - The next pseudo random value
+        fn next_random(&self, state: &mut u64) -> u64 {
+            *state ^= *state >> 12;
+            *state ^= *state << 25;
+            *state ^= *state >> 27;
+
+            state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+        }
+
+        /// Resolves accuracy, evasion and critical hits of a shot.
+        /// Random rolls are only made when needed, so that ships with default stats
+        /// do not advance the PRNG.
+        ///
+        /// # Arguments
+        ///
+        /// * `ships` - A Vec that holds the definiton of all the ships
+        /// * `random_state` - The fight PRNG state (mutable)
+        /// * `source` - Index of the ship shooting
+        /// * `target` - Index of the ship being shot at
+        /// * `damage` - Regular damage of the shot
+        /// * `shot_type` - Move type of a regular hit
+        /// * `evasion_bonus` - Additional evasion of the target, in percent
+        ///
+        /// # Returns
+        ///
+        /// * `damage` - Final damage of the shot
+        /// * `shot_type` - Move type of the shot (regular, miss or critical)
+        fn resolve_shot(&self, ships: &Vec<Ship>, random_state: &mut u64, source: u8, target: u8,
+            damage: u32, shot_type: u8, evasion_bonus: u8) -> (u32, u8) {
+
+            let source_ship: &Ship = &ships[source as usize];
+            let hit_chance: i32 = source_ship.accuracy as i32 - ships[target as usize].evasion as i32 -
+                evasion_bonus as i32;
+
+            if hit_chance < 100 && (self.next_random(random_state) % 100) as i32 >= hit_chance {
+                return (0, MOVE_MISS);
+            }
+
+            if source_ship.crit_chance > 0 && self.next_random(random_state) % 100 < source_ship.crit_chance as u64 {
+                return (damage.saturating_mul(source_ship.crit_multiplier as u32) / 100, MOVE_CRITICAL);
+            }
+
+            (damage, shot_type)
+        }
+
+        /// Records damage in the fight statistics of both sides, if required.
+        /// Has to be called before the damage is applied.
+        ///
+        /// # Arguments
+        ///
+        /// * `stats_source` - Statistics of the player dealing damage (mutable)
+        /// * `stats_target` - Statistics of the player receiving damage (mutable)
+        /// * `round` - Round in which the damage was dealt
+        /// * `source` - Index of the ship dealing damage
+        /// * `target` - Index of the ship receiving damage
+        /// * `damage` - Damage dealt to the hull
+        /// * `target_hp` - HPs left in the target stack, before the damage
+        /// * `target_ship_hp` - HPs of a single ship of the target type
+        fn record_damage(&self, stats_source: &mut Option<FightStats>, stats_target: &mut Option<FightStats>,
+            round: u8, source: u8, target: u8, damage: u32, target_hp: i32, target_ship_hp: u16) {
+
+            let hp_before: u32 = self.max(target_hp, 0) as u32;
+            let effective_damage: u32 = self.min(damage as i32, hp_before as i32) as u32;
+
+            match stats_source {
+                Some(ref mut stats) => {
+                    let ships_before: u16 = self.get_number_of_ships_from_hp(hp_before, target_ship_hp);
+                    let ships_after: u16 = self.get_number_of_ships_from_hp(hp_before - effective_damage,
+                        target_ship_hp);
+
+                    stats.damage_dealt[source as usize] += effective_damage;
+                    stats.overkill_damage += damage - effective_damage;
+
+                    if ships_after < ships_before && stats.first_blood_round.is_none() {
+                        stats.first_blood_round = Some(round);
+                    }
+                },
+                _ => ()
+            }
+
+            match stats_target {
+                Some(ref mut stats) => stats.damage_received[target as usize] += effective_damage,
+                _ => ()
+            }
+        }
+
+        /// Determines the most valuable ship (the one which dealt most damage), if required.
+        ///
+        /// # Arguments
+        ///
+        /// * `stats` - Statistics of the player (mutable)
+        fn finalize_stats(&self, stats: &mut Option<FightStats>) {
+            match stats {
+                Some(ref mut stats) => {
+                    for i in 0..MAX_SHIPS {
+                        if stats.damage_dealt[i] > stats.damage_dealt[stats.most_valuable_ship as usize] {
+                            stats.most_valuable_ship = i as u8;
+                        }
+                    }
+                },
+                _ => ()
+            }
+        }
+
+        /// Applies damage to an enemy stack. Shields absorb the damage before the hull.
+        /// Logs the absorption, and records the hull damage in the statistics.
+        ///
+        /// # Arguments
+        ///
+        /// * `ships` - A Vec that holds the definiton of all the ships
+        /// * `round` - Round in which the damage was dealt
+        /// * `source` - Index of the ship dealing damage
+        /// * `target` - Index of the ship receiving damage
+        /// * `damage` - Damage dealt
+        /// * `hps_enemy` - An array of fleet HPs of the player receiving damage (mutable)
+        /// * `shields_enemy` - An array of fleet shields of the player receiving damage (mutable)
+        /// * `moves_enemy` - Logged moves of the player receiving damage (mutable)
+        /// * `stats_own` - Statistics of the player dealing damage (mutable)
+        /// * `stats_enemy` - Statistics of the player receiving damage (mutable)
+        fn apply_damage(&self, ships: &Vec<Ship>, round: u8, source: u8, target: u8, damage: u32,
+            hps_enemy: &mut [i32; MAX_SHIPS], shields_enemy: &mut [u32; MAX_SHIPS],
+            moves_enemy: &mut Option<Vec<Move>>, stats_own: &mut Option<FightStats>,
+            stats_enemy: &mut Option<FightStats>) {
+
+            let target_usize: usize = target as usize;
+            let absorbed: u32 = self.min(shields_enemy[target_usize] as i32, damage as i32) as u32;
+            let hull_damage: u32 = damage - absorbed;
+
+            if absorbed > 0 {
+                shields_enemy[target_usize] -= absorbed;
+
+                // Log the move, if required
+                match moves_enemy {
+                    Some(ref mut moves) =>
+                        self.log_ability(round, moves, MOVE_SHIELD, target, source, absorbed),
+                    _ => ()
+                }
+            }
+
+            self.record_damage(stats_own, stats_enemy, round, source, target, hull_damage,
+                hps_enemy[target_usize], ships[target_usize].hp);
+            hps_enemy[target_usize] -= hull_damage as i32;
+        }
+
+        /// Applies a shot to the enemy fleet, including the splash damage to the stacks
+        /// adjacent to the target, if the shooting ship has the Splash ability.
+        ///
+        /// # Arguments
+        ///
+        /// * `ships` - A Vec that holds the definiton of all the ships
+        /// * `round` - Round in which the shot took place
+        /// * `source` - Index of the ship shooting
+        /// * `target` - Index of the ship being shot at
+        /// * `damage` - Damage of the shot
+        /// * `hps_enemy` - An array of fleet HPs of the player NOT shooting (mutable)
+        /// * `shields_enemy` - An array of fleet shields of the player NOT shooting (mutable)
+        /// * `moves_own` - Logged moves of the player shooting (mutable)
+        /// * `moves_enemy` - Logged moves of the player NOT shooting (mutable)
+        /// * `stats_own` - Statistics of the player shooting (mutable)
+        /// * `stats_enemy` - Statistics of the player NOT shooting (mutable)
+        fn apply_shot(&self, ships: &Vec<Ship>, round: u8, source: u8, target: u8, damage: u32,
+            hps_enemy: &mut [i32; MAX_SHIPS], shields_enemy: &mut [u32; MAX_SHIPS],
+            moves_own: &mut Option<Vec<Move>>, moves_enemy: &mut Option<Vec<Move>>,
+            stats_own: &mut Option<FightStats>, stats_enemy: &mut Option<FightStats>) {
+
+            match stats_own {
+                Some(ref mut stats) => stats.shots_fired[source as usize] += 1,
+                _ => ()
+            }
+
+            self.apply_damage(ships, round, source, target, damage, hps_enemy, shields_enemy,
+                moves_enemy, stats_own, stats_enemy);
+
+            let splash_damage: u32 = damage * self.get_splash(&ships[source as usize]) / 100;
+
+            if splash_damage > 0 {
+                for adjacent in 0..MAX_SHIPS {
+                    let adjacent_u8: u8 = adjacent as u8;
+
+                    if (adjacent_u8 + 1 == target || adjacent_u8 == target + 1) && hps_enemy[adjacent] > 0 {
+                        // Log the move, if required
+                        match moves_own {
+                            Some(ref mut moves) =>
+                                self.log_ability(round, moves, MOVE_SPLASH, source, adjacent_u8, splash_damage),
+                            _ => ()
+                        }
+
+                        self.apply_damage(ships, round, source, adjacent_u8, splash_damage, hps_enemy,
+                            shields_enemy, moves_enemy, stats_own, stats_enemy);
+                    }
+                }
+            }
+        }
+
+        /// Applies the abilities triggering at the start of every round, for one player:
+        /// shield regeneration and repair.
+        ///
+        /// # Arguments
+        ///
+        /// * `ships` - A Vec that holds the definiton of all the ships
+        /// * `round` - The current round
+        /// * `selection` - Fleet composition of the player (array with ship quantities)
+        /// * `routed_hps` - An array of HPs which left the battlefield, per ship type
+        /// * `hps` - An array of fleet HPs of the player (mutable)
+        /// * `shields` - An array of fleet shields of the player (mutable)
+        /// * `moves` - Logged moves of the player (mutable)
+        fn apply_round_abilities(&self, ships: &Vec<Ship>, round: u8, selection: [u8; MAX_SHIPS],
+            routed_hps: [i32; MAX_SHIPS], hps: &mut [i32; MAX_SHIPS], shields: &mut [u32; MAX_SHIPS],
+            moves: &mut Option<Vec<Move>>) {
+
+            for i in 0..MAX_SHIPS {
+                if hps[i] <= 0 {
+                    continue;
+                }
+
+                let ship_count: u32 = self.get_number_of_ships_from_hp(hps[i] as u32, ships[i].hp) as u32;
+                let (capacity, regeneration) = self.get_shield(&ships[i]);
+                let max_shield: u32 = capacity * ship_count;
+                let regenerated: u32 = self.min((regeneration * ship_count) as i32,
+                    self.max(max_shield as i32 - shields[i] as i32, 0)) as u32;
+
+                if regenerated > 0 {
+                    shields[i] += regenerated;
+
+                    // Log the move, if required
+                    match moves {
+                        Some(ref mut moves) =>
+                            self.log_ability(round, moves, MOVE_SHIELD_REGENERATION, i as u8, i as u8, regenerated),
+                        _ => ()
+                    }
+                }
+
+                let repair: u32 = self.get_repair(&ships[i]) * ship_count;
+
+                if repair > 0 {
+                    // Pick the most damaged friendly stack still alive, routed ships can't be repaired
+                    let mut repair_target: usize = MAX_SHIPS;
+                    let mut max_missing: i32 = 0;
+
+                    for j in 0..MAX_SHIPS {
+                        let missing: i32 = (ships[j].hp as i32) * (selection[j] as i32) - routed_hps[j] - hps[j];
+
+                        if hps[j] > 0 && missing > max_missing {
+                            repair_target = j;
+                            max_missing = missing;
+                        }
+                    }
+
+                    if repair_target < MAX_SHIPS {
+                        let repaired: u32 = self.min(repair as i32, max_missing) as u32;
+                        hps[repair_target] += repaired as i32;
+
+                        // Log the move, if required
+                        match moves {
+                            Some(ref mut moves) =>
+                                self.log_ability(round, moves, MOVE_REPAIR, i as u8, repair_target as u8, repaired),
+                            _ => ()
+                        }
+                    }
+                }
+            }
+        }
+
+        /// Applies the morale rules at the end of a round, for one player.
+        /// Stacks which lost enough of their starting HP rout, and leave the battlefield with
+        /// their remaining HP. The fleet retreats if it lost enough of its starting HP,
+        /// or if all its remaining stacks have routed.
+        ///
+        /// # Arguments
+        ///
+        /// * `ships` - A Vec that holds the definiton of all the ships
+        /// * `morale` - The morale rules
+        /// * `round` - The current round
+        /// * `selection` - Fleet composition of the player (array with ship quantities)
+        /// * `hps` - An array of fleet HPs of the player (mutable)
+        /// * `routed_hps` - An array of HPs which left the battlefield, per ship type (mutable)
+        /// * `moves` - Logged moves of the player (mutable)
+        ///
+        /// # Returns
+        ///
+        /// * `retreated` - Whether the fleet retreated
+        fn apply_morale(&self, ships: &Vec<Ship>, morale: Morale, round: u8, selection: [u8; MAX_SHIPS],
+            hps: &mut [i32; MAX_SHIPS], routed_hps: &mut [i32; MAX_SHIPS], moves: &mut Option<Vec<Move>>) -> bool {
+
+            let mut starting_total: i32 = 0;
+            let mut remaining_total: i32 = 0;
+            let mut routed_total: i32 = 0;
+
+            for i in 0..MAX_SHIPS {
+                let starting: i32 = (ships[i].hp as i32) * (selection[i] as i32);
+                // Ships which already routed don't count towards the stack, e.g. when reinforced
+                let starting_stack: i32 = starting - routed_hps[i];
+
+                if morale.rout_threshold > 0 && hps[i] > 0 &&
+                    (starting_stack - hps[i]) * 100 >= starting_stack * morale.rout_threshold as i32 {
+
+                    routed_hps[i] = hps[i];
+                    hps[i] = 0;
+
+                    // Log the move, if required
+                    match moves {
+                        Some(ref mut moves) =>
+                            self.log_ability(round, moves, MOVE_ROUT, i as u8, i as u8, routed_hps[i] as u32),
+                        _ => ()
+                    }
+                }
+
+                starting_total += starting;
+                remaining_total += self.max(hps[i], 0);
+                routed_total += routed_hps[i];
+            }
+
+            let retreated: bool = (remaining_total == 0 && routed_total > 0) ||
+                (morale.retreat_threshold > 0 && starting_total > 0 &&
+                    (starting_total - remaining_total) * 100 >= starting_total * morale.retreat_threshold as i32);
+
+            if retreated {
+                // Log the move, if required
+                match moves {
+                    Some(ref mut moves) =>
+                        self.log_ability(round, moves, MOVE_RETREAT, 0, 0, remaining_total as u32),
+                    _ => ()
+                }
+            }
+
+            retreated
+        }
+
+        /// Applies the morale rules at the end of a round, for both players.
+        /// Once a fleet is destroyed the fight is decided: the destroyed fleet can't retreat,
+        /// and neither can the fleet which destroyed it, so morale is skipped for both.
+        ///
+        /// # Arguments
+        ///
+        /// * `ships` - A Vec that holds the definiton of all the ships
+        /// * `morale` - The morale rules
+        /// * `round` - The current round
+        /// * `selection_lhs` - Fleet composition of the attacker (array with ship quantities)
+        /// * `selection_rhs` - Fleet composition of the defender (array with ship quantities)
+        /// * `hps_lhs` - An array of fleet HPs of the attacker (mutable)
+        /// * `hps_rhs` - An array of fleet HPs of the defender (mutable)
+        /// * `routed_hps_lhs` - An array of attacker HPs which left the battlefield, per ship type (mutable)
+        /// * `routed_hps_rhs` - An array of defender HPs which left the battlefield, per ship type (mutable)
+        /// * `moves_lhs` - Logged moves of the attacker (mutable)
+        /// * `moves_rhs` - Logged moves of the defender (mutable)
+        ///
+        /// # Returns
+        ///
+        /// * `lhs_retreated` - Whether the attacker retreated
+        /// * `rhs_retreated` - Whether the defender retreated
+        fn apply_round_morale(&self, ships: &Vec<Ship>, morale: Morale, round: u8, selection_lhs: [u8; MAX_SHIPS],
+            selection_rhs: [u8; MAX_SHIPS], hps_lhs: &mut [i32; MAX_SHIPS], hps_rhs: &mut [i32; MAX_SHIPS],
+            routed_hps_lhs: &mut [i32; MAX_SHIPS], routed_hps_rhs: &mut [i32; MAX_SHIPS],
+            moves_lhs: &mut Option<Vec<Move>>, moves_rhs: &mut Option<Vec<Move>>) -> (bool, bool) {
+
+            if self.is_dead(*hps_lhs) || self.is_dead(*hps_rhs) {
+                return (false, false);
+            }
+
+            (self.apply_morale(ships, morale, round, selection_lhs, hps_lhs, routed_hps_lhs, moves_lhs),
+                self.apply_morale(ships, morale, round, selection_rhs, hps_rhs, routed_hps_rhs, moves_rhs))
+        }
+
+        /// Checks whether the reserve of a player enters the battle at the start of a round
+        ///
+        /// # Arguments
+        ///
+        /// * `ships` - A Vec that holds the definiton of all the ships
+        /// * `reserve` - The reserve of the player
+        /// * `round` - The current round
+        /// * `selection` - Fleet composition of the front line (array with ship quantities)
+        /// * `hps` - An array of fleet HPs of the player
+        ///
+        /// # Returns
+        ///
+        /// * `is_due` - Whether the reserve enters the battle
+        fn is_reserve_due(&self, ships: &Vec<Ship>, reserve: Reserve, round: u8, selection: [u8; MAX_SHIPS],
+            hps: [i32; MAX_SHIPS]) -> bool {
+
+            if reserve.selection.iter().all(|&count| count == 0) {
+                return false;
+            }
+
+            let mut starting_total: i32 = 0;
+            let mut remaining_total: i32 = 0;
+
+            for i in 0..MAX_SHIPS {
+                starting_total += (ships[i].hp as i32) * (selection[i] as i32);
+                remaining_total += self.max(hps[i], 0);
+            }
+
+            (reserve.round > 0 && round >= reserve.round) ||
+                (reserve.threshold > 0 && remaining_total * 100 < starting_total * reserve.threshold as i32)
+        }
+
+        /// Brings the reserve of a player into the battle. Reserve ships merge into the stacks
+        /// of the same type still on the battlefield, or take the starting position of the stack
+        /// if it was destroyed or routed.
+        ///
+        /// # Arguments
+        ///
+        /// * `ships` - A Vec that holds the definiton of all the ships
+        /// * `reserve` - The reserve of the player
+        /// * `round` - The current round
+        /// * `starting_positions` - Starting ship positions of the player
+        /// * `selection` - Fleet composition of the player, reserve is added (mutable)
+        /// * `hps` - An array of fleet HPs of the player (mutable)
+        /// * `shields` - An array of fleet shields of the player (mutable)
+        /// * `positions` - Current ship positions of the player (mutable)
+        /// * `moves` - Logged moves of the player (mutable)
+        fn apply_reserve(&self, ships: &Vec<Ship>, reserve: Reserve, round: u8,
+            starting_positions: [i16; MAX_SHIPS], selection: &mut [u8; MAX_SHIPS], hps: &mut [i32; MAX_SHIPS],
+            shields: &mut [u32; MAX_SHIPS], positions: &mut [i16; MAX_SHIPS], moves: &mut Option<Vec<Move>>) {
+
+            for i in 0..MAX_SHIPS {
+                let count: u8 = reserve.selection[i];
+
+                if count == 0 {
+                    continue;
+                }
+
+                let (shield_capacity, _) = self.get_shield(&ships[i]);
+                let reserve_hp: i32 = (ships[i].hp as i32) * (count as i32);
+
+                if hps[i] > 0 {
+                    hps[i] += reserve_hp;
+                } else {
+                    hps[i] = reserve_hp;
+                    positions[i] = starting_positions[i];
+                }
+                shields[i] += shield_capacity * (count as u32);
+                selection[i] += count;
+
+                // Log the move, if required
+                match moves {
+                    Some(ref mut moves) =>
+                        self.log_reinforce(round, moves, i as u8, count, positions[i], reserve_hp as u32),
+                    _ => ()
+                }
+            }
+        }
+
+        /// Logs the Reinforce move into the moves array.
+        ///
+        /// # Arguments
+        ///
+        /// * `round` - Round in which the move took place
+        /// * `moves` - The Moves array to modify (mutable)
+        /// * `source` - Index of the reinforced ship
+        /// * `count` - Number of ships arriving
+        /// * `position` - Position of the reinforced stack
+        /// * `hp` - HP arriving
+        fn log_reinforce(&self, round: u8, moves: &mut Vec<Move>, source: u8, count: u8,
+            position: i16, hp: u32) {
+
+            moves.push(Move {
+                move_type: MOVE_REINFORCE,
+                round: round,
+                source: source,
+                target: count,
+                damage: hp,
+                target_position: position
+            });
+        }
+
+        /// Logs an ability move into the moves array.
+        ///
+        /// # Arguments
+        ///
+        /// * `round` - Round in which the move took place
+        /// * `moves` - The Moves array to modify (mutable)
+        /// * `move_type` - Type of the ability move
+        /// * `source` - Index of the ship using the ability
+        /// * `target` - Index of the ship affected by the ability
+        /// * `amount` - Amount of the ability effect (damage, absorbed damage, repaired HP)
+        fn log_ability(&self, round: u8, moves: &mut Vec<Move>, move_type: u8,
+            source: u8, target: u8, amount: u32) {
+
+            moves.push(Move {
+                move_type: move_type,
+                round: round,
+                source: source,
+                target: target,
+                damage: amount,
+                target_position: 0
+            });
+        }
+
+        /// Logs the Shoot move into the moves array.
+        ///
+        /// # Arguments
+        ///
+        /// * `round` - Round in which the move took place
+        /// * `moves` - The Moves array to modify (mutable)
+        /// * `move_type` - Shoot, Armor Piercing for ships with the ability, Miss or Critical
+        /// * `source` - Index of the ship performing the move
+        /// * `target` - Index of the target ship
+        /// * `damage` - Damage inflicted
+        /// * `position` - New ship position (can be unchanged)
+        fn log_shoot(&self, round: u8, moves: &mut Vec<Move>, move_type: u8,
+            source: u8, target: u8, damage: u32, position: i16) {
+
+            moves.push(Move {
+                move_type: move_type,
+                round: round,
+                source: source,
+                target: target,
+                damage: damage,
+                target_position: position
+            });
+        }
+
+        /// Logs the Reposition move into the moves array.
+        ///
+        /// # Arguments
+        ///
+        /// * `round` - Round in which the move took place
+        /// * `moves` - The Moves array to modify (mutable)
+        /// * `source` - Index of the ship performing the move
+        /// * `target` - Index of the target ship
+        /// * `damage` - Damage inflicted
+        /// * `position` - New ship position (can be unchanged)
+        fn log_move(&self, round: u8, moves: &mut Vec<Move>,
+            source: u8, target_position: i16) {
+
+            moves.push(Move {
+                move_type: MOVE_REPOSITION,
+                round: round,
+                source: source,
+                target_position: target_position,
+                target: 0,
+                damage: 0
+            });
+        }
+
+        /// Calculates a fight.
+        ///
+        /// # Arguments
+        ///
+        /// * `seed` - Seed used to generate randomness
+        /// * `log_moves` - Whether to return a detailed fight log
+        /// * `log_stats` - Whether to return aggregate fight statistics
+        /// * `ships` - A Vec that holds the definiton of all the ships
+        /// * `commanders` - A Vec that holds the definition of all the commanders
+        /// * `morale` - The morale rules
+        /// * `selection_lhs` - Attacker fleet composition (array with ship quantities)
+        /// * `selection_rhs` - Defender fleet composition (array with ship quantities)
         /// * `variants_lhs` - An array that holds variants of the attacker fleet
         /// * `variants_rhs` - An array that holds variants of the defender fleet
         /// * `commander_lhs` - The attacker commander
         /// * `commander_rhs` - The defender commander
+        /// * `commander_level_lhs` - Level of the attacker commander, 0 for no commander bonus
+        /// * `commander_level_rhs` - Level of the defender commander, 0 for no commander bonus
+        /// * `commander_skills_lhs` - Selected skills of the attacker commander (bitmask of skill ids)
+        /// * `commander_skills_rhs` - Selected skills of the defender commander (bitmask of skill ids)
+        /// * `reserve_lhs` - The attacker reserve
+        /// * `reserve_rhs` - The defender reserve
+        /// * `map` - Id of the map the fight takes place on
+        /// * `battlefield` - The battlefield (terrain) of the map
         ///
         /// # Returns
         ///
@@ -445,36 +2137,85 @@ mod newomega {
         ///     2. Each round, ships perform moves in turns, starting from smallest ships.
         ///     3. In each round, the same type of ship, of both the attacker and defender,
         ///        attacks at the same time.
-        ///     4. Ships can move, shoot, or both, depending on their Range and Speed.
-        ///     5. The winner is declared when one player is dead, or when the fight is still not finished
-        ///        after maximum number of rounds.
+        ///     4. Ships can move, shoot, or both, depending on their Range and Speed,
+        ///        as modified by the terrain they are in.
+        ///     5. At the end of each round, damaged stacks can rout, and a fleet can retreat,
+        ///        according to the morale rules.
+        ///     6. At the start of each round, reserves whose trigger is met enter the battle.
+        ///     7. Commanders increase the attack and defence of their whole fleet, according to their level,
+        ///        and apply the skills selected for them, which have to be valid for their level.
+        ///     8. The winner is declared when one player is dead or retreated, or when the fight is
+        ///        still not finished after maximum number of rounds.
         #[ink(message)]
-        pub fn fight(&self, seed: u64, log_moves: bool, ships: Vec<Ship>,
+        pub fn fight(&self, seed: u64, log_moves: bool, log_stats: bool, ships: Vec<Ship>,
+            commanders: Vec<Commander>, morale: Morale,
             selection_lhs: [u8; MAX_SHIPS], selection_rhs: [u8; MAX_SHIPS],
             variants_lhs: [u8; MAX_SHIPS], variants_rhs: [u8; MAX_SHIPS],
-            commander_lhs: u8, commander_rhs: u8) -> (FightResult, Option<Vec<Move>>,
-                Option<Vec<Move>>) {
+            commander_lhs: u8, commander_rhs: u8, commander_level_lhs: u8, commander_level_rhs: u8,
+            commander_skills_lhs: u32, commander_skills_rhs: u32, reserve_lhs: Reserve, reserve_rhs: Reserve,
+            map: u8, battlefield: Battlefield) -> (FightResult,
+                Option<Vec<Move>>, Option<Vec<Move>>) {
+
+            assert!(is_reserve_valid(selection_lhs, reserve_lhs));
+            assert!(is_reserve_valid(selection_rhs, reserve_rhs));
+            assert!(are_skills_valid(&commanders, commander_lhs, commander_level_lhs, commander_skills_lhs));
+            assert!(are_skills_valid(&commanders, commander_rhs, commander_level_rhs, commander_skills_rhs));
 
             // Starting ship positions for both sides
-            let mut ship_positions_lhs: [i16; MAX_SHIPS] = [10, 11, 12, 13];
-            let mut ship_positions_rhs: [i16; MAX_SHIPS] = [-10, -11, -12, -13];
+            let starting_positions_lhs: [i16; MAX_SHIPS] = [10, 11, 12, 13];
+            let starting_positions_rhs: [i16; MAX_SHIPS] = [-10, -11, -12, -13];
+            let mut ship_positions_lhs: [i16; MAX_SHIPS] = starting_positions_lhs;
+            let mut ship_positions_rhs: [i16; MAX_SHIPS] = starting_positions_rhs;
+            // Ships committed to the battle so far, reserves are added when they arrive
+            let mut committed_lhs: [u8; MAX_SHIPS] = selection_lhs;
+            let mut committed_rhs: [u8; MAX_SHIPS] = selection_rhs;
+            let mut reserve_lhs_arrived: bool = false;
+            let mut reserve_rhs_arrived: bool = false;
             // Current ship HPs, per ship type
             let mut ship_hps_lhs: [i32; MAX_SHIPS] = [0; MAX_SHIPS];
             let mut ship_hps_rhs: [i32; MAX_SHIPS] = [0; MAX_SHIPS];
+            // HPs which left the battlefield, per ship type
+            let mut routed_hps_lhs: [i32; MAX_SHIPS] = [0; MAX_SHIPS];
+            let mut routed_hps_rhs: [i32; MAX_SHIPS] = [0; MAX_SHIPS];
+            let mut lhs_retreated: bool = false;
+            let mut rhs_retreated: bool = false;
+            // Current ship shields, per ship type
+            let mut ship_shields_lhs: [u32; MAX_SHIPS] = [0; MAX_SHIPS];
+            let mut ship_shields_rhs: [u32; MAX_SHIPS] = [0; MAX_SHIPS];
+            // Move types of the shots, per ship type
+            let mut shot_types: [u8; MAX_SHIPS] = [MOVE_SHOOT; MAX_SHIPS];
             // Precalculated variable damage coefficients
             let mut variables_lhs: [u16; MAX_SHIPS] = [0; MAX_SHIPS];
             let mut variables_rhs: [u16; MAX_SHIPS] = [0; MAX_SHIPS];
-
-            // Precalculate the variables and initialize the ship HPs
+            // Commander bonuses, in percent
+            let (attack_bonus_lhs, defence_bonus_lhs) = self.get_commander_bonus(&commanders,
+                commander_lhs, commander_level_lhs);
+            let (attack_bonus_rhs, defence_bonus_rhs) = self.get_commander_bonus(&commanders,
+                commander_rhs, commander_level_rhs);
+            let (range_bonus_lhs, evasion_bonus_lhs, damage_bonus_lhs) = self.get_skill_bonuses(&commanders,
+                commander_lhs, commander_skills_lhs);
+            let (range_bonus_rhs, evasion_bonus_rhs, damage_bonus_rhs) = self.get_skill_bonuses(&commanders,
+                commander_rhs, commander_skills_rhs);
+
+            // Precalculate the variables and initialize the ship HPs and shields
             for i in 0..MAX_SHIPS {
+                let (shield_capacity, _) = self.get_shield(&ships[i]);
                 ship_hps_lhs[i] = (ships[i].hp as i32) * (selection_lhs[i] as i32);
                 ship_hps_rhs[i] = (ships[i].hp as i32) * (selection_rhs[i] as i32);
+                ship_shields_lhs[i] = shield_capacity * (selection_lhs[i] as u32);
+                ship_shields_rhs[i] = shield_capacity * (selection_rhs[i] as u32);
+                if self.get_armor_piercing(&ships[i]) > 0 {
+                    shot_types[i] = MOVE_ARMOR_PIERCING;
+                }
                 variables_lhs[i] = (seed % ships[i].attack_variable as u64) as u16;
                 variables_rhs[i] = ((seed / 2) % ships[i].attack_variable as u64) as u16;
             }
 
             let mut lhs_moves: Option<Vec<Move>> = None;
             let mut rhs_moves: Option<Vec<Move>> = None;
+            let mut stats_lhs: Option<FightStats> = None;
+            let mut stats_rhs: Option<FightStats> = None;
+            let mut random_state: u64 = seed ^ 0x9E37_79B9_7F4A_7C15;
             let mut total_rounds: u8 = 0;
 
             // Only initialize the moves when required, to save gas
@@ -483,15 +2224,44 @@ mod newomega {
                 rhs_moves = Some(Vec::new());
             }
 
+            // Same for the statistics
+            if log_stats {
+                stats_lhs = Some(FightStats::default());
+                stats_rhs = Some(FightStats::default());
+            }
+
             // Loop intented to be broken out of if resolution is found quicker than MAX_ROUNDS
             for round in 0..MAX_ROUNDS {
-                if self.is_dead(ship_hps_lhs) || self.is_dead(ship_hps_rhs) {
+                let round_u8: u8 = round as u8;
+
+                // Bring in the reserves, unless the fight is already decided
+                if !lhs_retreated && !rhs_retreated && !self.is_dead(ship_hps_rhs) && !reserve_lhs_arrived &&
+                    self.is_reserve_due(&ships, reserve_lhs, round_u8, committed_lhs, ship_hps_lhs) {
+
+                    self.apply_reserve(&ships, reserve_lhs, round_u8, starting_positions_lhs, &mut committed_lhs,
+                        &mut ship_hps_lhs, &mut ship_shields_lhs, &mut ship_positions_lhs, &mut lhs_moves);
+                    reserve_lhs_arrived = true;
+                }
+                if !lhs_retreated && !rhs_retreated && !self.is_dead(ship_hps_lhs) && !reserve_rhs_arrived &&
+                    self.is_reserve_due(&ships, reserve_rhs, round_u8, committed_rhs, ship_hps_rhs) {
+
+                    self.apply_reserve(&ships, reserve_rhs, round_u8, starting_positions_rhs, &mut committed_rhs,
+                        &mut ship_hps_rhs, &mut ship_shields_rhs, &mut ship_positions_rhs, &mut rhs_moves);
+                    reserve_rhs_arrived = true;
+                }
+
+                if self.is_dead(ship_hps_lhs) || self.is_dead(ship_hps_rhs) || lhs_retreated || rhs_retreated {
                     break;
                 }
 
-                let round_u8: u8 = round as u8;
                 total_rounds = total_rounds + 1;
 
+                // Regenerate shields and repair, at the start of the round
+                self.apply_round_abilities(&ships, round_u8, committed_lhs, routed_hps_lhs, &mut ship_hps_lhs,
+                    &mut ship_shields_lhs, &mut lhs_moves);
+                self.apply_round_abilities(&ships, round_u8, committed_rhs, routed_hps_rhs, &mut ship_hps_rhs,
+                    &mut ship_shields_rhs, &mut rhs_moves);
+
                 // Loop through all the ships
                 for current_ship in 0..MAX_SHIPS {
                     let current_ship_u8: u8 = current_ship as u8;
@@ -503,22 +2273,36 @@ mod newomega {
                     let mut rhs_damage: u32 = 0;
                     let mut lhs_target: u8 = 0;
                     let mut rhs_target: u8 = 0;
+                    let mut lhs_shot_type: u8 = MOVE_SHOOT;
+                    let mut rhs_shot_type: u8 = MOVE_SHOOT;
                     let mut lhs_delta_move: u8 = 0;
                     let mut rhs_delta_move: u8 = 0;
+                    let mut lhs_speed: u8 = 0;
+                    let rhs_position: i16 = ship_positions_rhs[current_ship];
 
                     // Note, moving and dealing damage to attacker is delayed until defender has moved also
                     if !lhs_dead_ship {
-                        (lhs_has_target, lhs_target, lhs_delta_move) = self.get_target(
-                            &ships, current_ship_u8, ship_positions_lhs, ship_positions_rhs, ship_hps_rhs);
+                        let lhs_range: u8 = self.get_range(&battlefield,
+                            ships[current_ship].range.saturating_add(range_bonus_lhs[current_ship]),
+                            ship_positions_lhs[current_ship]);
+                        lhs_speed = self.get_speed(&battlefield, ships[current_ship].speed,
+                            ship_positions_lhs[current_ship]);
+                        (lhs_has_target, lhs_target, lhs_delta_move) = self.get_target(current_ship_u8,
+                            lhs_range, lhs_speed, ship_positions_lhs, ship_positions_rhs, ship_hps_rhs);
 
                         if lhs_has_target {
                             lhs_damage = self.calculate_damage(variables_lhs, variants_lhs, variants_rhs,
-                                &ships, current_ship_u8, lhs_target, ship_hps_lhs[current_ship] as u32);
+                                &ships, current_ship_u8, lhs_target, ship_hps_lhs[current_ship] as u32,
+                                attack_bonus_lhs + damage_bonus_lhs[lhs_target as usize], defence_bonus_rhs);
+                            (lhs_damage, lhs_shot_type) = self.resolve_shot(&ships, &mut random_state,
+                                current_ship_u8, lhs_target, lhs_damage, shot_types[current_ship],
+                                if round == 0 { evasion_bonus_rhs } else { 0 });
 
                             // Log the move, if required
                             match lhs_moves {
                                 Some(ref mut moves) =>
-                                    self.log_shoot(round_u8, moves, current_ship_u8, lhs_target, lhs_damage,
+                                    self.log_shoot(round_u8, moves, lhs_shot_type, current_ship_u8,
+                                        lhs_target, lhs_damage,
                                         ship_positions_lhs[current_ship] - (lhs_delta_move as i16)),
                                 _ => ()
                             }
@@ -527,34 +2311,45 @@ mod newomega {
                             match lhs_moves {
                                 Some(ref mut moves) =>
                                     self.log_move(round_u8, moves, current_ship_u8, ship_positions_lhs[current_ship] -
-                                        (ships[current_ship].speed as i16)),
+                                        (lhs_speed as i16)),
                                 _ => ()
                             }
                         }
                     }
 
                     if !rhs_dead_ship {
-                        (rhs_has_target, rhs_target, rhs_delta_move) = self.get_target(
-                            &ships, current_ship_u8, ship_positions_rhs, ship_positions_lhs, ship_hps_lhs);
+                        let rhs_range: u8 = self.get_range(&battlefield,
+                            ships[current_ship].range.saturating_add(range_bonus_rhs[current_ship]), rhs_position);
+                        let rhs_speed: u8 = self.get_speed(&battlefield, ships[current_ship].speed, rhs_position);
+                        (rhs_has_target, rhs_target, rhs_delta_move) = self.get_target(current_ship_u8,
+                            rhs_range, rhs_speed, ship_positions_rhs, ship_positions_lhs, ship_hps_lhs);
 
                         if rhs_has_target {
                             rhs_damage = self.calculate_damage(variables_rhs, variants_rhs, variants_lhs,
-                                &ships, current_ship_u8, rhs_target, ship_hps_rhs[current_ship] as u32);
+                                &ships, current_ship_u8, rhs_target, ship_hps_rhs[current_ship] as u32,
+                                attack_bonus_rhs + damage_bonus_rhs[rhs_target as usize], defence_bonus_lhs);
+                            (rhs_damage, rhs_shot_type) = self.resolve_shot(&ships, &mut random_state,
+                                current_ship_u8, rhs_target, rhs_damage, shot_types[current_ship],
+                                if round == 0 { evasion_bonus_lhs } else { 0 });
 
-                            // Move the ships, apply the damage
-                            ship_hps_lhs[rhs_target as usize] -= rhs_damage as i32;
+                            // Move the ships
                             ship_positions_rhs[current_ship] += rhs_delta_move as i16;
 
                             // Log the move, if required
                             match rhs_moves {
                                 Some(ref mut moves) =>
-                                    self.log_shoot(round_u8, moves, current_ship_u8, rhs_target, rhs_damage,
-                                        ship_positions_rhs[current_ship]),
+                                    self.log_shoot(round_u8, moves, rhs_shot_type, current_ship_u8,
+                                        rhs_target, rhs_damage, ship_positions_rhs[current_ship]),
                                 _ => ()
                             }
+
+                            // Apply the damage
+                            self.apply_shot(&ships, round_u8, current_ship_u8, rhs_target, rhs_damage,
+                                &mut ship_hps_lhs, &mut ship_shields_lhs, &mut rhs_moves, &mut lhs_moves,
+                                &mut stats_rhs, &mut stats_lhs);
                         } else {
                             // Move the ships
-                            ship_positions_rhs[current_ship] += ships[current_ship].speed as i16;
+                            ship_positions_rhs[current_ship] += rhs_speed as i16;
 
                             // Log the move, if required
                             match rhs_moves {
@@ -564,52 +2359,90 @@ mod newomega {
                                 _ => ()
                             }
                         }
+
+                        self.apply_minefields(&ships, &battlefield, round_u8, current_ship_u8, rhs_position,
+                            ship_positions_rhs[current_ship], &mut ship_hps_rhs, &mut rhs_moves);
                     }
 
                     ///Now applying attacker moves
                     if !lhs_dead_ship {
+                        let lhs_position: i16 = ship_positions_lhs[current_ship];
+
                         if lhs_has_target {
                             // Move the ships, apply the damage
-                            ship_hps_rhs[lhs_target as usize] -= lhs_damage as i32;
+                            self.apply_shot(&ships, round_u8, current_ship_u8, lhs_target, lhs_damage,
+                                &mut ship_hps_rhs, &mut ship_shields_rhs, &mut lhs_moves, &mut rhs_moves,
+                                &mut stats_lhs, &mut stats_rhs);
                             ship_positions_lhs[current_ship] -= lhs_delta_move as i16;
                         } else {
                             // Move the ships
-                            ship_positions_lhs[current_ship] -= ships[current_ship].speed as i16;
+                            ship_positions_lhs[current_ship] -= lhs_speed as i16;
                         }
+
+                        self.apply_minefields(&ships, &battlefield, round_u8, current_ship_u8, lhs_position,
+                            ship_positions_lhs[current_ship], &mut ship_hps_lhs, &mut lhs_moves);
                     }
                 }
+
+                // Rout and retreat, at the end of the round
+                let (lhs_round_retreated, rhs_round_retreated) = self.apply_round_morale(&ships, morale,
+                    round_u8, committed_lhs, committed_rhs, &mut ship_hps_lhs, &mut ship_hps_rhs,
+                    &mut routed_hps_lhs, &mut routed_hps_rhs, &mut lhs_moves, &mut rhs_moves);
+                lhs_retreated = lhs_round_retreated;
+                rhs_retreated = rhs_round_retreated;
             }
 
-            /// Calculate ships lost according to HPs left
+            /// Calculate ships lost according to HPs left, routed ships and reserves which never arrived survive
             let mut ships_lost_lhs: [u8; MAX_SHIPS] = [0; MAX_SHIPS];
             let mut ships_lost_rhs: [u8; MAX_SHIPS] = [0; MAX_SHIPS];
             for i in 0..MAX_SHIPS {
-                let safe_hp_lhs: u32 = self.max(ship_hps_lhs[i], 0) as u32;
-                let safe_hp_rhs: u32 = self.max(ship_hps_rhs[i], 0) as u32;
-                ships_lost_lhs[i] = (((selection_lhs[i] as u32 * ships[i].hp as u32) - safe_hp_lhs) / ships[i].hp as u32) as u8;
-                ships_lost_rhs[i] = (((selection_rhs[i] as u32 * ships[i].hp as u32) - safe_hp_rhs) / ships[i].hp as u32) as u8;
+                let safe_hp_lhs: u32 = (self.max(ship_hps_lhs[i], 0) + routed_hps_lhs[i]) as u32;
+                let safe_hp_rhs: u32 = (self.max(ship_hps_rhs[i], 0) + routed_hps_rhs[i]) as u32;
+                ships_lost_lhs[i] = ((committed_lhs[i] as u32 * ships[i].hp as u32).saturating_sub(safe_hp_lhs)
+                    / ships[i].hp as u32) as u8;
+                ships_lost_rhs[i] = ((committed_rhs[i] as u32 * ships[i].hp as u32).saturating_sub(safe_hp_rhs)
+                    / ships[i].hp as u32) as u8;
             }
 
+            self.finalize_stats(&mut stats_lhs);
+            self.finalize_stats(&mut stats_rhs);
+
             let mut total_rhs_ships: u16 = 0;
             for i in 0..MAX_SHIPS {
-                total_rhs_ships += selection_rhs[i] as u16;
+                total_rhs_ships += selection_rhs[i] as u16 + reserve_rhs.selection[i] as u16;
             }
 
-            let result: FightResult = FightResult {
+            let ships_hash: [u8; 32] = hash_ships(&ships);
+            let mut result: FightResult = FightResult {
                 selection_lhs: selection_lhs,
                 selection_rhs: selection_rhs,
                 variants_lhs: variants_lhs,
                 variants_rhs: variants_rhs,
                 commander_lhs: commander_lhs,
                 commander_rhs: commander_rhs,
-                lhs_dead: total_rhs_ships > 0 && self.is_dead(ship_hps_lhs),
-                rhs_dead: self.is_dead(ship_hps_rhs),
+                commander_level_lhs: commander_level_lhs,
+                commander_level_rhs: commander_level_rhs,
+                commander_skills_lhs: commander_skills_lhs,
+                commander_skills_rhs: commander_skills_rhs,
+                reserve_lhs: reserve_lhs,
+                reserve_rhs: reserve_rhs,
+                lhs_dead: total_rhs_ships > 0 && self.is_dead(ship_hps_lhs) && !lhs_retreated,
+                rhs_dead: self.is_dead(ship_hps_rhs) && !rhs_retreated,
+                lhs_retreated: lhs_retreated,
+                rhs_retreated: rhs_retreated,
+                morale: morale,
+                map: map,
                 ships_lost_lhs: ships_lost_lhs,
                 ships_lost_rhs: ships_lost_rhs,
                 rounds: total_rounds,
-                seed: seed
+                seed: seed,
+                hash: [0; 32],
+                stats_lhs: stats_lhs,
+                stats_rhs: stats_rhs
             };
 
+            result.hash = fight_hash(ships_hash, &result);
+
             (result, lhs_moves, rhs_moves)
         }
     }
@@ -632,9 +2465,11 @@ mod newomega {
             let commander_lhs: u8 = 0;
             let commander_rhs: u8 = 1;
 
-            let (result, _moves_lhs, _moves_rhs) = contract.fight(seed, log_moves, ships,
+            let (result, _moves_lhs, _moves_rhs) = contract.fight(seed, log_moves, false, ships,
+                prepare_commanders(), Morale::default(),
                 selection_lhs, selection_rhs, variants_lhs, variants_rhs,
-                commander_lhs, commander_rhs);
+                commander_lhs, commander_rhs, 0, 0, 0, 0,
+                Reserve::default(), Reserve::default(), 0, prepare_maps()[0].clone());
 
             assert!(result.rhs_dead);
         }
@@ -650,21 +2485,667 @@ mod newomega {
             let target: u8 = 0;
             let source_hp: u32 = ships[source as usize].hp as u32;
             let damage: u32 = contract.calculate_damage(variables, variants_source,
-                variants_target, &ships, source, target, source_hp);
+                variants_target, &ships, source, target, source_hp, 0, 0);
 
             let source_hp_damaged: u32 = source_hp - 1;
             let damage_damaged: u32 = contract.calculate_damage(variables, variants_source,
-                variants_target, &ships, source, target, source_hp_damaged);
+                variants_target, &ships, source, target, source_hp_damaged, 0, 0);
 
             let source_hp_bigstack: u32 = source_hp * 32;
             let damage_bigstack: u32 = contract.calculate_damage(variables, variants_source,
-                variants_target, &ships, source, target, source_hp_bigstack);
+                variants_target, &ships, source, target, source_hp_bigstack, 0, 0);
 
             assert_eq!(damage, 80);
             assert_eq!(damage_damaged, 80);
             assert_eq!(damage_bigstack, 80 * 32);
         }
 
+        #[test]
+        fn test_replay_roundtrip() {
+            let contract = NewOmega::default();
+            let ships: Vec<Ship> = prepare_ships();
+            let ships_hash: [u8; 32] = hash_ships(&ships);
+
+            let (result, moves_lhs, moves_rhs) = contract.fight(1337, true, false, ships,
+                prepare_commanders(), Morale::default(),
+                [20, 20, 20, 20], [5, 5, 5, 5], [0, 1, 2, 0], [1, 0, 1, 2], 0, 1, 0, 0, 0, 0,
+                Reserve::default(), Reserve::default(), 0, prepare_maps()[0].clone());
+            let moves_lhs: Vec<Move> = moves_lhs.unwrap();
+            let moves_rhs: Vec<Move> = moves_rhs.unwrap();
+
+            let encoded: Vec<u8> = encode_replay(ships_hash, &result, &moves_lhs, &moves_rhs);
+            let replay: Replay = decode_replay(&encoded).unwrap();
+
+            assert_eq!(replay.ruleset_version, RULESET_VERSION);
+            assert_eq!(replay.ships_hash, ships_hash);
+            assert_eq!(replay.result, result);
+            assert_eq!(replay.moves_lhs, moves_lhs);
+            assert_eq!(replay.moves_rhs, moves_rhs);
+            // Must be smaller than the raw 12 byte per move representation
+            assert!(encoded.len() < (moves_lhs.len() + moves_rhs.len()) * 12);
+        }
+
+        #[test]
+        fn test_replay_rejects_malformed() {
+            let contract = NewOmega::default();
+            let ships: Vec<Ship> = prepare_ships();
+            let ships_hash: [u8; 32] = hash_ships(&ships);
+
+            let (result, moves_lhs, moves_rhs) = contract.fight(42, true, false, ships,
+                prepare_commanders(), Morale::default(),
+                [3, 3, 3, 3], [3, 3, 3, 3], [0, 0, 0, 0], [0, 0, 0, 0], 0, 0, 0, 0, 0, 0,
+                Reserve::default(), Reserve::default(), 0, prepare_maps()[0].clone());
+            let mut encoded: Vec<u8> = encode_replay(ships_hash, &result,
+                &moves_lhs.unwrap(), &moves_rhs.unwrap());
+
+            assert!(decode_replay(&encoded[..encoded.len() - 1]).is_none());
+            assert!(decode_replay(&[]).is_none());
+
+            encoded.push(0);
+            assert!(decode_replay(&encoded).is_none());
+
+            encoded.pop();
+            encoded[0] = REPLAY_FORMAT_VERSION + 1;
+            assert!(decode_replay(&encoded).is_none());
+        }
+
+        #[test]
+        fn test_fight_hash() {
+            let contract = NewOmega::default();
+            let selection_lhs: [u8; MAX_SHIPS] = [10, 0, 5, 1];
+            let selection_rhs: [u8; MAX_SHIPS] = [2, 8, 0, 3];
+            let variants: [u8; MAX_SHIPS] = [0, 1, 2, 0];
+
+            let (result, _, _) = contract.fight(7, false, false, prepare_ships(),
+                prepare_commanders(), Morale::default(),
+                selection_lhs, selection_rhs, variants, variants, 0, 1, 0, 0, 0, 0,
+                Reserve::default(), Reserve::default(), 0, prepare_maps()[0].clone());
+            let (result_replayed, moves_lhs, moves_rhs) = contract.fight(7, true, false, prepare_ships(),
+                prepare_commanders(), Morale::default(),
+                selection_lhs, selection_rhs, variants, variants, 0, 1, 0, 0, 0, 0,
+                Reserve::default(), Reserve::default(), 0, prepare_maps()[0].clone());
+            let (result_other_seed, _, _) = contract.fight(8, false, false, prepare_ships(),
+                prepare_commanders(), Morale::default(),
+                selection_lhs, selection_rhs, variants, variants, 0, 1, 0, 0, 0, 0,
+                Reserve::default(), Reserve::default(), 0, prepare_maps()[0].clone());
+
+            assert_ne!(result.hash, [0; 32]);
+            assert_eq!(result.hash, result_replayed.hash);
+            assert_ne!(result.hash, result_other_seed.hash);
+
+            // A decoded replay must commit to the same hash
+            let encoded: Vec<u8> = encode_replay(hash_ships(&prepare_ships()), &result_replayed,
+                &moves_lhs.unwrap(), &moves_rhs.unwrap());
+            assert_eq!(decode_replay(&encoded).unwrap().result.hash, result.hash);
+
+            // Different ship definitions must change the hash
+            let mut ships: Vec<Ship> = prepare_ships();
+            ships[0].hp = ships[0].hp + 1;
+            let (result_other_ships, _, _) = contract.fight(7, false, false, ships,
+                prepare_commanders(), Morale::default(),
+                selection_lhs, selection_rhs, variants, variants, 0, 1, 0, 0, 0, 0,
+                Reserve::default(), Reserve::default(), 0, prepare_maps()[0].clone());
+            assert_ne!(result.hash, result_other_ships.hash);
+        }
+
+        #[test]
+        fn test_ships_hash() {
+            // Ships with the default precision stats keep the hash they had before the stats existed
+            let baseline: [u8; 32] = [18, 41, 95, 222, 20, 130, 110, 124, 94, 174, 209, 98, 107, 127, 151, 252,
+                98, 19, 172, 36, 68, 222, 111, 221, 94, 119, 136, 9, 252, 51, 34, 37];
+            assert_eq!(hash_ships(&prepare_ships()), baseline);
+
+            // Any precision stat which is not the default is part of the hash
+            let mut ships: Vec<Ship> = prepare_ships();
+            ships[2].evasion = 10;
+            let evasive_hash: [u8; 32] = hash_ships(&ships);
+            assert_ne!(evasive_hash, baseline);
+
+            ships[2].evasion = DEFAULT_EVASION;
+            ships[2].crit_chance = 10;
+            assert_ne!(hash_ships(&ships), baseline);
+            assert_ne!(hash_ships(&ships), evasive_hash);
+
+            ships[2].crit_chance = DEFAULT_CRIT_CHANCE;
+            assert_eq!(hash_ships(&ships), baseline);
+        }
+
+        #[test]
+        fn test_commit_fleet() {
+            let selection: [u8; MAX_SHIPS] = [10, 0, 5, 1];
+            let variants: [u8; MAX_SHIPS] = [0, 1, 2, 0];
+            let commitment: [u8; 32] = commit_fleet(selection, variants, 0, Reserve::default(), [1; 32]);
+
+            assert_eq!(commitment, commit_fleet(selection, variants, 0, Reserve::default(), [1; 32]));
+            // Any change to the fleet or the salt must change the commitment
+            assert_ne!(commitment, commit_fleet(selection, variants, 0, Reserve::default(), [2; 32]));
+            assert_ne!(commitment, commit_fleet(selection, variants, 1, Reserve::default(), [1; 32]));
+            assert_ne!(commitment, commit_fleet([10, 0, 5, 2], variants, 0, Reserve::default(), [1; 32]));
+            assert_ne!(commitment, commit_fleet(selection, variants, 0, Reserve {
+                selection: [1, 0, 0, 0],
+                round: 2,
+                threshold: 0,
+            }, [1; 32]));
+        }
+
+        #[test]
+        fn test_fight_stats() {
+            let contract = NewOmega::default();
+            let ships: Vec<Ship> = prepare_ships();
+            let selection_rhs: [u8; MAX_SHIPS] = [5, 5, 5, 5];
+            let mut total_hp_rhs: u32 = 0;
+            for i in 0..MAX_SHIPS {
+                total_hp_rhs += ships[i].hp as u32 * selection_rhs[i] as u32;
+            }
+
+            let (result, moves_lhs, _) = contract.fight(1337, false, true, ships,
+                prepare_commanders(), Morale::default(),
+                [20, 20, 20, 20], selection_rhs, [0, 1, 2, 0], [1, 0, 1, 2], 0, 1, 0, 0, 0, 0,
+                Reserve::default(), Reserve::default(), 0, prepare_maps()[0].clone());
+            let stats_lhs: FightStats = result.stats_lhs.unwrap();
+            let stats_rhs: FightStats = result.stats_rhs.unwrap();
+
+            assert!(moves_lhs.is_none());
+            assert!(result.rhs_dead);
+            assert_eq!(stats_lhs.damage_dealt.iter().sum::<u32>(), total_hp_rhs);
+            assert_eq!(stats_rhs.damage_received.iter().sum::<u32>(), total_hp_rhs);
+            assert_eq!(stats_rhs.damage_dealt.iter().sum::<u32>(),
+                stats_lhs.damage_received.iter().sum::<u32>());
+            assert!(stats_lhs.shots_fired.iter().sum::<u32>() > 0);
+            assert!(stats_lhs.first_blood_round.is_some());
+            assert!(stats_lhs.first_blood_round.unwrap() < result.rounds);
+
+            for i in 0..MAX_SHIPS {
+                assert!(stats_lhs.damage_dealt[stats_lhs.most_valuable_ship as usize] >= stats_lhs.damage_dealt[i]);
+            }
+
+            let (result_no_stats, _, _) = contract.fight(1337, false, false, prepare_ships(),
+                prepare_commanders(), Morale::default(),
+                [20, 20, 20, 20], selection_rhs, [0, 1, 2, 0], [1, 0, 1, 2], 0, 1, 0, 0, 0, 0,
+                Reserve::default(), Reserve::default(), 0, prepare_maps()[0].clone());
+
+            assert!(result_no_stats.stats_lhs.is_none());
+            assert!(result_no_stats.stats_rhs.is_none());
+            assert_eq!(result_no_stats.hash, result.hash);
+        }
+
+        #[test]
+        fn test_armor_piercing() {
+            let contract = NewOmega::default();
+            let mut ships: Vec<Ship> = prepare_ships();
+            let variants: [u8; MAX_SHIPS] = [0, 0, 0, 0];
+            let variables: [u16; MAX_SHIPS] = [0, 0, 0, 0];
+            let source_hp: u32 = ships[0].hp as u32;
+
+            let damage: u32 = contract.calculate_damage(variables, variants, variants,
+                &ships, 0, 1, source_hp, 0, 0);
+            ships[0].abilities.push(Ability::ArmorPiercing { percent: 50 });
+            let damage_piercing: u32 = contract.calculate_damage(variables, variants, variants,
+                &ships, 0, 1, source_hp, 0, 0);
+
+            assert_eq!(damage, 80 - 30);
+            assert_eq!(damage_piercing, 80 - 15);
+        }
+
+        #[test]
+        fn test_damage_blocked() {
+            let contract = NewOmega::default();
+            let ships: Vec<Ship> = prepare_ships();
+            let variables: [u16; MAX_SHIPS] = [0, 0, 0, 0];
+            let source_hp: u32 = ships[1].hp as u32;
+
+            // 65 - 20 attack against 40 + 20 defence, boosted by +50% defence
+            let damage: u32 = contract.calculate_damage(variables, [1, 1, 1, 1], [1, 1, 1, 1],
+                &ships, 1, 3, source_hp, 0, 50);
+            assert_eq!(damage, 0);
+        }
+
+        #[test]
+        fn test_abilities() {
+            let contract = NewOmega::default();
+            let mut ships: Vec<Ship> = prepare_ships();
+            ships[0].abilities.push(Ability::Shield { capacity: 30, regeneration: 10 });
+            ships[1].abilities.push(Ability::ArmorPiercing { percent: 25 });
+            ships[2].abilities.push(Ability::Splash { percent: 50 });
+            ships[3].abilities.push(Ability::Repair { amount: 40 });
+
+            let (result, moves_lhs, moves_rhs) = contract.fight(1337, true, true, ships,
+                prepare_commanders(), Morale::default(),
+                [10, 10, 10, 10], [10, 10, 10, 10], [0, 0, 0, 0], [0, 0, 0, 0], 0, 0, 0, 0, 0, 0,
+                Reserve::default(), Reserve::default(), 0, prepare_maps()[0].clone());
+            let moves: Vec<Move> = moves_lhs.unwrap().into_iter().chain(moves_rhs.unwrap().into_iter()).collect();
+            let count = |move_type: u8| moves.iter().filter(|m| m.move_type == move_type).count();
+
+            assert!(result.rounds > 0);
+            assert!(count(MOVE_SHIELD) > 0);
+            assert!(count(MOVE_SHIELD_REGENERATION) > 0);
+            assert!(count(MOVE_ARMOR_PIERCING) > 0);
+            assert!(count(MOVE_SPLASH) > 0);
+            assert!(count(MOVE_REPAIR) > 0);
+
+            // Only the shields of the first ship type absorb damage, and only its own stack regenerates
+            assert!(moves.iter().filter(|m| m.move_type == MOVE_SHIELD).all(|m| m.source == 0));
+            assert!(moves.iter().filter(|m| m.move_type == MOVE_SHIELD_REGENERATION).all(|m| m.source == 0));
+            // Only the third ship type deals splash damage
+            assert!(moves.iter().filter(|m| m.move_type == MOVE_SPLASH).all(|m| m.source == 2));
+        }
+
+        #[test]
+        fn test_accuracy_and_criticals() {
+            let contract = NewOmega::default();
+            let mut ships: Vec<Ship> = prepare_ships();
+            let mut random_state: u64 = 1337;
+
+            // Default stats never miss nor crit, and do not consume randomness
+            assert_eq!(contract.resolve_shot(&ships, &mut random_state, 0, 1, 50, MOVE_SHOOT, 0), (50, MOVE_SHOOT));
+            assert_eq!(random_state, 1337);
+
+            // Fully evasive targets are never hit
+            ships[1].evasion = 100;
+            for _ in 0..20 {
+                assert_eq!(contract.resolve_shot(&ships, &mut random_state, 0, 1, 50, MOVE_SHOOT, 0), (0, MOVE_MISS));
+            }
+
+            // Guaranteed criticals
+            ships[0].crit_chance = 100;
+            ships[0].crit_multiplier = 150;
+            for _ in 0..20 {
+                assert_eq!(contract.resolve_shot(&ships, &mut random_state, 0, 2, 50, MOVE_SHOOT, 0),
+                    (75, MOVE_CRITICAL));
+            }
+
+            // Critical damage saturates instead of overflowing
+            ships[0].crit_multiplier = u16::MAX;
+            assert_eq!(contract.resolve_shot(&ships, &mut random_state, 0, 2, u32::MAX, MOVE_SHOOT, 0),
+                (u32::MAX / 100, MOVE_CRITICAL));
+
+            // Partial accuracy misses some, but not all, shots
+            ships[0].crit_chance = 0;
+            ships[0].accuracy = 50;
+            let misses: usize = (0..100)
+                .filter(|_| contract.resolve_shot(&ships, &mut random_state, 0, 2, 50, MOVE_SHOOT, 0).1 == MOVE_MISS)
+                .count();
+            assert!(misses > 0 && misses < 100);
+        }
+
+        #[test]
+        fn test_morale() {
+            let contract = NewOmega::default();
+            let morale: Morale = Morale {
+                rout_threshold: 50,
+                retreat_threshold: 60,
+            };
+
+            let (result, moves_lhs, moves_rhs) = contract.fight(1337, true, false, prepare_ships(),
+                prepare_commanders(), morale,
+                [20, 20, 20, 20], [5, 5, 5, 5], [0, 1, 2, 0], [1, 0, 1, 2], 0, 1, 0, 0, 0, 0,
+                Reserve::default(), Reserve::default(), 0, prepare_maps()[0].clone());
+            let moves_rhs: Vec<Move> = moves_rhs.unwrap();
+
+            // Outnumbered, the defender retreats instead of being wiped out
+            assert!(result.rhs_retreated);
+            assert!(!result.rhs_dead);
+            assert!(!result.lhs_retreated);
+            assert!(!result.lhs_dead);
+            assert!(moves_rhs.iter().any(|m| m.move_type == MOVE_ROUT));
+            assert_eq!(moves_rhs.iter().filter(|m| m.move_type == MOVE_RETREAT).count(), 1);
+            assert!(moves_lhs.unwrap().iter().all(|m| m.move_type != MOVE_RETREAT));
+
+            // Routed ships are not lost
+            let (result_no_morale, _, _) = contract.fight(1337, false, false, prepare_ships(),
+                prepare_commanders(), Morale::default(),
+                [20, 20, 20, 20], [5, 5, 5, 5], [0, 1, 2, 0], [1, 0, 1, 2], 0, 1, 0, 0, 0, 0,
+                Reserve::default(), Reserve::default(), 0, prepare_maps()[0].clone());
+            assert!(result_no_morale.rhs_dead);
+            assert!(result.ships_lost_rhs.iter().sum::<u8>() < result_no_morale.ships_lost_rhs.iter().sum::<u8>());
+            assert!(result.rounds < result_no_morale.rounds);
+        }
+
+        #[test]
+        fn test_morale_destroyed() {
+            let contract = NewOmega::default();
+            let morale: Morale = Morale {
+                rout_threshold: 0,
+                retreat_threshold: 100,
+            };
+
+            // A fleet wiped out with retreat enabled is destroyed, not retreated
+            let (result, _, moves_rhs) = contract.fight(1337, true, false, prepare_ships(),
+                prepare_commanders(), morale,
+                [20, 20, 20, 20], [5, 5, 5, 5], [0, 1, 2, 0], [1, 0, 1, 2], 0, 1, 0, 0, 0, 0,
+                Reserve::default(), Reserve::default(), 0, prepare_maps()[0].clone());
+            let (result_no_morale, _, _) = contract.fight(1337, false, false, prepare_ships(),
+                prepare_commanders(), Morale::default(),
+                [20, 20, 20, 20], [5, 5, 5, 5], [0, 1, 2, 0], [1, 0, 1, 2], 0, 1, 0, 0, 0, 0,
+                Reserve::default(), Reserve::default(), 0, prepare_maps()[0].clone());
+
+            assert!(result.rhs_dead);
+            assert!(!result.rhs_retreated);
+            assert!(!result.lhs_retreated);
+            assert!(moves_rhs.unwrap().iter().all(|m| m.move_type != MOVE_RETREAT));
+            assert_eq!(result.ships_lost_rhs, result_no_morale.ships_lost_rhs);
+            assert_eq!(result.rounds, result_no_morale.rounds);
+        }
+
+        #[test]
+        fn test_morale_annihilation() {
+            let contract = NewOmega::default();
+            let ships: Vec<Ship> = prepare_ships();
+            let morale: Morale = Morale {
+                rout_threshold: 0,
+                retreat_threshold: 50,
+            };
+            let selection: [u8; MAX_SHIPS] = [10, 10, 10, 10];
+            let mut moves_lhs: Option<Vec<Move>> = Some(Vec::new());
+            let mut moves_rhs: Option<Vec<Move>> = Some(Vec::new());
+            let mut routed_hps_lhs: [i32; MAX_SHIPS] = [0; MAX_SHIPS];
+            let mut routed_hps_rhs: [i32; MAX_SHIPS] = [0; MAX_SHIPS];
+
+            // The attacker crossed its retreat threshold in the round it annihilated the defender
+            let mut hps_lhs: [i32; MAX_SHIPS] = [0; MAX_SHIPS];
+            for i in 0..MAX_SHIPS {
+                hps_lhs[i] = ships[i].hp as i32 * 3;
+            }
+            let mut hps_rhs: [i32; MAX_SHIPS] = [0; MAX_SHIPS];
+
+            assert_eq!(contract.apply_round_morale(&ships, morale, 5, selection, selection, &mut hps_lhs,
+                &mut hps_rhs, &mut routed_hps_lhs, &mut routed_hps_rhs, &mut moves_lhs, &mut moves_rhs),
+                (false, false));
+            assert!(moves_lhs.unwrap().is_empty());
+
+            // While both fleets stand, morale applies to both
+            let mut moves_lhs: Option<Vec<Move>> = Some(Vec::new());
+            hps_rhs[0] = ships[0].hp as i32 * 10;
+            assert_eq!(contract.apply_round_morale(&ships, morale, 5, selection, selection, &mut hps_lhs,
+                &mut hps_rhs, &mut routed_hps_lhs, &mut routed_hps_rhs, &mut moves_lhs, &mut moves_rhs),
+                (true, true));
+            assert_eq!(moves_lhs.unwrap()[0].move_type, MOVE_RETREAT);
+        }
+
+        #[test]
+        fn test_terrain() {
+            let contract = NewOmega::default();
+            let maps: Vec<Battlefield> = prepare_maps();
+
+            // Terrain modifiers only apply inside the feature, and never go below 1
+            assert_eq!(contract.get_speed(&maps[1], 3, 0), 2);
+            assert_eq!(contract.get_speed(&maps[1], 3, 10), 3);
+            assert_eq!(contract.get_speed(&maps[1], 1, 0), 1);
+            assert_eq!(contract.get_range(&maps[2], 5, -6), 3);
+            assert_eq!(contract.get_range(&maps[2], 5, 7), 5);
+            assert_eq!(contract.get_range(&maps[2], 2, 0), 1);
+
+            // The open space map reproduces the terrain-less fight
+            let (result, moves_lhs, _) = contract.fight(1337, true, false, prepare_ships(),
+                prepare_commanders(), Morale::default(),
+                [20, 20, 20, 20], [5, 5, 5, 5], [0, 1, 2, 0], [1, 0, 1, 2], 0, 1, 0, 0, 0, 0,
+                Reserve::default(), Reserve::default(), 0, maps[0].clone());
+            assert_eq!(result.map, 0);
+            let moves_open: Vec<Move> = moves_lhs.unwrap();
+            assert!(moves_open.iter().all(|m| m.move_type != MOVE_MINE));
+
+            // Crossing the minefield damages the fleets
+            let (result_mines, moves_lhs, moves_rhs) = contract.fight(1337, true, false, prepare_ships(),
+                prepare_commanders(),
+                Morale::default(), [20, 20, 20, 20], [5, 5, 5, 5], [0, 1, 2, 0], [1, 0, 1, 2], 0, 1, 0, 0, 0, 0,
+                Reserve::default(), Reserve::default(), 3, maps[3].clone());
+            assert_eq!(result_mines.map, 3);
+            assert!(moves_lhs.unwrap().iter().chain(moves_rhs.unwrap().iter())
+                .any(|m| m.move_type == MOVE_MINE && m.damage > 0));
+            assert_ne!(result.hash, result_mines.hash);
+
+            // Ships on the asteroid map can't cover the same distance in one move
+            let (_, moves_lhs, _) = contract.fight(1337, true, false, prepare_ships(),
+                prepare_commanders(), Morale::default(),
+                [20, 20, 20, 20], [5, 5, 5, 5], [0, 1, 2, 0], [1, 0, 1, 2], 0, 1, 0, 0, 0, 0,
+                Reserve::default(), Reserve::default(), 1, maps[1].clone());
+            let moves_asteroids: Vec<Move> = moves_lhs.unwrap();
+            let reposition = |moves: &Vec<Move>, round: u8, source: u8| moves
+                .iter()
+                .find(|m| m.move_type == MOVE_REPOSITION && m.round == round && m.source == source)
+                .unwrap()
+                .target_position;
+
+            // Outside of the asteroid field (from -4 to 4) the ships move as in open space
+            assert_eq!(reposition(&moves_open, 1, 0), 2);
+            assert_eq!(reposition(&moves_asteroids, 1, 0), 2);
+
+            // Starting the move inside the field, ships 0 (speed 4) and 1 (speed 3) are slowed down by 1
+            assert_eq!(reposition(&moves_open, 3, 0), -2);
+            assert_eq!(reposition(&moves_asteroids, 3, 0), -1);
+            assert_eq!(reposition(&moves_open, 3, 1), 0);
+            assert_eq!(reposition(&moves_asteroids, 3, 1), 1);
+        }
+
+        #[test]
+        fn test_reserves() {
+            let contract = NewOmega::default();
+            let ships: Vec<Ship> = prepare_ships();
+            let reserve_by_round: Reserve = Reserve {
+                selection: [10, 10, 10, 10],
+                round: 3,
+                threshold: 0,
+            };
+
+            // Without the reserve, the front line is wiped out
+            let (result_alone, _, _) = contract.fight(1337, false, false, ships.clone(),
+                prepare_commanders(), Morale::default(),
+                [5, 5, 5, 5], [10, 10, 10, 10], [0, 0, 0, 0], [0, 0, 0, 0], 0, 1, 0, 0, 0, 0,
+                Reserve::default(), Reserve::default(), 0, prepare_maps()[0].clone());
+            assert!(result_alone.lhs_dead);
+
+            // The reserve arrives at the chosen round, merged into its stacks
+            let (result, moves_lhs, moves_rhs) = contract.fight(1337, true, false, ships.clone(),
+                prepare_commanders(), Morale::default(),
+                [5, 5, 5, 5], [10, 10, 10, 10], [0, 0, 0, 0], [0, 0, 0, 0], 0, 1, 0, 0, 0, 0,
+                reserve_by_round, Reserve::default(), 0, prepare_maps()[0].clone());
+            let moves_lhs: Vec<Move> = moves_lhs.unwrap();
+            let reinforcements: Vec<&Move> = moves_lhs.iter().filter(|m| m.move_type == MOVE_REINFORCE).collect();
+            assert_eq!(reinforcements.len(), MAX_SHIPS);
+            assert!(reinforcements.iter().all(|m| m.round == 3 && m.target == 10));
+            assert!(moves_rhs.unwrap().iter().all(|m| m.move_type != MOVE_REINFORCE));
+            assert_ne!(result.hash, result_alone.hash);
+            assert!(result.rounds > 3);
+
+            // Reserves are part of the replay
+            let encoded: Vec<u8> = encode_replay(hash_ships(&ships), &result, &moves_lhs, &Vec::new());
+            let replay: Replay = decode_replay(&encoded).unwrap();
+            assert_eq!(replay.result.reserve_lhs, reserve_by_round);
+            assert_eq!(replay.result.hash, result.hash);
+
+            // The reserve arrives once the front line falls below the threshold
+            let reserve_by_threshold: Reserve = Reserve {
+                selection: [10, 10, 10, 10],
+                round: 0,
+                threshold: 50,
+            };
+            let (_, moves_lhs, _) = contract.fight(1337, true, false, ships.clone(),
+                prepare_commanders(), Morale::default(),
+                [5, 5, 5, 5], [10, 10, 10, 10], [0, 0, 0, 0], [0, 0, 0, 0], 0, 1, 0, 0, 0, 0,
+                reserve_by_threshold, Reserve::default(), 0, prepare_maps()[0].clone());
+            let moves_lhs: Vec<Move> = moves_lhs.unwrap();
+            let arrival_round: u8 = moves_lhs.iter().find(|m| m.move_type == MOVE_REINFORCE).unwrap().round;
+            assert!(arrival_round > 0);
+            assert!(moves_lhs.iter().filter(|m| m.round < arrival_round).all(|m| m.move_type != MOVE_REINFORCE));
+
+            // A reserve without triggers never arrives, and is not lost
+            let (result_held, _, _) = contract.fight(1337, false, false, ships,
+                prepare_commanders(), Morale::default(),
+                [5, 5, 5, 5], [10, 10, 10, 10], [0, 0, 0, 0], [0, 0, 0, 0], 0, 1, 0, 0, 0, 0,
+                Reserve { selection: [10, 10, 10, 10], round: 0, threshold: 0 }, Reserve::default(),
+                0, prepare_maps()[0].clone());
+            assert_eq!(result_held.ships_lost_lhs, result_alone.ships_lost_lhs);
+
+            // Stacks have to stay countable once the reserve arrives
+            let reserve_large: Reserve = Reserve { selection: [100, 0, 0, 0], round: 3, threshold: 0 };
+            assert!(is_reserve_valid([155, 255, 0, 0], reserve_large));
+            assert!(!is_reserve_valid([200, 0, 0, 0], reserve_large));
+        }
+
+        #[test]
+        fn test_reserve_repair_routed() {
+            let contract = NewOmega::default();
+            let mut ships: Vec<Ship> = prepare_ships();
+            ships[3].abilities.push(Ability::Repair { amount: 1000 });
+            let hp: i32 = ships[0].hp as i32;
+
+            // 5 ships of the first stack were committed, 2 were destroyed and 3 routed,
+            // then a reserve of 5 arrived into the routed stack
+            let selection: [u8; MAX_SHIPS] = [10, 1, 1, 1];
+            let mut routed_hps: [i32; MAX_SHIPS] = [0; MAX_SHIPS];
+            routed_hps[0] = hp * 3;
+            let mut hps: [i32; MAX_SHIPS] = [hp * 5, 0, 0, 0];
+            for i in 1..MAX_SHIPS {
+                hps[i] = ships[i].hp as i32;
+            }
+            let mut shields: [u32; MAX_SHIPS] = [0; MAX_SHIPS];
+
+            // Only the destroyed ships are repaired, not the routed ones
+            contract.apply_round_abilities(&ships, 0, selection, routed_hps, &mut hps, &mut shields, &mut None);
+            assert_eq!(hps[0], hp * 7);
+            assert!(hps[0] + routed_hps[0] <= hp * selection[0] as i32);
+        }
+
+        #[test]
+        #[should_panic]
+        fn test_reserve_overflow() {
+            let contract = NewOmega::default();
+
+            contract.fight(1337, false, false, prepare_ships(), prepare_commanders(), Morale::default(),
+                [200, 0, 0, 0], [10, 10, 10, 10], [0, 0, 0, 0], [0, 0, 0, 0], 0, 1, 0, 0, 0, 0,
+                Reserve { selection: [100, 0, 0, 0], round: 3, threshold: 0 }, Reserve::default(),
+                0, prepare_maps()[0].clone());
+        }
+
+        #[test]
+        fn test_commander_level() {
+            assert_eq!(commander_level(0, 0), 1);
+            assert_eq!(commander_level(9, 0), 1);
+            assert_eq!(commander_level(10, 0), 2);
+            assert_eq!(commander_level(29, 0), 2);
+            assert_eq!(commander_level(30, 0), 3);
+            assert_eq!(commander_level(60, 3), 4);
+
+            // Levels are capped by the rarity
+            assert_eq!(commander_level(u32::MAX, 0), 10);
+            assert_eq!(commander_level(u32::MAX, 1), 15);
+            assert_eq!(commander_level(u32::MAX, 3), 25);
+            assert_eq!(commander_level(u32::MAX, 200), 25);
+        }
+
+        #[test]
+        fn test_commander_bonus() {
+            let contract = NewOmega::default();
+            let ships: Vec<Ship> = prepare_ships();
+            let commanders: Vec<Commander> = prepare_commanders();
+            let variants: [u8; MAX_SHIPS] = [0, 0, 0, 0];
+            let variables: [u16; MAX_SHIPS] = [0, 0, 0, 0];
+            let source_hp: u32 = ships[0].hp as u32;
+
+            // Level 0 and unknown commanders give no bonus, levels above the cap count as the cap
+            assert_eq!(contract.get_commander_bonus(&commanders, 0, 0), (0, 0));
+            assert_eq!(contract.get_commander_bonus(&commanders, 9, 5), (0, 0));
+            assert_eq!(contract.get_commander_bonus(&commanders, 2, 5), (5, 5));
+            assert_eq!(contract.get_commander_bonus(&commanders, 0, 50), (10, 0));
+
+            // 80 attack against 20 defence, +10% attack and +10% defence
+            let damage: u32 = contract.calculate_damage(variables, variants, variants,
+                &ships, 0, 0, source_hp, 10, 10);
+            assert_eq!(damage, 88 - 22);
+
+            // Levelled commanders change the outcome, and are recorded in the result and the replay
+            let (result, moves_lhs, moves_rhs) = contract.fight(1337, true, false, ships.clone(),
+                commanders.clone(), Morale::default(),
+                [5, 5, 5, 5], [5, 5, 5, 5], [0, 0, 0, 0], [0, 0, 0, 0], 3, 0, 25, 0, 0, 0,
+                Reserve::default(), Reserve::default(), 0, prepare_maps()[0].clone());
+            let (result_unlevelled, _, _) = contract.fight(1337, false, false, ships.clone(),
+                commanders, Morale::default(),
+                [5, 5, 5, 5], [5, 5, 5, 5], [0, 0, 0, 0], [0, 0, 0, 0], 3, 0, 0, 0, 0, 0,
+                Reserve::default(), Reserve::default(), 0, prepare_maps()[0].clone());
+            assert!(result.rhs_dead);
+            assert_ne!(result.hash, result_unlevelled.hash);
+            assert_ne!(result.ships_lost_lhs, result_unlevelled.ships_lost_lhs);
+
+            let encoded: Vec<u8> = encode_replay(hash_ships(&ships), &result, &moves_lhs.unwrap(),
+                &moves_rhs.unwrap());
+            let replay: Replay = decode_replay(&encoded).unwrap();
+            assert_eq!(replay.result.commander_level_lhs, 25);
+            assert_eq!(replay.result.hash, result.hash);
+        }
+
+        #[test]
+        fn test_commander_skills() {
+            let contract = NewOmega::default();
+            let commanders: Vec<Commander> = prepare_commanders();
+            let legendary: &Commander = &commanders[3];
+
+            // Skills are unlocked by level, need their prerequisite and a skill point
+            assert_eq!(skill_points(4), 0);
+            assert_eq!(skill_points(25), 5);
+            assert!(!can_select_skill(legendary, 4, 0, 0));
+            assert!(can_select_skill(legendary, 5, 0, 0));
+            assert!(!can_select_skill(legendary, 5, 0b1, 0));
+            assert!(!can_select_skill(legendary, 5, 0b1, 1));
+            assert!(!can_select_skill(legendary, 10, 0b10, 2));
+            assert!(can_select_skill(legendary, 10, 0b1, 2));
+            assert!(!can_select_skill(legendary, 25, 0, 6));
+
+            // Fights only accept skills within the points, unlocked by the (capped) level and with their prerequisite
+            assert!(are_skills_valid(&commanders, 3, 10, 0b101));
+            assert!(!are_skills_valid(&commanders, 3, 10, 0b111));
+            assert!(!are_skills_valid(&commanders, 3, 15, 0b10001));
+            assert!(!are_skills_valid(&commanders, 3, 25, 0b100));
+            assert!(!are_skills_valid(&commanders, 3, 25, 0b1000000));
+            assert!(are_skills_valid(&commanders, 0, 50, 0b101));
+            assert!(!are_skills_valid(&commanders, 0, 50, 0b111));
+            assert!(are_skills_valid(&commanders, 9, 25, 0));
+            assert!(!are_skills_valid(&commanders, 9, 25, 0b1));
+
+            let (range_bonus, evasion_bonus, damage_bonus) = contract.get_skill_bonuses(&commanders, 3, 0b101);
+            assert_eq!(range_bonus, [2, 0, 0, 5]);
+            assert_eq!(evasion_bonus, 0);
+            assert_eq!(damage_bonus, [0, 0, 0, 0]);
+            let (_, _, damage_bonus) = contract.get_skill_bonuses(&commanders, 0, 0b101);
+            assert_eq!(damage_bonus, [10, 0, 0, 0]);
+            assert_eq!(contract.get_skill_bonuses(&commanders, 9, u32::MAX), ([0; MAX_SHIPS], 0, [0; MAX_SHIPS]));
+
+            // Evasion makes shots miss
+            let ships: Vec<Ship> = prepare_ships();
+            let mut random_state: u64 = 1;
+            let misses: usize = (0..100)
+                .filter(|_| contract.resolve_shot(&ships, &mut random_state, 0, 1, 50, MOVE_SHOOT, 50).1 == MOVE_MISS)
+                .count();
+            assert!(misses > 25 && misses < 75);
+
+            // The skill loadout changes the fight, and is recorded in the result and the replay
+            let (result, moves_lhs, moves_rhs) = contract.fight(1337, true, false, ships.clone(),
+                commanders.clone(), Morale::default(),
+                [5, 5, 5, 5], [5, 5, 5, 5], [0, 0, 0, 0], [0, 0, 0, 0], 3, 0, 10, 0, 0b101, 0,
+                Reserve::default(), Reserve::default(), 0, prepare_maps()[0].clone());
+            let (result_no_skills, _, _) = contract.fight(1337, false, false, ships.clone(),
+                commanders, Morale::default(),
+                [5, 5, 5, 5], [5, 5, 5, 5], [0, 0, 0, 0], [0, 0, 0, 0], 3, 0, 10, 0, 0, 0,
+                Reserve::default(), Reserve::default(), 0, prepare_maps()[0].clone());
+            assert_ne!(result.hash, result_no_skills.hash);
+
+            let encoded: Vec<u8> = encode_replay(hash_ships(&ships), &result, &moves_lhs.unwrap(),
+                &moves_rhs.unwrap());
+            let replay: Replay = decode_replay(&encoded).unwrap();
+            assert_eq!(replay.result.commander_skills_lhs, 0b101);
+            assert_eq!(replay.result.hash, result.hash);
+        }
+
+        #[test]
+        #[should_panic]
+        fn test_commander_skills_over_budget() {
+            let contract = NewOmega::default();
+
+            // Three skills at level 10, with two skill points
+            contract.fight(1337, false, false, prepare_ships(), prepare_commanders(), Morale::default(),
+                [5, 5, 5, 5], [5, 5, 5, 5], [0, 0, 0, 0], [0, 0, 0, 0], 3, 0, 10, 0, 0b111, 0,
+                Reserve::default(), Reserve::default(), 0, prepare_maps()[0].clone());
+        }
+
         #[test]
         fn test_isdead() {
             let contract = NewOmega::default();
@@ -0,0 +1,685 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use ink_lang as ink;
+pub use self::newomegachallenge::NewOmegaChallenge;
+pub use self::newomegachallenge::Challenge;
+pub use self::newomegachallenge::ChallengeFleet;
+pub use self::newomegachallenge::ChallengeState;
+
+/// Direct challenges between players: a player challenges another one with an optional wager,
+/// both players commit to their fleets without revealing them, and the fight is resolved
+/// by the Game Engine through Fight Management once both fleets are revealed.
+/// This contract does not deal with payments, it only keeps track of the wagers
+/// and computes the payouts, leaving the escrow and the transfers to the Delegator.
+#[ink::contract]
+mod newomegachallenge {
+    use newomegagame::NewOmegaGame;
+    use newomega::MAX_SHIPS;
+    use newomega::Reserve;
+    use newomega::is_reserve_valid;
+    use newomega::FightResult;
+    use newomega::commit_fleet;
+    use ink_prelude::vec::Vec;
+    use ink_env::hash::Blake2x256;
+    use ink_storage::{
+        collections::{
+            HashMap as StorageHashMap,
+        },
+        traits::{
+            PackedLayout,
+            SpreadLayout,
+        },
+    };
+
+    /// Roughly a day, with 6 second blocks
+    const DEFAULT_CHALLENGE_TIMEOUT: BlockNumber = 14_400;
+
+    /// Describes the stage a challenge is in
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Copy, Clone,
+        Debug, Eq, PartialEq)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub enum ChallengeState {
+        /// Waiting for the opponent to accept
+        Open,
+        /// Accepted, waiting for both fleets to be revealed
+        Revealing,
+        /// Fought or forfeited, wagers paid out
+        Resolved,
+        /// Withdrawn, declined or expired, wager refunded
+        Cancelled,
+    }
+
+    /// Describes a revealed fleet taking part in a challenge
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Copy, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            Debug,
+            PartialEq,
+            Eq,
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub struct ChallengeFleet {
+        /// Fleet composition
+        pub selection: [u8; MAX_SHIPS],
+        /// Fleet variants (fittings)
+        pub variants: [u8; MAX_SHIPS],
+        /// Commander index
+        pub commander: u8,
+        /// Commander level, as of the commitment
+        pub commander_level: u8,
+        /// Commander skills (bitmask of skill ids), as of the commitment
+        pub commander_skills: u32,
+        /// Reserve group, entering the battle later
+        pub reserve: Reserve,
+        /// Salt the fleet was committed with
+        pub salt: [u8; 32],
+    }
+
+    /// Describes a challenge between two players
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            Debug,
+            PartialEq,
+            Eq,
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub struct Challenge {
+        /// Account id of the challenging player, fighting as the attacker
+        pub challenger: AccountId,
+        /// Account id of the challenged player, fighting as the defender
+        pub opponent: AccountId,
+        /// Wager, staked by each of the players
+        pub wager: Balance,
+        /// Current stage
+        pub state: ChallengeState,
+        /// Fleet commitment of the challenger
+        pub challenger_commitment: [u8; 32],
+        /// Fleet commitment of the opponent, once accepted
+        pub opponent_commitment: Option<[u8; 32]>,
+        /// Commanders of the challenger as of the challenge, as (commander id, level, skills)
+        pub challenger_commanders: Vec<(u8, u8, u32)>,
+        /// Commanders of the opponent as of the acceptance, as (commander id, level, skills)
+        pub opponent_commanders: Vec<(u8, u8, u32)>,
+        /// Fleet of the challenger, once revealed
+        pub challenger_fleet: Option<ChallengeFleet>,
+        /// Fleet of the opponent, once revealed
+        pub opponent_fleet: Option<ChallengeFleet>,
+        /// Block the challenge was created in
+        pub created: BlockNumber,
+        /// Block from which the challenge can no longer be accepted or, once accepted, revealed
+        pub expires: BlockNumber,
+        /// Winner of the fight, None for a draw or an unresolved challenge
+        pub winner: Option<AccountId>,
+        /// Commitment hash of the fight, zeroed until resolved
+        pub hash: [u8; 32],
+    }
+
+    /// Derives the seed of a challenge fight from the salts of both fleets. Both salts are committed to
+    /// before either is revealed, so neither of the players can pick the seed on their own.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Id of the challenge
+    /// * `salt_lhs` - Salt of the challenger fleet
+    /// * `salt_rhs` - Salt of the opponent fleet
+    ///
+    /// # Returns
+    ///
+    /// * `seed` - Seed of the fight
+    pub fn challenge_seed(id: u32, salt_lhs: [u8; 32], salt_rhs: [u8; 32]) -> u64 {
+        let mut output: [u8; 32] = [0; 32];
+        ink_env::hash_encoded::<Blake2x256, _>(&(id, salt_lhs, salt_rhs), &mut output);
+        let mut seed: [u8; 8] = [0; 8];
+        seed.copy_from_slice(&output[0..8]);
+
+        u64::from_le_bytes(seed)
+    }
+
+    /// Gets the level and skills a commander fights with in a challenge, from the snapshot taken
+    /// when the player committed to their fleet. Commanders the player did not own back then fight
+    /// without bonuses.
+    ///
+    /// # Arguments
+    ///
+    /// * `commanders` - Snapshot of the commanders, as (commander id, level, skills)
+    /// * `commander` - Index of the commander leading the fleet
+    ///
+    /// # Returns
+    ///
+    /// * `commander_level` - Level of the commander
+    /// * `commander_skills` - Selected skills of the commander (bitmask of skill ids)
+    pub fn commander_loadout(commanders: &Vec<(u8, u8, u32)>, commander: u8) -> (u8, u32) {
+        commanders
+            .iter()
+            .find(|&&(id, _, _)| id == commander)
+            .map(|&(_, level, skills)| (level, skills))
+            .unwrap_or((0, 0))
+    }
+
+    /// Settles a challenge. The attacker has to destroy or rout the defender to win,
+    /// a fleet that gets destroyed or routed loses; if both fleets survive, the fight is a draw
+    /// and the wagers are returned. A player failing to reveal their fleet loses as well.
+    ///
+    /// # Arguments
+    ///
+    /// * `challenge` - The challenge
+    /// * `lhs_lost` - Whether the challenger fleet was destroyed, routed or not revealed
+    /// * `rhs_lost` - Whether the opponent fleet was destroyed, routed or not revealed
+    ///
+    /// # Returns
+    ///
+    /// * `winner` - Winner of the challenge, None for a draw
+    /// * `payouts` - A Vec containing a tuple of (player account id, amount to pay out)
+    pub fn settle_challenge(challenge: &Challenge, lhs_lost: bool, rhs_lost: bool)
+        -> (Option<AccountId>, Vec<(AccountId, Balance)>) {
+
+        let winner: Option<AccountId> = if lhs_lost {
+            Some(challenge.opponent)
+        } else if rhs_lost {
+            Some(challenge.challenger)
+        } else {
+            None
+        };
+        let payouts: Vec<(AccountId, Balance)> = match winner {
+            Some(player) => [(player, challenge.wager * 2)].to_vec(),
+            None => [(challenge.challenger, challenge.wager), (challenge.opponent, challenge.wager)].to_vec(),
+        };
+
+        (winner, payouts.into_iter().filter(|&(_, amount)| amount > 0).collect())
+    }
+
+    #[ink(storage)]
+    pub struct NewOmegaChallenge {
+        owner: AccountId,
+        new_omega_game: NewOmegaGame,
+        challenges: StorageHashMap<u32, Challenge>,
+        next_challenge_id: u32,
+        challenge_timeout: BlockNumber,
+    }
+
+    impl NewOmegaChallenge {
+        #[ink(constructor)]
+        pub fn new(new_omega_game: NewOmegaGame) -> Self {
+            Self {
+                owner: Self::env().caller(),
+                new_omega_game,
+                challenges: StorageHashMap::default(),
+                next_challenge_id: 0,
+                challenge_timeout: DEFAULT_CHALLENGE_TIMEOUT,
+            }
+        }
+
+        /// Sets the number of blocks a challenge stays open for, and the time to reveal the fleets once accepted.
+        ///
+        /// # Arguments
+        ///
+        /// * `timeout` - Challenge timeout, in blocks
+        #[ink(message)]
+        pub fn set_challenge_timeout(&mut self, timeout: BlockNumber) {
+            assert_eq!(self.env().caller(), self.owner);
+            assert!(timeout > 0);
+            self.challenge_timeout = timeout;
+        }
+
+        /// Gets the number of blocks a challenge stays open for, and the time to reveal the fleets once accepted.
+        ///
+        /// # Returns
+        ///
+        /// * `timeout` - Challenge timeout, in blocks
+        #[ink(message)]
+        pub fn get_challenge_timeout(&self) -> BlockNumber {
+            self.challenge_timeout
+        }
+
+        /// Challenges another player, with a commitment to the fleet (see `commit_fleet` in the Game Engine).
+        /// The wager is expected to be collected by the caller.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - Account id of the challenging player
+        /// * `opponent` - Account id of the challenged player
+        /// * `commitment` - Fleet commitment
+        /// * `wager` - Wager, to be matched by the opponent
+        /// * `commanders` - Commanders of the challenger, as (commander id, level, skills), fought with as they are now
+        ///
+        /// # Returns
+        ///
+        /// * `id` - Id of the created challenge
+        #[ink(message)]
+        pub fn create_challenge(&mut self, caller: AccountId, opponent: AccountId, commitment: [u8; 32],
+            wager: Balance, commanders: Vec<(u8, u8, u32)>) -> u32 {
+
+            assert_eq!(self.env().caller(), self.owner);
+            assert!(caller != opponent);
+
+            let id: u32 = self.next_challenge_id;
+            let created: BlockNumber = self.env().block_number();
+            self.challenges.insert(id, Challenge {
+                challenger: caller,
+                opponent,
+                wager,
+                state: ChallengeState::Open,
+                challenger_commitment: commitment,
+                opponent_commitment: None,
+                challenger_commanders: commanders,
+                opponent_commanders: Vec::new(),
+                challenger_fleet: None,
+                opponent_fleet: None,
+                created,
+                expires: created + self.challenge_timeout,
+                winner: None,
+                hash: [0; 32],
+            });
+            self.next_challenge_id = id + 1;
+
+            id
+        }
+
+        /// Accepts a challenge, with a commitment to the fleet, and starts the reveal phase.
+        /// The matching wager is expected to be collected by the caller.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - Account id of the challenged player
+        /// * `id` - Id of the challenge
+        /// * `commitment` - Fleet commitment
+        /// * `commanders` - Commanders of the opponent, as (commander id, level, skills), fought with as they are now
+        #[ink(message)]
+        pub fn accept_challenge(&mut self, caller: AccountId, id: u32, commitment: [u8; 32],
+            commanders: Vec<(u8, u8, u32)>) {
+            assert_eq!(self.env().caller(), self.owner);
+            assert!(self.challenges.get(&id).is_some());
+
+            let block: BlockNumber = self.env().block_number();
+            let timeout: BlockNumber = self.challenge_timeout;
+            let challenge: &mut Challenge = self.challenges.get_mut(&id).unwrap();
+
+            assert_eq!(challenge.state, ChallengeState::Open);
+            assert_eq!(challenge.opponent, caller);
+            assert!(block < challenge.expires);
+
+            challenge.opponent_commitment = Some(commitment);
+            challenge.opponent_commanders = commanders;
+            challenge.state = ChallengeState::Revealing;
+            challenge.expires = block + timeout;
+        }
+
+        /// Reveals the fleet of a player, which has to match their commitment.
+        /// The commander fights at the level and with the skills it had when the player committed,
+        /// so the second player to reveal can't counter the first fleet by changing them.
+        /// Once both fleets are revealed, the fight is resolved.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - Account id of the revealing player
+        /// * `id` - Id of the challenge
+        /// * `selection` - Fleet composition (array with ship quantities)
+        /// * `variants` - An array that holds variants of the fleet
+        /// * `commander` - Index of the commander leading the fleet
+        /// * `reserve` - The reserve of the fleet
+        /// * `salt` - Salt the fleet was committed with
+        ///
+        /// # Returns
+        ///
+        /// * `resolution` - None while waiting for the other fleet, otherwise a tuple of
+        ///     (fight result, winner of the challenge (None for a draw), payouts as a Vec of (player account id, amount))
+        #[ink(message)]
+        pub fn reveal_fleet(&mut self, caller: AccountId, id: u32, selection: [u8; MAX_SHIPS],
+            variants: [u8; MAX_SHIPS], commander: u8, reserve: Reserve, salt: [u8; 32])
+            -> Option<(FightResult, Option<AccountId>, Vec<(AccountId, Balance)>)> {
+
+            assert_eq!(self.env().caller(), self.owner);
+            assert!(self.challenges.get(&id).is_some());
+
+            let mut challenge: Challenge = self.challenges.get(&id).unwrap().clone();
+
+            assert_eq!(challenge.state, ChallengeState::Revealing);
+            assert!(self.env().block_number() < challenge.expires);
+            assert!(is_reserve_valid(selection, reserve));
+
+            let commanders: &Vec<(u8, u8, u32)> = if caller == challenge.challenger {
+                &challenge.challenger_commanders
+            } else {
+                &challenge.opponent_commanders
+            };
+            let (commander_level, commander_skills) = commander_loadout(commanders, commander);
+            let fleet: ChallengeFleet = ChallengeFleet {
+                selection,
+                variants,
+                commander,
+                commander_level,
+                commander_skills,
+                reserve,
+                salt,
+            };
+            let commitment: [u8; 32] = commit_fleet(selection, variants, commander, reserve, salt);
+
+            if caller == challenge.challenger {
+                assert!(challenge.challenger_fleet.is_none());
+                assert_eq!(challenge.challenger_commitment, commitment);
+                challenge.challenger_fleet = Some(fleet);
+            } else {
+                assert_eq!(challenge.opponent, caller);
+                assert!(challenge.opponent_fleet.is_none());
+                assert_eq!(challenge.opponent_commitment, Some(commitment));
+                challenge.opponent_fleet = Some(fleet);
+            }
+
+            let resolution: Option<(FightResult, Option<AccountId>, Vec<(AccountId, Balance)>)> =
+                match (challenge.challenger_fleet, challenge.opponent_fleet) {
+                    (Some(lhs), Some(rhs)) => {
+                        let seed: u64 = challenge_seed(id, lhs.salt, rhs.salt);
+                        let map: u8 = self.new_omega_game.pick_map(seed);
+                        let (result, _lhs_moves, _rhs_moves): (FightResult, _, _) =
+                            self.new_omega_game.fight(
+                                seed,
+                                false,
+                                true,
+                                lhs.selection,
+                                rhs.selection,
+                                lhs.variants,
+                                rhs.variants,
+                                lhs.commander,
+                                rhs.commander,
+                                lhs.commander_level,
+                                rhs.commander_level,
+                                lhs.commander_skills,
+                                rhs.commander_skills,
+                                lhs.reserve,
+                                rhs.reserve,
+                                map);
+
+                        let (winner, payouts) = settle_challenge(&challenge,
+                            result.lhs_dead || result.lhs_retreated, result.rhs_dead || result.rhs_retreated);
+
+                        challenge.state = ChallengeState::Resolved;
+                        challenge.winner = winner;
+                        challenge.hash = result.hash;
+
+                        Some((result, winner, payouts))
+                    },
+                    _ => None,
+                };
+
+            self.challenges.insert(id, challenge);
+
+            resolution
+        }
+
+        /// Settles a challenge, in which a fleet was not revealed before the deadline.
+        /// The player who failed to reveal forfeits; if neither fleet was revealed, the wagers are returned.
+        /// Anybody can settle a challenge past its deadline.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the challenge
+        ///
+        /// # Returns
+        ///
+        /// * `winner` - Winner of the challenge, None if neither fleet was revealed
+        /// * `payouts` - A Vec containing a tuple of (player account id, amount to pay out)
+        #[ink(message)]
+        pub fn claim_forfeit(&mut self, id: u32) -> (Option<AccountId>, Vec<(AccountId, Balance)>) {
+            assert_eq!(self.env().caller(), self.owner);
+            assert!(self.challenges.get(&id).is_some());
+
+            let block: BlockNumber = self.env().block_number();
+            let challenge: &mut Challenge = self.challenges.get_mut(&id).unwrap();
+
+            assert_eq!(challenge.state, ChallengeState::Revealing);
+            assert!(block >= challenge.expires);
+
+            let lhs_revealed: bool = challenge.challenger_fleet.is_some();
+            let rhs_revealed: bool = challenge.opponent_fleet.is_some();
+            let (winner, payouts) = settle_challenge(challenge,
+                !lhs_revealed && rhs_revealed, lhs_revealed && !rhs_revealed);
+
+            challenge.state = ChallengeState::Resolved;
+            challenge.winner = winner;
+
+            (winner, payouts)
+        }
+
+        /// Cancels a challenge which has not been accepted. The challenger can withdraw and the opponent
+        /// can decline at any time, anybody can cancel an expired challenge.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - Account id of the player cancelling the challenge
+        /// * `id` - Id of the challenge
+        ///
+        /// # Returns
+        ///
+        /// * `refunds` - A Vec containing a tuple of (player account id, wager to refund)
+        #[ink(message)]
+        pub fn cancel_challenge(&mut self, caller: AccountId, id: u32) -> Vec<(AccountId, Balance)> {
+            assert_eq!(self.env().caller(), self.owner);
+            assert!(self.challenges.get(&id).is_some());
+
+            let block: BlockNumber = self.env().block_number();
+            let challenge: &mut Challenge = self.challenges.get_mut(&id).unwrap();
+
+            assert_eq!(challenge.state, ChallengeState::Open);
+            assert!(caller == challenge.challenger || caller == challenge.opponent ||
+                block >= challenge.expires);
+            challenge.state = ChallengeState::Cancelled;
+
+            if challenge.wager > 0 {
+                [(challenge.challenger, challenge.wager)].to_vec()
+            } else {
+                Vec::new()
+            }
+        }
+
+        /// Gets a challenge.
+        /// Will panic if the challenge does not exist.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the challenge
+        ///
+        /// # Returns
+        ///
+        /// * `challenge` - The challenge
+        #[ink(message)]
+        pub fn get_challenge(&self, id: u32) -> Challenge {
+            assert!(self.challenges.get(&id).is_some());
+            self.challenges.get(&id).unwrap().clone()
+        }
+
+        /// Gets all the challenges a player took part in, either as the challenger or the opponent.
+        ///
+        /// # Arguments
+        ///
+        /// * `player` - Account id of the player
+        ///
+        /// # Returns
+        ///
+        /// * `challenges` - A Vec containing a tuple of (challenge id, challenge)
+        #[ink(message)]
+        pub fn get_challenges(&self, player: AccountId) -> Vec<(u32, Challenge)> {
+            self.challenges
+                .iter()
+                .filter(|(_, challenge)| challenge.challenger == player || challenge.opponent == player)
+                .map(|(&id, challenge)| (id, challenge.clone()))
+                .collect()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use ink_env::{
+            call::FromAccountId,
+            test,
+        };
+        use ink_lang as ink;
+        type Accounts = test::DefaultAccounts<Environment>;
+
+        fn default_accounts() -> Accounts {
+            test::default_accounts()
+                .expect("Test environment is expected to be initialized.")
+        }
+
+        fn new_contract() -> NewOmegaChallenge {
+            NewOmegaChallenge::new(NewOmegaGame::from_account_id(default_accounts().django))
+        }
+
+        fn commitment(salt: u8) -> [u8; 32] {
+            commit_fleet([1, 1, 1, 1], [0, 0, 0, 0], 0, Reserve::default(), [salt; 32])
+        }
+
+        fn create_challenge(contract: &mut NewOmegaChallenge, wager: Balance) -> u32 {
+            let accounts = default_accounts();
+            contract.create_challenge(accounts.alice, accounts.bob, commitment(1), wager, [(0, 4, 0b1)].to_vec())
+        }
+
+        #[ink::test]
+        fn test_challenge_and_decline() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+
+            let id: u32 = create_challenge(&mut contract, 10);
+
+            let challenge: Challenge = contract.get_challenge(id);
+            assert_eq!(challenge.state, ChallengeState::Open);
+            assert_eq!(challenge.expires, challenge.created + DEFAULT_CHALLENGE_TIMEOUT);
+            assert_eq!(contract.get_challenges(accounts.alice).len(), 1);
+            assert_eq!(contract.get_challenges(accounts.bob).len(), 1);
+            assert_eq!(contract.get_challenges(accounts.charlie).len(), 0);
+
+            // The opponent declines, the challenger gets the wager back
+            assert_eq!(contract.cancel_challenge(accounts.bob, id), [(accounts.alice, 10)].to_vec());
+            assert_eq!(contract.get_challenge(id).state, ChallengeState::Cancelled);
+        }
+
+        #[ink::test]
+        fn test_challenge_expiry() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+
+            contract.set_challenge_timeout(2);
+            let id: u32 = create_challenge(&mut contract, 10);
+
+            test::advance_block::<Environment>().expect("Cannot advance block");
+            test::advance_block::<Environment>().expect("Cannot advance block");
+
+            // Anybody can clean up an expired challenge
+            assert_eq!(contract.cancel_challenge(accounts.charlie, id), [(accounts.alice, 10)].to_vec());
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_cancel_before_expiry() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+
+            let id: u32 = create_challenge(&mut contract, 10);
+            contract.cancel_challenge(accounts.charlie, id);
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_accept_by_stranger() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+
+            let id: u32 = create_challenge(&mut contract, 10);
+            contract.accept_challenge(accounts.charlie, id, commitment(2), Vec::new());
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_reveal_mismatch() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+
+            let id: u32 = create_challenge(&mut contract, 10);
+            contract.accept_challenge(accounts.bob, id, commitment(2), Vec::new());
+            contract.reveal_fleet(accounts.alice, id, [1, 1, 1, 2], [0, 0, 0, 0], 0, Reserve::default(), [1; 32]);
+        }
+
+        #[ink::test]
+        fn test_forfeit() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+
+            contract.set_challenge_timeout(2);
+            let id: u32 = create_challenge(&mut contract, 10);
+            contract.accept_challenge(accounts.bob, id, commitment(2), Vec::new());
+            assert_eq!(contract.get_challenge(id).state, ChallengeState::Revealing);
+
+            // The fight waits for the other fleet
+            assert!(contract.reveal_fleet(accounts.alice, id, [1, 1, 1, 1], [0, 0, 0, 0], 0,
+                Reserve::default(), [1; 32]).is_none());
+            assert!(contract.get_challenge(id).challenger_fleet.is_some());
+
+            test::advance_block::<Environment>().expect("Cannot advance block");
+            test::advance_block::<Environment>().expect("Cannot advance block");
+
+            // The opponent did not reveal in time, and forfeits
+            assert_eq!(contract.claim_forfeit(id), (Some(accounts.alice), [(accounts.alice, 20)].to_vec()));
+            assert_eq!(contract.get_challenge(id).state, ChallengeState::Resolved);
+        }
+
+        #[ink::test]
+        fn test_commander_snapshot() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+
+            let id: u32 = create_challenge(&mut contract, 10);
+            contract.accept_challenge(accounts.bob, id, commitment(2), [(1, 7, 0)].to_vec());
+            assert_eq!(contract.get_challenge(id).opponent_commanders, [(1, 7, 0)].to_vec());
+
+            // The revealed commander fights as it was when the challenge was created
+            contract.reveal_fleet(accounts.alice, id, [1, 1, 1, 1], [0, 0, 0, 0], 0, Reserve::default(), [1; 32]);
+            let fleet: ChallengeFleet = contract.get_challenge(id).challenger_fleet.unwrap();
+            assert_eq!((fleet.commander_level, fleet.commander_skills), (4, 0b1));
+
+            // Commanders missing from the snapshot fight without bonuses
+            assert_eq!(commander_loadout(&[(0, 4, 0b1)].to_vec(), 2), (0, 0));
+            assert_eq!(commander_loadout(&Vec::new(), 0), (0, 0));
+        }
+
+        #[ink::test]
+        fn test_settle_challenge() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+
+            let id: u32 = create_challenge(&mut contract, 10);
+            let challenge: Challenge = contract.get_challenge(id);
+
+            // The winner takes both wagers, a draw returns them
+            assert_eq!(settle_challenge(&challenge, false, true),
+                (Some(accounts.alice), [(accounts.alice, 20)].to_vec()));
+            assert_eq!(settle_challenge(&challenge, true, false),
+                (Some(accounts.bob), [(accounts.bob, 20)].to_vec()));
+            assert_eq!(settle_challenge(&challenge, true, true),
+                (Some(accounts.bob), [(accounts.bob, 20)].to_vec()));
+            assert_eq!(settle_challenge(&challenge, false, false),
+                (None, [(accounts.alice, 10), (accounts.bob, 10)].to_vec()));
+
+            // Nothing to pay out without a wager
+            let id: u32 = create_challenge(&mut contract, 0);
+            assert_eq!(settle_challenge(&contract.get_challenge(id), false, true),
+                (Some(accounts.alice), Vec::new()));
+
+            // The seed depends on both salts
+            assert_ne!(challenge_seed(0, [1; 32], [2; 32]), challenge_seed(0, [1; 32], [3; 32]));
+            assert_ne!(challenge_seed(0, [1; 32], [2; 32]), challenge_seed(0, [3; 32], [2; 32]));
+        }
+    }
+}
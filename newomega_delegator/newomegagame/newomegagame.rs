@@ -10,16 +10,27 @@ pub use self::newomegagame::NewOmegaGame;
 mod newomegagame {
     use newomega::NewOmega;
     use newomega::Ship;
+    use newomega::Commander;
+    use newomega::Ability;
+    use newomega::Morale;
+    use newomega::Reserve;
+    use newomega::Terrain;
+    use newomega::Battlefield;
     use newomega::MAX_SHIPS;
+    use newomega::MAX_CRIT_MULTIPLIER;
     use newomega::FightResult;
     use newomega::Move;
     use ink_prelude::vec::Vec;
+    use ink_prelude::string::String;
 
     #[ink(storage)]
     pub struct NewOmegaGame {
         owner: AccountId,
         new_omega: NewOmega,
         ships: Vec<Ship>,
+        commanders: Vec<Commander>,
+        morale: Morale,
+        maps: Vec<Battlefield>,
     }
 
     impl NewOmegaGame {
@@ -29,6 +40,9 @@ mod newomegagame {
                 owner: Self::env().caller(),
                 new_omega,
                 ships: newomega::prepare_ships(),
+                commanders: newomega::prepare_commanders(),
+                morale: Morale::default(),
+                maps: newomega::prepare_maps(),
             }
         }
 
@@ -56,9 +70,77 @@ mod newomegagame {
                 defence,
                 speed,
                 range,
+                abilities: Vec::new(),
+                accuracy: newomega::DEFAULT_ACCURACY,
+                evasion: newomega::DEFAULT_EVASION,
+                crit_chance: newomega::DEFAULT_CRIT_CHANCE,
+                crit_multiplier: newomega::DEFAULT_CRIT_MULTIPLIER,
             });
         }
 
+        /// Sets the precision stats of a ship.
+        /// Defaults (100, 0, 0, 100) make the ship always hit, with regular damage.
+        ///
+        /// # Arguments
+        ///
+        /// * `ship` - Index of the ship in the ship definitions
+        /// * `accuracy` - Chance to hit, in percent
+        /// * `evasion` - Chance to evade, in percent
+        /// * `crit_chance` - Chance of a critical hit, in percent
+        /// * `crit_multiplier` - Damage of a critical hit, in percent of the regular damage, up to 1000
+        #[ink(message)]
+        pub fn set_ship_precision(&mut self, ship: u8, accuracy: u8, evasion: u8, crit_chance: u8,
+            crit_multiplier: u16) {
+
+            assert_eq!(self.env().caller(), self.owner);
+            assert!((ship as usize) < self.ships.len());
+            assert!(crit_multiplier <= MAX_CRIT_MULTIPLIER);
+            let definition: &mut Ship = &mut self.ships[ship as usize];
+            definition.accuracy = accuracy;
+            definition.evasion = evasion;
+            definition.crit_chance = crit_chance;
+            definition.crit_multiplier = crit_multiplier;
+        }
+
+        /// Sets the special abilities of a ship, replacing the previous ones
+        ///
+        /// # Arguments
+        ///
+        /// * `ship` - Index of the ship in the ship definitions
+        /// * `abilities` - The abilities of the ship
+        #[ink(message)]
+        pub fn set_ship_abilities(&mut self, ship: u8, abilities: Vec<Ability>) {
+            assert_eq!(self.env().caller(), self.owner);
+            assert!((ship as usize) < self.ships.len());
+            self.ships[ship as usize].abilities = abilities;
+        }
+
+        /// Sets the morale rules applied to all fights. A threshold of 0 disables the rule.
+        ///
+        /// # Arguments
+        ///
+        /// * `rout_threshold` - Percentage of its starting HP a stack has to lose to rout
+        /// * `retreat_threshold` - Percentage of its starting HP a fleet has to lose to retreat
+        #[ink(message)]
+        pub fn set_morale(&mut self, rout_threshold: u8, retreat_threshold: u8) {
+            assert_eq!(self.env().caller(), self.owner);
+            assert!(rout_threshold <= 100 && retreat_threshold <= 100);
+            self.morale = Morale {
+                rout_threshold,
+                retreat_threshold,
+            };
+        }
+
+        /// Returns the morale rules applied to all fights
+        ///
+        /// # Returns
+        ///
+        /// * `morale` - The morale rules
+        #[ink(message)]
+        pub fn get_morale(&self) -> Morale {
+            self.morale
+        }
+
         /// Returns all the registered ships
         ///
         /// # Returns
@@ -69,18 +151,78 @@ mod newomegagame {
             self.ships.clone()
         }
 
-        /// Calculates a fight, using registered ships.
+        /// Returns all the commander definitions, including their skill trees. Commanders can't be modified,
+        /// since their levels and skills are validated against them by the storage.
+        ///
+        /// # Returns
+        ///
+        /// * `commanders` - A Vector containing the commander definitions
+        #[ink(message)]
+        pub fn get_commanders(&self) -> Vec<Commander> {
+            self.commanders.clone()
+        }
+
+        /// Adds a map to the map definitions. Maps can't be removed or modified,
+        /// so that map ids recorded in past fights stay valid.
+        ///
+        /// # Arguments
+        ///
+        /// * `name` - Name of the map
+        /// * `terrain` - Terrain features of the map
+        #[ink(message)]
+        pub fn add_map(&mut self, name: String, terrain: Vec<Terrain>) {
+            assert_eq!(self.env().caller(), self.owner);
+            assert!(self.maps.len() < u8::MAX as usize);
+            self.maps.push(Battlefield {
+                name,
+                terrain,
+            });
+        }
+
+        /// Returns all the registered maps
+        ///
+        /// # Returns
+        ///
+        /// * `maps` - A Vector containing the registered maps
+        #[ink(message)]
+        pub fn get_maps(&self) -> Vec<Battlefield> {
+            self.maps.clone()
+        }
+
+        /// Picks a map for a fight, deterministically from the fight seed
+        ///
+        /// # Arguments
+        ///
+        /// * `seed` - Seed of the fight
+        ///
+        /// # Returns
+        ///
+        /// * `map` - Id of the picked map
+        #[ink(message)]
+        pub fn pick_map(&self, seed: u64) -> u8 {
+            (seed % self.maps.len() as u64) as u8
+        }
+
+        /// Calculates a fight, using registered ships, commanders, morale rules and maps.
         ///
         /// # Arguments
         ///
         /// * `seed` - Seed used to generate randomness
         /// * `log_moves` - Whether to return a detailed fight log
+        /// * `log_stats` - Whether to return aggregate fight statistics
         /// * `selection_lhs` - Attacker fleet composition (array with ship quantities)
         /// * `selection_rhs` - Defender fleet composition (array with ship quantities)
         /// * `variants_lhs` - An array that holds variants of the attacker fleet
         /// * `variants_rhs` - An array that holds variants of the defender fleet
         /// * `commander_lhs` - The attacker commander
         /// * `commander_rhs` - The defender commander
+        /// * `commander_level_lhs` - Level of the attacker commander, 0 for no commander bonus
+        /// * `commander_level_rhs` - Level of the defender commander, 0 for no commander bonus
+        /// * `commander_skills_lhs` - Selected skills of the attacker commander (bitmask of skill ids)
+        /// * `commander_skills_rhs` - Selected skills of the defender commander (bitmask of skill ids)
+        /// * `reserve_lhs` - The attacker reserve
+        /// * `reserve_rhs` - The defender reserve
+        /// * `map` - Id of the map the fight takes place on
         ///
         /// # Returns
         ///
@@ -88,13 +230,18 @@ mod newomegagame {
         /// * `moves_lhs` - Logged moves of the attacker, if requested. None if not.
         /// * `moves_rhs` - Logged moves of the defender, if requested. None if not.
         #[ink(message)]
-        pub fn fight(&self, seed: u64, log_moves: bool, selection_lhs: [u8; MAX_SHIPS],
+        pub fn fight(&self, seed: u64, log_moves: bool, log_stats: bool, selection_lhs: [u8; MAX_SHIPS],
             selection_rhs: [u8; MAX_SHIPS], variants_lhs: [u8; MAX_SHIPS],
-            variants_rhs: [u8; MAX_SHIPS], commander_lhs: u8, commander_rhs: u8) -> (FightResult, Option<Vec<Move>>,
-                Option<Vec<Move>>) {
+            variants_rhs: [u8; MAX_SHIPS], commander_lhs: u8, commander_rhs: u8, commander_level_lhs: u8,
+            commander_level_rhs: u8, commander_skills_lhs: u32, commander_skills_rhs: u32, reserve_lhs: Reserve,
+            reserve_rhs: Reserve, map: u8) -> (FightResult, Option<Vec<Move>>, Option<Vec<Move>>) {
 
-            self.new_omega.fight(seed, log_moves, self.get_ships(),
-                selection_lhs, selection_rhs, variants_lhs, variants_rhs, commander_lhs, commander_rhs)
+            assert!((map as usize) < self.maps.len());
+            self.new_omega.fight(seed, log_moves, log_stats, self.get_ships(), self.get_commanders(), self.morale,
+                selection_lhs, selection_rhs, variants_lhs, variants_rhs, commander_lhs, commander_rhs,
+                commander_level_lhs, commander_level_rhs, commander_skills_lhs, commander_skills_rhs,
+                reserve_lhs, reserve_rhs, map,
+                self.maps[map as usize].clone())
         }
     }
 }
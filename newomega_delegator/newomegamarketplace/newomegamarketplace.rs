@@ -0,0 +1,825 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use ink_lang as ink;
+pub use self::newomegamarketplace::NewOmegaMarketplace;
+pub use self::newomegamarketplace::Listing;
+pub use self::newomegamarketplace::ListingState;
+pub use self::newomegamarketplace::Bid;
+pub use self::newomegamarketplace::sale_transfers;
+
+/// Marketplace for commanders: players list their commander tokens at a fixed price,
+/// other players buy them outright or place bids, which the seller can accept.
+/// Listed tokens are held in escrow by this contract (as their owner in Storage) until
+/// they are sold, or the listing is cancelled.
+/// This contract does not deal with payments, it only keeps track of the prices and bids
+/// and computes the payouts and the marketplace fee, leaving the escrow of the funds
+/// and the transfers to the Delegator.
+#[ink::contract]
+mod newomegamarketplace {
+    use newomegastorage::NewOmegaStorage;
+    use ink_prelude::vec::Vec;
+    use ink_storage::{
+        collections::{
+            HashMap as StorageHashMap,
+        },
+        traits::{
+            PackedLayout,
+            SpreadLayout,
+        },
+    };
+
+    /// Percentage of each sale kept as the marketplace fee
+    const DEFAULT_MARKETPLACE_FEE: u8 = 5;
+    const MAX_MARKETPLACE_FEE: u8 = 25;
+
+    /// Describes the stage a listing is in
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Copy, Clone,
+        Debug, Eq, PartialEq)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub enum ListingState {
+        /// Open for buying and bidding, the token is in escrow
+        Active,
+        /// Bought or bid accepted, the token went to the buyer
+        Sold,
+        /// Withdrawn by the seller, the token went back to the seller
+        Cancelled,
+    }
+
+    /// Describes a bid on a listing, held in escrow until outbid, withdrawn or accepted
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Copy, Clone,
+        Debug, Eq, PartialEq)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub struct Bid {
+        /// Account id of the bidding player
+        pub bidder: AccountId,
+        /// Amount bid
+        pub amount: Balance,
+    }
+
+    /// Describes a commander token listed for sale
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Copy, Clone,
+        Debug, Eq, PartialEq)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub struct Listing {
+        /// Account id of the selling player
+        pub seller: AccountId,
+        /// Id of the commander token
+        pub token: u32,
+        /// Fixed price the token can be bought for
+        pub price: Balance,
+        /// Current stage
+        pub state: ListingState,
+        /// Highest bid, below the price
+        pub highest_bid: Option<Bid>,
+        /// Block the listing was created in
+        pub created: BlockNumber,
+        /// Account id of the buyer, once sold
+        pub buyer: Option<AccountId>,
+        /// Amount the token was sold for, zero until sold
+        pub sold_for: Balance,
+    }
+
+    /// Calculates the marketplace fee of a sale.
+    ///
+    /// # Arguments
+    ///
+    /// * `amount` - Amount the token is sold for
+    /// * `fee` - Marketplace fee, in percent
+    ///
+    /// # Returns
+    ///
+    /// * `fee` - Amount kept as the marketplace fee
+    pub fn marketplace_fee(amount: Balance, fee: u8) -> Balance {
+        amount * fee as Balance / 100
+    }
+
+    /// Creates an active listing, without bids.
+    ///
+    /// # Arguments
+    ///
+    /// * `seller` - The account id of the selling player
+    /// * `token` - Id of the commander token
+    /// * `price` - Fixed price the token can be bought for
+    /// * `created` - Block the listing is created in
+    ///
+    /// # Returns
+    ///
+    /// * `listing` - The listing
+    pub fn new_listing(seller: AccountId, token: u32, price: Balance, created: BlockNumber) -> Listing {
+        assert!(price > 0);
+
+        Listing {
+            seller,
+            token,
+            price,
+            state: ListingState::Active,
+            highest_bid: None,
+            created,
+            buyer: None,
+            sold_for: 0,
+        }
+    }
+
+    /// Records a bid on a listing. A bid has to top the highest bid and stay below the price,
+    /// otherwise the token should just be bought.
+    ///
+    /// # Arguments
+    ///
+    /// * `listing` - The listing
+    /// * `bidder` - The account id of the bidding player
+    /// * `amount` - Amount bid
+    ///
+    /// # Returns
+    ///
+    /// * `refund` - The outbid bid to refund, if any, as a tuple of (player account id, amount)
+    pub fn record_bid(listing: &mut Listing, bidder: AccountId, amount: Balance) -> Option<(AccountId, Balance)> {
+        assert_eq!(listing.state, ListingState::Active);
+        assert!(bidder != listing.seller);
+        assert!(amount > listing.highest_bid.map_or(0, |bid| bid.amount));
+        assert!(amount < listing.price);
+
+        listing.highest_bid
+            .replace(Bid {
+                bidder,
+                amount,
+            })
+            .map(|bid| (bid.bidder, bid.amount))
+    }
+
+    /// Withdraws the highest bid on a listing.
+    ///
+    /// # Arguments
+    ///
+    /// * `listing` - The listing
+    /// * `bidder` - The account id of the player who placed the highest bid
+    ///
+    /// # Returns
+    ///
+    /// * `refund` - The withdrawn bid to refund, as a tuple of (player account id, amount)
+    pub fn record_bid_withdrawal(listing: &mut Listing, bidder: AccountId) -> (AccountId, Balance) {
+        assert_eq!(listing.state, ListingState::Active);
+        assert!(listing.highest_bid.map_or(false, |bid| bid.bidder == bidder));
+
+        let bid: Bid = listing.highest_bid.take().unwrap();
+        (bid.bidder, bid.amount)
+    }
+
+    /// Records the sale of a listing. The seller is paid the amount minus the marketplace fee,
+    /// and the highest bid (if it was not the one accepted) is refunded.
+    ///
+    /// # Arguments
+    ///
+    /// * `listing` - The listing
+    /// * `buyer` - The account id of the buying player
+    /// * `amount` - Amount the token is sold for
+    /// * `fee` - Marketplace fee, in percent
+    ///
+    /// # Returns
+    ///
+    /// * `payouts` - A Vec containing a tuple of (player account id, amount to pay out)
+    /// * `fee` - Amount kept as the marketplace fee
+    pub fn record_sale(listing: &mut Listing, buyer: AccountId, amount: Balance,
+        fee: u8) -> (Vec<(AccountId, Balance)>, Balance) {
+
+        assert_eq!(listing.state, ListingState::Active);
+        assert!(buyer != listing.seller);
+
+        let fee_amount: Balance = marketplace_fee(amount, fee);
+        let mut payouts: Vec<(AccountId, Balance)> = [(listing.seller, amount - fee_amount)].to_vec();
+        match listing.highest_bid.take() {
+            Some(bid) => payouts.push((bid.bidder, bid.amount)),
+            _ => (),
+        }
+
+        listing.state = ListingState::Sold;
+        listing.buyer = Some(buyer);
+        listing.sold_for = amount;
+
+        (payouts, fee_amount)
+    }
+
+    /// Records the purchase of a listing at its fixed price.
+    ///
+    /// # Arguments
+    ///
+    /// * `listing` - The listing
+    /// * `buyer` - The account id of the buying player
+    /// * `payment` - Amount paid, has to match the price
+    /// * `fee` - Marketplace fee, in percent
+    ///
+    /// # Returns
+    ///
+    /// * `payouts` - A Vec containing a tuple of (player account id, amount to pay out)
+    /// * `fee` - Amount kept as the marketplace fee
+    pub fn record_purchase(listing: &mut Listing, buyer: AccountId, payment: Balance,
+        fee: u8) -> (Vec<(AccountId, Balance)>, Balance) {
+
+        assert_eq!(payment, listing.price);
+        record_sale(listing, buyer, payment, fee)
+    }
+
+    /// Records the acceptance of the highest bid on a listing by the seller, selling the token
+    /// to the bidder for the amount bid.
+    ///
+    /// # Arguments
+    ///
+    /// * `listing` - The listing
+    /// * `caller` - The account id of the seller
+    /// * `fee` - Marketplace fee, in percent
+    ///
+    /// # Returns
+    ///
+    /// * `payouts` - A Vec containing a tuple of (player account id, amount to pay out)
+    /// * `fee` - Amount kept as the marketplace fee
+    pub fn record_bid_acceptance(listing: &mut Listing, caller: AccountId,
+        fee: u8) -> (Vec<(AccountId, Balance)>, Balance) {
+
+        assert_eq!(listing.state, ListingState::Active);
+        assert_eq!(caller, listing.seller);
+        assert!(listing.highest_bid.is_some());
+
+        let bid: Bid = listing.highest_bid.take().unwrap();
+        record_sale(listing, bid.bidder, bid.amount, fee)
+    }
+
+    /// Gets where the token of a closed listing goes out of escrow: to the buyer once sold,
+    /// back to the seller once cancelled.
+    ///
+    /// # Arguments
+    ///
+    /// * `listing` - The listing
+    ///
+    /// # Returns
+    ///
+    /// * `to` - The account id receiving the token
+    /// * `token` - Id of the commander token
+    pub fn escrow_release(listing: &Listing) -> (AccountId, u32) {
+        assert!(listing.state != ListingState::Active);
+
+        match listing.state {
+            ListingState::Sold => (listing.buyer.unwrap(), listing.token),
+            _ => (listing.seller, listing.token),
+        }
+    }
+
+    /// Gets all the transfers paying out a sale: the seller (and refunded bidder), then the fee
+    /// to the marketplace owner, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `payouts` - Payouts of the sale, as a tuple of (player account id, amount)
+    /// * `fee` - Amount kept as the marketplace fee
+    /// * `owner` - The account id receiving the fee
+    ///
+    /// # Returns
+    ///
+    /// * `transfers` - A Vec containing a tuple of (account id, amount to transfer)
+    pub fn sale_transfers(payouts: Vec<(AccountId, Balance)>, fee: Balance,
+        owner: AccountId) -> Vec<(AccountId, Balance)> {
+
+        payouts
+            .into_iter()
+            .chain(Some((owner, fee)).into_iter().filter(|&(_, amount)| amount > 0))
+            .collect()
+    }
+
+    /// Records the cancellation of a listing by the seller, refunding the highest bid.
+    ///
+    /// # Arguments
+    ///
+    /// * `listing` - The listing
+    /// * `caller` - The account id of the seller
+    ///
+    /// # Returns
+    ///
+    /// * `refunds` - A Vec containing a tuple of (player account id, amount to refund)
+    pub fn record_cancellation(listing: &mut Listing, caller: AccountId) -> Vec<(AccountId, Balance)> {
+        assert_eq!(listing.state, ListingState::Active);
+        assert_eq!(caller, listing.seller);
+
+        listing.state = ListingState::Cancelled;
+        listing.highest_bid
+            .take()
+            .map(|bid| (bid.bidder, bid.amount))
+            .into_iter()
+            .collect()
+    }
+
+    #[ink(storage)]
+    pub struct NewOmegaMarketplace {
+        owner: AccountId,
+        new_omega_storage: newomegastorage::NewOmegaStorage,
+        listings: StorageHashMap<u32, Listing>,
+        next_listing_id: u32,
+        fee: u8,
+    }
+
+    impl NewOmegaMarketplace {
+        #[ink(constructor)]
+        pub fn new(new_omega_storage: NewOmegaStorage) -> Self {
+            Self {
+                owner: Self::env().caller(),
+                new_omega_storage,
+                listings: StorageHashMap::default(),
+                next_listing_id: 0,
+                fee: DEFAULT_MARKETPLACE_FEE,
+            }
+        }
+
+        /// Sets the marketplace fee, kept from each sale.
+        ///
+        /// # Arguments
+        ///
+        /// * `fee` - Marketplace fee, in percent
+        #[ink(message)]
+        pub fn set_fee(&mut self, fee: u8) {
+            assert_eq!(self.env().caller(), self.owner);
+            assert!(fee <= MAX_MARKETPLACE_FEE);
+            self.fee = fee;
+        }
+
+        /// Gets the marketplace fee, kept from each sale.
+        ///
+        /// # Returns
+        ///
+        /// * `fee` - Marketplace fee, in percent
+        #[ink(message)]
+        pub fn get_fee(&self) -> u8 {
+            self.fee
+        }
+
+        /// Gets an active listing, for modification.
+        /// Will panic if the listing does not exist.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the listing
+        ///
+        /// # Returns
+        ///
+        /// * `listing` - The listing
+        fn get_listing_mut(&mut self, id: u32) -> &mut Listing {
+            assert!(self.listings.get(&id).is_some());
+            self.listings.get_mut(&id).unwrap()
+        }
+
+        /// Lists a commander token for sale, taking it into escrow.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the owner of the token
+        /// * `token` - Id of the commander token
+        /// * `price` - Fixed price the token can be bought for
+        ///
+        /// # Returns
+        ///
+        /// * `id` - Id of the created listing
+        #[ink(message)]
+        pub fn create_listing(&mut self, caller: AccountId, token: u32, price: Balance) -> u32 {
+            assert_eq!(self.env().caller(), self.owner);
+            assert!(price > 0);
+            assert_eq!(self.new_omega_storage.owner_of(token), Some(caller));
+
+            let listing: Listing = new_listing(caller, token, price, self.env().block_number());
+            self.new_omega_storage.transfer(caller, self.env().account_id(), token);
+
+            let id: u32 = self.next_listing_id;
+            self.listings.insert(id, listing);
+            self.next_listing_id = id + 1;
+
+            id
+        }
+
+        /// Buys a listed token at its fixed price, releasing it from escrow to the buyer.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the buyer
+        /// * `id` - Id of the listing
+        /// * `payment` - Amount paid, has to match the price
+        ///
+        /// # Returns
+        ///
+        /// * `payouts` - A Vec containing a tuple of (player account id, amount to pay out)
+        /// * `fee` - Amount kept as the marketplace fee
+        #[ink(message)]
+        pub fn buy(&mut self, caller: AccountId, id: u32, payment: Balance) -> (Vec<(AccountId, Balance)>, Balance) {
+            assert_eq!(self.env().caller(), self.owner);
+
+            let fee: u8 = self.fee;
+            let listing: &mut Listing = self.get_listing_mut(id);
+            let (payouts, fee_amount) = record_purchase(listing, caller, payment, fee);
+
+            let (to, token) = escrow_release(listing);
+            self.new_omega_storage.transfer(self.env().account_id(), to, token);
+
+            (payouts, fee_amount)
+        }
+
+        /// Places a bid on a listing, outbidding the highest bid.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the bidder
+        /// * `id` - Id of the listing
+        /// * `amount` - Amount bid
+        ///
+        /// # Returns
+        ///
+        /// * `refund` - The outbid bid to refund, if any, as a tuple of (player account id, amount)
+        #[ink(message)]
+        pub fn place_bid(&mut self, caller: AccountId, id: u32, amount: Balance) -> Option<(AccountId, Balance)> {
+            assert_eq!(self.env().caller(), self.owner);
+            record_bid(self.get_listing_mut(id), caller, amount)
+        }
+
+        /// Withdraws the highest bid on a listing.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the bidder
+        /// * `id` - Id of the listing
+        ///
+        /// # Returns
+        ///
+        /// * `refund` - The withdrawn bid to refund, as a tuple of (player account id, amount)
+        #[ink(message)]
+        pub fn cancel_bid(&mut self, caller: AccountId, id: u32) -> (AccountId, Balance) {
+            assert_eq!(self.env().caller(), self.owner);
+            record_bid_withdrawal(self.get_listing_mut(id), caller)
+        }
+
+        /// Accepts the highest bid on a listing, releasing the token from escrow to the bidder.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the seller
+        /// * `id` - Id of the listing
+        ///
+        /// # Returns
+        ///
+        /// * `payouts` - A Vec containing a tuple of (player account id, amount to pay out)
+        /// * `fee` - Amount kept as the marketplace fee
+        #[ink(message)]
+        pub fn accept_bid(&mut self, caller: AccountId, id: u32) -> (Vec<(AccountId, Balance)>, Balance) {
+            assert_eq!(self.env().caller(), self.owner);
+
+            let fee: u8 = self.fee;
+            let listing: &mut Listing = self.get_listing_mut(id);
+            let (payouts, fee_amount) = record_bid_acceptance(listing, caller, fee);
+
+            let (to, token) = escrow_release(listing);
+            self.new_omega_storage.transfer(self.env().account_id(), to, token);
+
+            (payouts, fee_amount)
+        }
+
+        /// Cancels a listing, returning the token from escrow to the seller.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the seller
+        /// * `id` - Id of the listing
+        ///
+        /// # Returns
+        ///
+        /// * `refunds` - A Vec containing a tuple of (player account id, amount to refund)
+        #[ink(message)]
+        pub fn cancel_listing(&mut self, caller: AccountId, id: u32) -> Vec<(AccountId, Balance)> {
+            assert_eq!(self.env().caller(), self.owner);
+
+            let listing: &mut Listing = self.get_listing_mut(id);
+            let refunds: Vec<(AccountId, Balance)> = record_cancellation(listing, caller);
+
+            let (to, token) = escrow_release(listing);
+            self.new_omega_storage.transfer(self.env().account_id(), to, token);
+
+            refunds
+        }
+
+        /// Gets a listing.
+        /// Will panic if the listing does not exist.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the listing
+        ///
+        /// # Returns
+        ///
+        /// * `listing` - The listing
+        #[ink(message)]
+        pub fn get_listing(&self, id: u32) -> Listing {
+            assert!(self.listings.get(&id).is_some());
+            *self.listings.get(&id).unwrap()
+        }
+
+        /// Gets all the active listings.
+        ///
+        /// # Returns
+        ///
+        /// * `listings` - A Vec containing a tuple of (listing id, listing)
+        #[ink(message)]
+        pub fn get_listings(&self) -> Vec<(u32, Listing)> {
+            self.listings
+                .iter()
+                .filter(|(_, listing)| listing.state == ListingState::Active)
+                .map(|(&id, &listing)| (id, listing))
+                .collect()
+        }
+
+        /// Gets all the listings of a player, including the closed ones.
+        ///
+        /// # Arguments
+        ///
+        /// * `seller` - The account id of the seller
+        ///
+        /// # Returns
+        ///
+        /// * `listings` - A Vec containing a tuple of (listing id, listing)
+        #[ink(message)]
+        pub fn get_player_listings(&self, seller: AccountId) -> Vec<(u32, Listing)> {
+            self.listings
+                .iter()
+                .filter(|(_, listing)| listing.seller == seller)
+                .map(|(&id, &listing)| (id, listing))
+                .collect()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use ink_env::{
+            call::FromAccountId,
+            test,
+        };
+        use ink_lang as ink;
+        type Accounts = test::DefaultAccounts<Environment>;
+
+        fn default_accounts() -> Accounts {
+            test::default_accounts()
+                .expect("Test environment is expected to be initialized.")
+        }
+
+        /// Lists token 7 of alice
+        fn alice_listing(price: Balance) -> Listing {
+            new_listing(default_accounts().alice, 7, price, 0)
+        }
+
+        #[ink::test]
+        fn test_marketplace_fee() {
+            let mut contract = NewOmegaMarketplace::new(NewOmegaStorage::from_account_id(default_accounts().django));
+
+            assert_eq!(contract.get_fee(), DEFAULT_MARKETPLACE_FEE);
+            contract.set_fee(10);
+            assert_eq!(contract.get_fee(), 10);
+
+            assert_eq!(marketplace_fee(100, 10), 10);
+            assert_eq!(marketplace_fee(5, 10), 0);
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_marketplace_fee_too_high() {
+            let mut contract = NewOmegaMarketplace::new(NewOmegaStorage::from_account_id(default_accounts().django));
+            contract.set_fee(MAX_MARKETPLACE_FEE + 1);
+        }
+
+        #[ink::test]
+        fn test_listing_create() {
+            let accounts = default_accounts();
+            let listing: Listing = new_listing(accounts.alice, 7, 100, 3);
+
+            assert_eq!(listing.seller, accounts.alice);
+            assert_eq!(listing.token, 7);
+            assert_eq!(listing.price, 100);
+            assert_eq!(listing.state, ListingState::Active);
+            assert_eq!(listing.created, 3);
+            assert_eq!((listing.highest_bid, listing.buyer, listing.sold_for), (None, None, 0));
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_listing_create_free() {
+            new_listing(default_accounts().alice, 7, 0, 0);
+        }
+
+        #[ink::test]
+        fn test_listing_purchase() {
+            let accounts = default_accounts();
+            let mut listing: Listing = alice_listing(100);
+
+            assert_eq!(record_purchase(&mut listing, accounts.bob, 100, 5),
+                ([(accounts.alice, 95)].to_vec(), 5));
+            assert_eq!(listing.buyer, Some(accounts.bob));
+
+            // The token leaves escrow to the buyer
+            assert_eq!(escrow_release(&listing), (accounts.bob, 7));
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_listing_purchase_underpaid() {
+            let mut listing: Listing = alice_listing(100);
+            record_purchase(&mut listing, default_accounts().bob, 99, 5);
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_listing_purchase_overpaid() {
+            let mut listing: Listing = alice_listing(100);
+            record_purchase(&mut listing, default_accounts().bob, 101, 5);
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_listing_purchase_by_seller() {
+            let mut listing: Listing = alice_listing(100);
+            record_purchase(&mut listing, default_accounts().alice, 100, 5);
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_listing_purchase_cancelled() {
+            let accounts = default_accounts();
+            let mut listing: Listing = alice_listing(100);
+
+            record_cancellation(&mut listing, accounts.alice);
+            record_purchase(&mut listing, accounts.bob, 100, 5);
+        }
+
+        #[ink::test]
+        fn test_listing_bid_acceptance() {
+            let accounts = default_accounts();
+            let mut listing: Listing = alice_listing(100);
+
+            record_bid(&mut listing, accounts.bob, 40);
+            assert_eq!(record_bid_acceptance(&mut listing, accounts.alice, 5), ([(accounts.alice, 38)].to_vec(), 2));
+            assert_eq!(listing.state, ListingState::Sold);
+            assert_eq!(listing.buyer, Some(accounts.bob));
+            assert_eq!(listing.sold_for, 40);
+            assert_eq!(escrow_release(&listing), (accounts.bob, 7));
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_listing_bid_acceptance_by_stranger() {
+            let accounts = default_accounts();
+            let mut listing: Listing = alice_listing(100);
+
+            record_bid(&mut listing, accounts.bob, 40);
+            record_bid_acceptance(&mut listing, accounts.charlie, 5);
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_listing_bid_acceptance_without_bid() {
+            let mut listing: Listing = alice_listing(100);
+            record_bid_acceptance(&mut listing, default_accounts().alice, 5);
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_listing_bid_acceptance_sold() {
+            let accounts = default_accounts();
+            let mut listing: Listing = alice_listing(100);
+
+            record_bid(&mut listing, accounts.bob, 40);
+            record_bid_acceptance(&mut listing, accounts.alice, 5);
+            record_bid_acceptance(&mut listing, accounts.alice, 5);
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_listing_escrow_active() {
+            escrow_release(&alice_listing(100));
+        }
+
+        #[ink::test]
+        fn test_sale_transfers() {
+            let accounts = default_accounts();
+            let mut listing: Listing = alice_listing(100);
+
+            // The seller and the refunded bidder are paid first, the fee goes to the owner
+            record_bid(&mut listing, accounts.bob, 50);
+            let (payouts, fee) = record_purchase(&mut listing, accounts.charlie, 100, 5);
+            assert_eq!(sale_transfers(payouts, fee, accounts.eve),
+                [(accounts.alice, 95), (accounts.bob, 50), (accounts.eve, 5)].to_vec());
+
+            // Without a fee, the owner gets nothing
+            let mut listing: Listing = alice_listing(100);
+            let (payouts, fee) = record_purchase(&mut listing, accounts.charlie, 100, 0);
+            assert_eq!(sale_transfers(payouts, fee, accounts.eve), [(accounts.alice, 100)].to_vec());
+        }
+
+        #[ink::test]
+        fn test_listing_buy() {
+            let accounts = default_accounts();
+            let mut listing: Listing = alice_listing(100);
+
+            // The seller gets the price minus the fee, the pending bid is refunded
+            assert_eq!(record_bid(&mut listing, accounts.bob, 50), None);
+            assert_eq!(record_sale(&mut listing, accounts.charlie, 100, 5),
+                ([(accounts.alice, 95), (accounts.bob, 50)].to_vec(), 5));
+            assert_eq!(listing.state, ListingState::Sold);
+            assert_eq!(listing.buyer, Some(accounts.charlie));
+            assert_eq!(listing.sold_for, 100);
+            assert_eq!(listing.highest_bid, None);
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_listing_buy_sold() {
+            let accounts = default_accounts();
+            let mut listing: Listing = alice_listing(100);
+
+            record_sale(&mut listing, accounts.bob, 100, 5);
+            record_sale(&mut listing, accounts.charlie, 100, 5);
+        }
+
+        #[ink::test]
+        fn test_listing_bids() {
+            let accounts = default_accounts();
+            let mut listing: Listing = alice_listing(100);
+
+            // Outbid bids are refunded, a withdrawn bid too
+            assert_eq!(record_bid(&mut listing, accounts.bob, 50), None);
+            assert_eq!(record_bid(&mut listing, accounts.charlie, 60), Some((accounts.bob, 50)));
+            assert_eq!(record_bid_withdrawal(&mut listing, accounts.charlie), (accounts.charlie, 60));
+            assert_eq!(listing.highest_bid, None);
+
+            // A new bid can be placed after a withdrawal
+            assert_eq!(record_bid(&mut listing, accounts.bob, 40), None);
+            assert_eq!(listing.highest_bid, Some(Bid {
+                bidder: accounts.bob,
+                amount: 40,
+            }));
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_listing_bid_too_low() {
+            let accounts = default_accounts();
+            let mut listing: Listing = alice_listing(100);
+
+            record_bid(&mut listing, accounts.bob, 50);
+            record_bid(&mut listing, accounts.charlie, 50);
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_listing_bid_by_seller() {
+            let mut listing: Listing = alice_listing(100);
+            record_bid(&mut listing, default_accounts().alice, 50);
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_listing_bid_withdrawal_by_stranger() {
+            let accounts = default_accounts();
+            let mut listing: Listing = alice_listing(100);
+
+            record_bid(&mut listing, accounts.bob, 50);
+            record_bid_withdrawal(&mut listing, accounts.charlie);
+        }
+
+        #[ink::test]
+        fn test_listing_cancel() {
+            let accounts = default_accounts();
+            let mut listing: Listing = alice_listing(100);
+
+            record_bid(&mut listing, accounts.bob, 50);
+            assert_eq!(record_cancellation(&mut listing, accounts.alice), [(accounts.bob, 50)].to_vec());
+            assert_eq!(listing.state, ListingState::Cancelled);
+
+            // The token goes back to the seller
+            assert_eq!(escrow_release(&listing), (accounts.alice, 7));
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_listing_cancel_by_stranger() {
+            let accounts = default_accounts();
+            let mut listing: Listing = alice_listing(100);
+            record_cancellation(&mut listing, accounts.bob);
+        }
+    }
+}
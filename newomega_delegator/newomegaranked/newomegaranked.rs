@@ -5,6 +5,10 @@
 use ink_lang as ink;
 pub use self::newomegaranked::NewOmegaRanked;
 pub use self::newomegaranked::PlayerDefence;
+pub use self::newomegaranked::DefencePreset;
+pub use self::newomegaranked::RotationPolicy;
+pub use self::newomegaranked::RankedFight;
+pub use self::newomegaranked::match_opponents;
 
 /// The logic for all ranked fights between players. Connected to Fight Management
 /// in order to run fights, and to Storage in order to save the results and perform
@@ -13,10 +17,16 @@ pub use self::newomegaranked::PlayerDefence;
 mod newomegaranked {
     use newomegagame::NewOmegaGame;
     use newomegastorage::NewOmegaStorage;
+    use newomegastorage::PlayerData;
+    use newomegastorage::SeasonRecord;
+    use newomegastorage::League;
     use newomega::MAX_SHIPS;
+    use newomega::Reserve;
+    use newomega::is_reserve_valid;
     use newomega::FightResult;
     use ink_prelude::vec::Vec;
     use ink_prelude::string::String;
+    use ink_env::hash::Blake2x256;
     use ink_storage::{
         collections::{
             HashMap as StorageHashMap,
@@ -28,8 +38,17 @@ mod newomegaranked {
     };
 
     const XP_PER_RANKED_WIN: u32 = 1;
+    const MAX_DEFENCE_PRESETS: u8 = 4;
+    /// Roughly a week, with 6 second blocks
+    const DEFAULT_PAUSE_DURATION: BlockNumber = 100_800;
+    /// Players can only attack players at most this many leagues away
+    const MAX_LEAGUE_DISTANCE: u8 = 1;
+    /// Targets attacked within this many blocks are not suggested again, roughly an hour
+    const RECENT_ATTACK_WINDOW: BlockNumber = 600;
+    /// Ranked fights recorded per player, older fights are dropped
+    const MAX_RECORDED_FIGHTS: usize = 50;
 
-    /// Describes a registered defence of a player
+    /// Describes a defence preset of a player
     #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Clone)]
     #[cfg_attr(
         feature = "std",
@@ -41,15 +60,199 @@ mod newomegaranked {
             ink_storage::traits::StorageLayout
         )
     )]
-    pub struct PlayerDefence {
+    pub struct DefencePreset {
         /// Fleet composition
         selection: [u8; MAX_SHIPS],
         /// Fleet variants (fittings)
         variants: [u8; MAX_SHIPS],
         /// Commander index
         commander: u8,
+        /// Reserve group, entering the battle later
+        reserve: Reserve,
+        /// Preset name
+        label: String,
+        /// Revision of the preset, unique among all the presets ever set
+        revision: u32,
+    }
+
+    /// Describes how the defence preset is picked for each attack
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Copy, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            Debug,
+            PartialEq,
+            Eq,
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub enum RotationPolicy {
+        /// Always defend with the active preset
+        Fixed,
+        /// Defend with the active preset, then move on to the next one
+        RoundRobin,
+        /// Defend with a random preset
+        Random,
+    }
+
+    /// Describes a registered defence of a player
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            Debug,
+            PartialEq,
+            Eq,
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub struct PlayerDefence {
+        /// Defence presets, at most MAX_DEFENCE_PRESETS
+        presets: Vec<DefencePreset>,
+        /// How the preset is picked for each attack
+        policy: RotationPolicy,
+        /// Index of the active preset
+        active: u8,
         /// Defender name
         name: String,
+        /// Block until which the defence is paused (vacation mode), 0 if not paused
+        paused_until: BlockNumber,
+    }
+
+    /// Describes a recorded ranked fight
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            Debug,
+            PartialEq,
+            Eq,
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub struct RankedFight {
+        /// Account id of the attacker
+        attacker: AccountId,
+        /// Account id of the defender
+        defender: AccountId,
+        /// Index of the defence preset used
+        preset: u8,
+        /// Revision of the defence preset used, to tell whether the preset was replaced since
+        preset_revision: u32,
+        /// Commitment hash of the fight
+        hash: [u8; 32],
+        /// Block the fight took place in
+        block: BlockNumber,
+        /// Season the fight took place in
+        season: u32,
+    }
+
+    /// Picks the fairest opponents for a player: the candidates closest to the player's rating.
+    /// Ties are broken by a hash of the block and the candidate, so the result is deterministic
+    /// for a given block, but varies between blocks.
+    ///
+    /// # Arguments
+    ///
+    /// * `caller` - The account id of the player looking for opponents
+    /// * `caller_rating` - Rating of the player
+    /// * `candidates` - The possible opponents, with their ratings
+    /// * `excluded` - The opponents not to suggest (e.g. recently attacked)
+    /// * `block` - The current block
+    /// * `count` - Maximum number of opponents to return
+    ///
+    /// # Returns
+    ///
+    /// * `opponents` - The suggested opponents, fairest first
+    pub fn match_opponents(caller: AccountId, caller_rating: i32, candidates: Vec<(AccountId, i32)>,
+        excluded: &Vec<AccountId>, block: BlockNumber, count: u8) -> Vec<AccountId> {
+
+        let mut scored: Vec<(u32, u64, AccountId)> = candidates
+            .into_iter()
+            .filter(|(account, _)| *account != caller && !excluded.contains(account))
+            .map(|(account, rating)| {
+                let mut output: [u8; 32] = [0; 32];
+                ink_env::hash_encoded::<Blake2x256, _>(&(block, account), &mut output);
+                let mut tiebreak: [u8; 8] = [0; 8];
+                tiebreak.copy_from_slice(&output[0..8]);
+
+                ((rating - caller_rating).abs() as u32, u64::from_le_bytes(tiebreak), account)
+            })
+            .collect();
+
+        scored.sort();
+        scored
+            .into_iter()
+            .take(count as usize)
+            .map(|(_, _, account)| account)
+            .collect()
+    }
+
+    /// Picks the defence preset for an attack, according to the rotation policy.
+    ///
+    /// # Arguments
+    ///
+    /// * `defence` - The defence of the player
+    /// * `seed` - Seed of the fight
+    ///
+    /// # Returns
+    ///
+    /// * `preset` - Index of the picked preset
+    pub fn pick_preset(defence: &PlayerDefence, seed: u64) -> u8 {
+        match defence.policy {
+            RotationPolicy::Random => {
+                let mut output: [u8; 32] = [0; 32];
+                ink_env::hash_encoded::<Blake2x256, _>(&seed, &mut output);
+                output[0] % (defence.presets.len() as u8)
+            },
+            _ => defence.active,
+        }
+    }
+
+    /// Advances the rotation of a defence after an attack: round-robin moves on to the next preset.
+    ///
+    /// # Arguments
+    ///
+    /// * `defence` - The defence of the player (mutable)
+    pub fn advance_rotation(defence: &mut PlayerDefence) {
+        match defence.policy {
+            RotationPolicy::RoundRobin =>
+                defence.active = (defence.active + 1) % (defence.presets.len() as u8),
+            _ => (),
+        }
+    }
+
+    /// Records a ranked fight of a player, dropping the oldest one once MAX_RECORDED_FIGHTS are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `fights` - The recorded fights of the player, oldest first (mutable)
+    /// * `fight` - The fight to record
+    pub fn record_fight(fights: &mut Vec<RankedFight>, fight: RankedFight) {
+        if fights.len() >= MAX_RECORDED_FIGHTS {
+            fights.remove(0);
+        }
+        fights.push(fight);
+    }
+
+    /// Gets the defence preset used in a recorded fight. The preset index is only valid as long as
+    /// the preset was not replaced since, so the revision of the preset has to match as well.
+    ///
+    /// # Arguments
+    ///
+    /// * `defence` - The defence of the defender
+    /// * `fight` - The recorded fight
+    ///
+    /// # Returns
+    ///
+    /// * `preset` - The preset used, None if it was replaced or removed since
+    pub fn fight_preset(defence: &PlayerDefence, fight: &RankedFight) -> Option<DefencePreset> {
+        defence.presets
+            .get(fight.preset as usize)
+            .filter(|preset| preset.revision == fight.preset_revision)
+            .cloned()
     }
 
     #[ink(storage)]
@@ -58,6 +261,9 @@ mod newomegaranked {
         new_omega_game: newomegagame::NewOmegaGame,
         new_omega_storage: newomegastorage::NewOmegaStorage,
         defences: StorageHashMap<AccountId, PlayerDefence>,
+        fights: StorageHashMap<AccountId, Vec<RankedFight>>,
+        pause_duration: BlockNumber,
+        next_preset_revision: u32,
     }
 
     impl NewOmegaRanked {
@@ -68,10 +274,14 @@ mod newomegaranked {
                 new_omega_game,
                 new_omega_storage,
                 defences: StorageHashMap::default(),
+                fights: StorageHashMap::default(),
+                pause_duration: DEFAULT_PAUSE_DURATION,
+                next_preset_revision: 0,
             }
         }
 
-        /// Registers a fleet for Ranked Defence.
+        /// Registers a fleet for Ranked Defence, as the only defence preset of the player.
+        /// Replaces all the previously registered presets.
         ///
         /// # Arguments
         ///
@@ -79,18 +289,241 @@ mod newomegaranked {
         /// * `selection` - The fleet composition of the defence
         /// * `variants` - The variants (fittings) of the defence
         /// * `commander` - Index of the commander leading the defence
+        /// * `reserve` - The reserve of the defence
         /// * `name` - The defender name
         #[ink(message)]
         pub fn register_defence(&mut self, caller: AccountId, selection: [u8; MAX_SHIPS],
-            variants: [u8; MAX_SHIPS], commander: u8, name: String) {
+            variants: [u8; MAX_SHIPS], commander: u8, reserve: Reserve, name: String) {
 
             assert_eq!(self.env().caller(), self.owner);
+            assert!(is_reserve_valid(selection, reserve));
+            let preset: DefencePreset = self.new_preset(name.clone(), selection, variants, commander, reserve);
             self.defences.insert(caller, PlayerDefence {
+                presets: [preset].to_vec(),
+                policy: RotationPolicy::Fixed,
+                active: 0,
+                name,
+                paused_until: 0,
+            });
+        }
+
+        /// Unregisters the defence of a player, withdrawing them from ranked.
+        /// Will panic if defence has not been registered for the player.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the player to unregister the defence for
+        #[ink(message)]
+        pub fn unregister_defence(&mut self, caller: AccountId) {
+            assert_eq!(self.env().caller(), self.owner);
+            assert!(self.defences.take(&caller).is_some());
+        }
+
+        /// Pauses the defence of a player (vacation mode), for the configured number of blocks.
+        /// A paused defence is hidden from the defenders list and cannot be attacked.
+        /// Will panic if defence has not been registered for the player.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the player to pause the defence for
+        #[ink(message)]
+        pub fn pause_defence(&mut self, caller: AccountId) {
+            assert_eq!(self.env().caller(), self.owner);
+            assert!(self.defences.get(&caller).is_some());
+
+            let paused_until: BlockNumber = self.env().block_number() + self.pause_duration;
+            self.defences.get_mut(&caller).unwrap().paused_until = paused_until;
+        }
+
+        /// Resumes the paused defence of a player, before the pause expires.
+        /// Will panic if defence has not been registered for the player.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the player to resume the defence for
+        #[ink(message)]
+        pub fn resume_defence(&mut self, caller: AccountId) {
+            assert_eq!(self.env().caller(), self.owner);
+            assert!(self.defences.get(&caller).is_some());
+            self.defences.get_mut(&caller).unwrap().paused_until = 0;
+        }
+
+        /// Sets the number of blocks a defence pause lasts for.
+        ///
+        /// # Arguments
+        ///
+        /// * `pause_duration` - Duration of the pause, in blocks
+        #[ink(message)]
+        pub fn set_pause_duration(&mut self, pause_duration: BlockNumber) {
+            assert_eq!(self.env().caller(), self.owner);
+            self.pause_duration = pause_duration;
+        }
+
+        /// Gets the number of blocks a defence pause lasts for.
+        ///
+        /// # Returns
+        ///
+        /// * `pause_duration` - Duration of the pause, in blocks
+        #[ink(message)]
+        pub fn get_pause_duration(&self) -> BlockNumber {
+            self.pause_duration
+        }
+
+        /// Checks whether a defence is currently paused
+        ///
+        /// # Arguments
+        ///
+        /// * `defence` - The defence of the player
+        ///
+        /// # Returns
+        ///
+        /// * `is_paused` - Whether the defence is paused
+        fn is_paused(&self, defence: &PlayerDefence) -> bool {
+            self.env().block_number() < defence.paused_until
+        }
+
+        /// Sets a defence preset of a player, replacing the preset in the slot,
+        /// or adding a new one if the slot is right after the last preset.
+        /// Will panic if defence has not been registered for the player.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the player to set the preset for
+        /// * `slot` - Index of the preset
+        /// * `label` - The preset name
+        /// * `selection` - The fleet composition of the preset
+        /// * `variants` - The variants (fittings) of the preset
+        /// * `commander` - Index of the commander leading the preset
+        /// * `reserve` - The reserve of the preset
+        #[ink(message)]
+        pub fn set_defence_preset(&mut self, caller: AccountId, slot: u8, label: String,
+            selection: [u8; MAX_SHIPS], variants: [u8; MAX_SHIPS], commander: u8, reserve: Reserve) {
+
+            assert_eq!(self.env().caller(), self.owner);
+            assert!(slot < MAX_DEFENCE_PRESETS);
+            assert!(self.defences.get(&caller).is_some());
+            assert!(is_reserve_valid(selection, reserve));
+
+            let preset: DefencePreset = self.new_preset(label, selection, variants, commander, reserve);
+            let defence: &mut PlayerDefence = self.defences.get_mut(&caller).unwrap();
+
+            assert!((slot as usize) <= defence.presets.len());
+            if (slot as usize) == defence.presets.len() {
+                defence.presets.push(preset);
+            } else {
+                defence.presets[slot as usize] = preset;
+            }
+        }
+
+        /// Creates a defence preset, with a new revision.
+        ///
+        /// # Arguments
+        ///
+        /// * `label` - The preset name
+        /// * `selection` - The fleet composition of the preset
+        /// * `variants` - The variants (fittings) of the preset
+        /// * `commander` - Index of the commander leading the preset
+        /// * `reserve` - The reserve of the preset
+        ///
+        /// # Returns
+        ///
+        /// * `preset` - The defence preset
+        fn new_preset(&mut self, label: String, selection: [u8; MAX_SHIPS], variants: [u8; MAX_SHIPS],
+            commander: u8, reserve: Reserve) -> DefencePreset {
+
+            let revision: u32 = self.next_preset_revision;
+            self.next_preset_revision += 1;
+
+            DefencePreset {
                 selection,
                 variants,
                 commander,
-                name,
-            });
+                reserve,
+                label,
+                revision,
+            }
+        }
+
+        /// Sets how the defence preset of a player is picked for each attack.
+        /// Will panic if defence has not been registered for the player.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the player to set the policy for
+        /// * `policy` - The rotation policy
+        /// * `active` - Index of the active preset (the next one, for round-robin)
+        #[ink(message)]
+        pub fn set_defence_policy(&mut self, caller: AccountId, policy: RotationPolicy, active: u8) {
+            assert_eq!(self.env().caller(), self.owner);
+            assert!(self.defences.get(&caller).is_some());
+
+            let defence: &mut PlayerDefence = self.defences.get_mut(&caller).unwrap();
+
+            assert!((active as usize) < defence.presets.len());
+            defence.policy = policy;
+            defence.active = active;
+        }
+
+        /// Gets the defence presets of a player.
+        /// Will panic if defence has not been registered for the player.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the player
+        ///
+        /// # Returns
+        ///
+        /// * `presets` - The defence presets
+        #[ink(message)]
+        pub fn get_defence_presets(&self, caller: AccountId) -> Vec<DefencePreset> {
+            assert_eq!(self.env().caller(), self.owner);
+            assert!(self.defences.get(&caller).is_some());
+
+            self.defences.get(&caller).unwrap().presets.clone()
+        }
+
+        /// Gets the recorded ranked fights of a player, as attacker or defender.
+        /// Only the last MAX_RECORDED_FIGHTS fights of each player are kept.
+        ///
+        /// # Arguments
+        ///
+        /// * `player` - The account id of the player
+        ///
+        /// # Returns
+        ///
+        /// * `fights` - The recorded fights, oldest first
+        #[ink(message)]
+        pub fn get_fights(&self, player: AccountId) -> Vec<RankedFight> {
+            self.fights.get(&player).cloned().unwrap_or_default()
+        }
+
+        /// Gets the defence preset used in a recorded fight, as long as the defender did not
+        /// replace or remove it since.
+        ///
+        /// # Arguments
+        ///
+        /// * `fight` - The recorded fight
+        ///
+        /// # Returns
+        ///
+        /// * `preset` - The preset used, None if it was replaced or removed since
+        #[ink(message)]
+        pub fn get_fight_preset(&self, fight: RankedFight) -> Option<DefencePreset> {
+            self.defences
+                .get(&fight.defender)
+                .and_then(|defence| fight_preset(defence, &fight))
+        }
+
+        /// Records a ranked fight for both the attacker and the defender.
+        ///
+        /// # Arguments
+        ///
+        /// * `fight` - The fight to record
+        fn record_fight(&mut self, fight: RankedFight) {
+            record_fight(self.fights.entry(fight.attacker).or_insert(Vec::new()), fight.clone());
+            if fight.defender != fight.attacker {
+                record_fight(self.fights.entry(fight.defender).or_insert(Vec::new()), fight);
+            }
         }
 
         /// Gets the registered defence of a player.
@@ -108,17 +541,10 @@ mod newomegaranked {
             assert_eq!(self.env().caller(), self.owner);
             assert!(self.defences.get(&caller).is_some());
 
-            let defence: &PlayerDefence = self.defences.get(&caller).unwrap();
-
-            PlayerDefence {
-                selection: defence.selection,
-                variants: defence.variants,
-                commander: defence.commander,
-                name: defence.name.clone(),
-            }
+            self.defences.get(&caller).unwrap().clone()
         }
 
-        /// Gets all the registered defenders (all players).
+        /// Gets all the registered defenders (all players), except the paused ones.
         ///
         /// # Returns
         ///
@@ -129,12 +555,75 @@ mod newomegaranked {
                 .iter()
                 .filter_map(|entry| {
                     let (&key, value) = entry;
-                    Some((key, value.clone()))
+                    if self.is_paused(value) {
+                        None
+                    } else {
+                        Some((key, value.clone()))
+                    }
                 })
                 .collect()
         }
 
+        /// Closes the current ranked season: the final leaderboard is archived in Storage,
+        /// and the ranked stats of all players are reset. Recorded fights are kept,
+        /// tagged with the season they took place in.
+        ///
+        /// # Returns
+        ///
+        /// * `record` - The archived season record
+        #[ink(message)]
+        pub fn close_season(&mut self) -> SeasonRecord {
+            assert_eq!(self.env().caller(), self.owner);
+            self.new_omega_storage.close_season()
+        }
+
+        /// Suggests fair opponents for a player: active defenders within reach of the player's league,
+        /// closest to the player's rating, excluding targets the player attacked recently.
+        /// Deterministic for a given block.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the player looking for opponents
+        /// * `count` - Maximum number of opponents to return
+        ///
+        /// # Returns
+        ///
+        /// * `opponents` - The suggested defenders, fairest first
+        #[ink(message)]
+        pub fn find_opponents(&self, caller: AccountId, count: u8) -> Vec<(AccountId, PlayerDefence)> {
+            let block: BlockNumber = self.env().block_number();
+            let leaderboard: Vec<(AccountId, PlayerData)> = self.new_omega_storage.get_leaderboard();
+            let data_of = |player: &AccountId| -> PlayerData {
+                leaderboard
+                    .iter()
+                    .find(|(account, _)| account == player)
+                    .map_or(PlayerData::default(), |(_, data)| *data)
+            };
+            let caller_data: PlayerData = data_of(&caller);
+
+            let recent: Vec<AccountId> = self.get_fights(caller)
+                .iter()
+                .filter(|fight| fight.attacker == caller && fight.block + RECENT_ATTACK_WINDOW > block)
+                .map(|fight| fight.defender)
+                .collect();
+            let candidates: Vec<(AccountId, i32)> = self.get_all_defenders()
+                .iter()
+                .map(|(account, _)| (*account, data_of(account)))
+                .filter(|(_, data)| data.get_league().distance(caller_data.get_league()) <= MAX_LEAGUE_DISTANCE)
+                .map(|(account, data)| (account, data.get_rating()))
+                .collect();
+
+            match_opponents(caller, caller_data.get_rating(), candidates, &recent, block, count)
+                .into_iter()
+                .map(|account| (account, self.defences.get(&account).unwrap().clone()))
+                .collect()
+        }
+
         /// Calculates a ranked fight between two players.
+        /// Paused defences cannot be attacked, and attacking ends the pause of the attacker.
+        /// Players can only attack players at most one league away.
+        /// Commanders fight at the level and with the skills they have in the storage,
+        /// unowned commanders give no bonus.
         ///
         /// # Arguments
         ///
@@ -143,44 +632,358 @@ mod newomegaranked {
         /// * `selection` - Attacker fleet composition (array with ship quantities)
         /// * `variants` - An array that holds variants of the attacker fleet
         /// * `commander` - The attacker commander
+        /// * `reserve` - The attacker reserve
         #[ink(message)]
         pub fn attack(&mut self, caller: AccountId, target: AccountId, selection: [u8; MAX_SHIPS],
-            variants: [u8; MAX_SHIPS], commander: u8) -> FightResult {
+            variants: [u8; MAX_SHIPS], commander: u8, reserve: Reserve) -> FightResult {
 
             assert_eq!(self.env().caller(), self.owner);
             assert!(self.defences.get(&caller).is_some());
             assert!(self.defences.get(&target).is_some());
+            assert!(!self.is_paused(self.defences.get(&target).unwrap()));
+            let caller_league: League = self.new_omega_storage.get_league(caller);
+            assert!(caller_league.distance(self.new_omega_storage.get_league(target)) <= MAX_LEAGUE_DISTANCE);
+
+            // Players on vacation can't attack without being attackable
+            self.defences.get_mut(&caller).unwrap().paused_until = 0;
 
-            // Try to get the defence
-            let target_defence: &PlayerDefence = self.defences.get(&target).unwrap();
             // Determine the seed, in a naive way -> IMPROVEME: MOVE TO VRF
             let seed: u64 = self.env().block_timestamp();
+            // Try to get the defence, with the preset picked according to the rotation policy
+            let preset: u8 = pick_preset(self.defences.get(&target).unwrap(), seed);
+            let target_defence: DefencePreset = self.defences.get(&target).unwrap().presets[preset as usize].clone();
+            // The map is picked from the seed as well
+            let map: u8 = self.new_omega_game.pick_map(seed);
             // Calculate the fight result
             let (result, _lhs_moves, _rhs_moves) =
                 self.new_omega_game.fight(
                     seed,
                     false,
+                    true,
                     selection,
                     target_defence.selection,
                     variants,
                     target_defence.variants,
                     commander,
-                    target_defence.commander);
+                    target_defence.commander,
+                    self.new_omega_storage.get_commander_level(caller, commander),
+                    self.new_omega_storage.get_commander_level(target, target_defence.commander),
+                    self.new_omega_storage.get_commander_skills(caller, commander),
+                    self.new_omega_storage.get_commander_skills(target, target_defence.commander),
+                    reserve,
+                    target_defence.reserve,
+                    map);
 
-            // Mark results of the fight on the leaderboard and adjust commander xp
-            if result.lhs_dead {
+            // Mark results of the fight on the leaderboard and adjust commander xp.
+            // A retreat is marked separately from a loss, as the fleet was not wiped out.
+            if result.lhs_dead || result.lhs_retreated {
                 self.new_omega_storage.mark_ranked_win(target);
-                self.new_omega_storage.mark_ranked_loss(caller);
+                if result.lhs_retreated {
+                    self.new_omega_storage.mark_ranked_retreat(caller);
+                } else {
+                    self.new_omega_storage.mark_ranked_loss(caller);
+                }
                 self.new_omega_storage.add_commander_xp(target,
                     target_defence.commander, XP_PER_RANKED_WIN);
-            } else if result.rhs_dead {
+            } else if result.rhs_dead || result.rhs_retreated {
                 self.new_omega_storage.mark_ranked_win(caller);
-                self.new_omega_storage.mark_ranked_loss(target);
+                if result.rhs_retreated {
+                    self.new_omega_storage.mark_ranked_retreat(target);
+                } else {
+                    self.new_omega_storage.mark_ranked_loss(target);
+                }
                 self.new_omega_storage.add_commander_xp(caller,
                     commander, XP_PER_RANKED_WIN);
             }
 
+            // Advance the rotation
+            advance_rotation(self.defences.get_mut(&target).unwrap());
+
+            // Record the fight
+            self.record_fight(RankedFight {
+                attacker: caller,
+                defender: target,
+                preset,
+                preset_revision: target_defence.revision,
+                hash: result.hash,
+                block: self.env().block_number(),
+                season: self.new_omega_storage.get_season().0,
+            });
+
             result
         }
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use ink_env::{
+            call::FromAccountId,
+            test,
+        };
+        use ink_lang as ink;
+        type Accounts = test::DefaultAccounts<Environment>;
+
+        fn default_accounts() -> Accounts {
+            test::default_accounts()
+                .expect("Test environment is expected to be initialized.")
+        }
+
+        fn new_contract() -> NewOmegaRanked {
+            let accounts = default_accounts();
+            NewOmegaRanked::new(NewOmegaGame::from_account_id(accounts.django),
+                NewOmegaStorage::from_account_id(accounts.frank))
+        }
+
+        fn register(contract: &mut NewOmegaRanked, player: AccountId, presets: u8) {
+            contract.register_defence(player, [1, 1, 1, 1], [0, 0, 0, 0], 0, Reserve::default(),
+                String::from("Defence"));
+            for slot in 1..presets {
+                contract.set_defence_preset(player, slot, String::from("Preset"), [slot, 1, 1, 1],
+                    [0, 0, 0, 0], 0, Reserve::default());
+            }
+        }
+
+        fn new_fight(attacker: AccountId, defender: AccountId, block: BlockNumber) -> RankedFight {
+            RankedFight {
+                attacker,
+                defender,
+                preset: 0,
+                preset_revision: 0,
+                hash: [0; 32],
+                block,
+                season: 0,
+            }
+        }
+
+        #[ink::test]
+        fn test_defence_presets() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+
+            register(&mut contract, accounts.alice, 3);
+            let presets: Vec<DefencePreset> = contract.get_defence_presets(accounts.alice);
+            assert_eq!(presets.len(), 3);
+            assert_eq!(presets[0].label, String::from("Defence"));
+            assert_eq!(presets[2].selection, [2, 1, 1, 1]);
+
+            // Setting an existing slot replaces the preset
+            contract.set_defence_preset(accounts.alice, 1, String::from("Replaced"), [5, 5, 5, 5],
+                [0, 0, 0, 0], 1, Reserve::default());
+            let presets: Vec<DefencePreset> = contract.get_defence_presets(accounts.alice);
+            assert_eq!(presets.len(), 3);
+            assert_eq!(presets[1].label, String::from("Replaced"));
+            assert_eq!(presets[1].commander, 1);
+
+            // Registering again keeps only the registered preset
+            contract.set_defence_policy(accounts.alice, RotationPolicy::RoundRobin, 2);
+            register(&mut contract, accounts.alice, 1);
+            let defence: PlayerDefence = contract.get_own_defence(accounts.alice);
+            assert_eq!(defence.presets.len(), 1);
+            assert_eq!(defence.policy, RotationPolicy::Fixed);
+            assert_eq!(defence.active, 0);
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_defence_preset_gap() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+
+            register(&mut contract, accounts.alice, 1);
+            contract.set_defence_preset(accounts.alice, 2, String::from("Gap"), [1, 1, 1, 1],
+                [0, 0, 0, 0], 0, Reserve::default());
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_defence_preset_limit() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+
+            register(&mut contract, accounts.alice, MAX_DEFENCE_PRESETS + 1);
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_defence_policy_missing_preset() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+
+            register(&mut contract, accounts.alice, 2);
+            contract.set_defence_policy(accounts.alice, RotationPolicy::Fixed, 2);
+        }
+
+        #[ink::test]
+        fn test_defence_rotation() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+            register(&mut contract, accounts.alice, 3);
+
+            // Fixed always defends with the active preset
+            contract.set_defence_policy(accounts.alice, RotationPolicy::Fixed, 1);
+            let mut defence: PlayerDefence = contract.get_own_defence(accounts.alice);
+            for seed in 0..5 {
+                assert_eq!(pick_preset(&defence, seed), 1);
+                advance_rotation(&mut defence);
+            }
+
+            // Round-robin moves on to the next preset after every attack, wrapping around
+            contract.set_defence_policy(accounts.alice, RotationPolicy::RoundRobin, 1);
+            let mut defence: PlayerDefence = contract.get_own_defence(accounts.alice);
+            let picked: Vec<u8> = (0..5)
+                .map(|seed| {
+                    let preset: u8 = pick_preset(&defence, seed);
+                    advance_rotation(&mut defence);
+                    preset
+                })
+                .collect();
+            assert_eq!(picked, [1, 2, 0, 1, 2].to_vec());
+
+            // Random picks any of the presets, deterministically for a seed
+            contract.set_defence_policy(accounts.alice, RotationPolicy::Random, 0);
+            let defence: PlayerDefence = contract.get_own_defence(accounts.alice);
+            let picked: Vec<u8> = (0..32).map(|seed| pick_preset(&defence, seed)).collect();
+            assert!(picked.iter().all(|preset| *preset < 3));
+            assert!((0..3).all(|preset| picked.contains(&preset)));
+            assert_eq!(picked, (0..32).map(|seed| pick_preset(&defence, seed)).collect::<Vec<u8>>());
+        }
+
+        #[ink::test]
+        fn test_fight_preset() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+            register(&mut contract, accounts.alice, 2);
+
+            let presets: Vec<DefencePreset> = contract.get_defence_presets(accounts.alice);
+            let mut fight: RankedFight = new_fight(accounts.bob, accounts.alice, 0);
+            fight.preset = 1;
+            fight.preset_revision = presets[1].revision;
+            assert_eq!(contract.get_fight_preset(fight.clone()), Some(presets[1].clone()));
+
+            // Out of range preset indices are rejected
+            let mut missing: RankedFight = fight.clone();
+            missing.preset = 2;
+            assert_eq!(contract.get_fight_preset(missing), None);
+
+            // Once the preset is replaced, the fight no longer points at it
+            contract.set_defence_preset(accounts.alice, 1, String::from("Replaced"), [5, 5, 5, 5],
+                [0, 0, 0, 0], 0, Reserve::default());
+            assert_eq!(contract.get_fight_preset(fight.clone()), None);
+
+            // Nor after registering again, or unregistering
+            fight.preset = 0;
+            fight.preset_revision = presets[0].revision;
+            assert_eq!(contract.get_fight_preset(fight.clone()), Some(presets[0].clone()));
+            register(&mut contract, accounts.alice, 1);
+            assert_eq!(contract.get_fight_preset(fight.clone()), None);
+            contract.unregister_defence(accounts.alice);
+            assert_eq!(contract.get_fight_preset(fight), None);
+        }
+
+        #[ink::test]
+        fn test_pause_defence() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+            register(&mut contract, accounts.alice, 1);
+            register(&mut contract, accounts.bob, 1);
+            contract.set_pause_duration(3);
+
+            // Paused defences are hidden from the defenders
+            contract.pause_defence(accounts.alice);
+            let defenders: Vec<(AccountId, PlayerDefence)> = contract.get_all_defenders();
+            assert_eq!(defenders.len(), 1);
+            assert_eq!(defenders[0].0, accounts.bob);
+
+            // Until resumed
+            contract.resume_defence(accounts.alice);
+            assert_eq!(contract.get_all_defenders().len(), 2);
+
+            // Or until the pause expires
+            contract.pause_defence(accounts.alice);
+            for _ in 0..2 {
+                test::advance_block::<Environment>().expect("Cannot advance block");
+            }
+            assert_eq!(contract.get_all_defenders().len(), 1);
+            test::advance_block::<Environment>().expect("Cannot advance block");
+            assert_eq!(contract.get_all_defenders().len(), 2);
+        }
+
+        #[ink::test]
+        fn test_unregister_defence() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+            register(&mut contract, accounts.alice, 2);
+            register(&mut contract, accounts.bob, 1);
+
+            contract.unregister_defence(accounts.alice);
+            let defenders: Vec<(AccountId, PlayerDefence)> = contract.get_all_defenders();
+            assert_eq!(defenders.len(), 1);
+            assert_eq!(defenders[0].0, accounts.bob);
+
+            // Players can register again afterwards
+            register(&mut contract, accounts.alice, 1);
+            assert_eq!(contract.get_all_defenders().len(), 2);
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_unregister_missing_defence() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+            register(&mut contract, accounts.alice, 1);
+
+            contract.unregister_defence(accounts.alice);
+            contract.unregister_defence(accounts.alice);
+        }
+
+        #[test]
+        fn test_fight_records() {
+            let attacker: AccountId = AccountId::from([1; 32]);
+            let defender: AccountId = AccountId::from([2; 32]);
+            let mut fights: Vec<RankedFight> = Vec::new();
+
+            for block in 0..(MAX_RECORDED_FIGHTS as BlockNumber + 5) {
+                record_fight(&mut fights, new_fight(attacker, defender, block));
+            }
+
+            // Only the most recent fights are kept, oldest first
+            assert_eq!(fights.len(), MAX_RECORDED_FIGHTS);
+            assert_eq!(fights[0].block, 5);
+            assert_eq!(fights[MAX_RECORDED_FIGHTS - 1].block, MAX_RECORDED_FIGHTS as BlockNumber + 4);
+        }
+
+        #[test]
+        fn test_match_opponents() {
+            let caller: AccountId = AccountId::from([0; 32]);
+            let candidates: Vec<(AccountId, i32)> = (1..=6)
+                .map(|i| (AccountId::from([i; 32]), (i as i32) * 2))
+                .chain([(caller, 5)].iter().cloned())
+                .collect();
+            let excluded: Vec<AccountId> = [AccountId::from([3; 32])].to_vec();
+
+            let opponents: Vec<AccountId> = match_opponents(caller, 5, candidates.clone(), &excluded, 10, 3);
+
+            // Closest ratings first, without the caller and the excluded players
+            assert_eq!(opponents.len(), 3);
+            assert!(!opponents.contains(&caller));
+            assert!(!opponents.contains(&AccountId::from([3; 32])));
+            assert_eq!(opponents[0], AccountId::from([2; 32]));
+            let mut tied: Vec<AccountId> = opponents[1..3].to_vec();
+            tied.sort();
+            assert_eq!(tied, [AccountId::from([1; 32]), AccountId::from([4; 32])].to_vec());
+
+            // Deterministic for a given block
+            assert_eq!(opponents, match_opponents(caller, 5, candidates.clone(), &excluded, 10, 3));
+
+            // Ties are broken differently between blocks
+            let orders: Vec<Vec<AccountId>> = (0..16)
+                .map(|block| match_opponents(caller, 5, candidates.clone(), &excluded, block, 2))
+                .collect();
+            assert!(orders.iter().any(|order| *order != orders[0]));
+
+            // Never more than available
+            assert_eq!(match_opponents(caller, 5, candidates, &excluded, 10, 10).len(), 5);
+        }
+    }
 }
@@ -3,46 +3,526 @@
 
 use ink_lang as ink;
 pub use self::newomegarewarder::NewOmegaRewarder;
+pub use self::newomegarewarder::CrateType;
+pub use self::newomegarewarder::LootDrop;
+pub use self::newomegarewarder::LootReward;
+pub use self::newomegarewarder::loot_payout;
 
 /// A simple module hands out rewards for a player in the form of loot crates.
 /// The crates are buyable, but this contract does not deal with payments, instead
 /// leaving that to the Delegator and focusing on the crate generation, and Storage updates.
+/// Crate types, with their prices and drop tables, are managed by the admin.
+/// A pity timer guarantees the top rarity of a crate after a number of crates without one,
+/// and duplicate commanders are converted into shards, which can be redeemed for a chosen commander.
 #[ink::contract]
 mod newomegarewarder {
     use newomegastorage::NewOmegaStorage;
+    use newomega::Commander;
     use ink_prelude::vec::Vec;
+    use ink_prelude::string::String;
+    use ink_env::hash::Blake2x256;
+    use ink_storage::{
+        collections::{
+            HashMap as StorageHashMap,
+        },
+        traits::{
+            PackedLayout,
+            SpreadLayout,
+        },
+    };
 
     const XP_PER_LOOT_CRATE: u32 = 10;
     const MAX_COMMANDERS: u8 = 4;
+    const DEFAULT_LOOT_CRATE_PRICE: Balance = 1;
+    /// Weights of the commanders in the default crate: 75% for the lowest commander, halving
+    /// the remaining probability for each next one, with the remainder going to the lowest commander
+    const DEFAULT_LOOT_CRATE_WEIGHTS: [u32; MAX_COMMANDERS as usize] = [79, 12, 6, 3];
+    /// Drop rates are published in basis points
+    const DROP_RATE_PRECISION: u32 = 10_000;
+    /// Crates of a type within which a commander of the top rarity of the crate is guaranteed
+    const DEFAULT_PITY_THRESHOLD: u32 = 40;
+    /// Commander shards given for a duplicate commander, per rarity (common, rare, epic, legendary)
+    const SHARDS_PER_DUPLICATE: [u32; 4] = [1, 3, 10, 30];
+    /// Crates which can be bought at once, bounding the work of a single purchase
+    const MAX_LOOT_CRATES_PER_PURCHASE: u32 = 10;
+
+    /// Describes a reward which can drop from a loot crate
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Copy, Clone,
+        Debug, Eq, PartialEq)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub enum LootReward {
+        /// Experience for a commander, players who do not own the commander receive it
+        Commander {
+            commander: u8,
+            xp: u32,
+        },
+        /// A new instance of a commander, as a separate commander token
+        CommanderToken {
+            commander: u8,
+        },
+        /// A payout in the native token, paid by the Delegator
+        Funds {
+            amount: Balance,
+        },
+        /// Commander shards, also given instead of a duplicate commander
+        Shards {
+            amount: u32,
+        },
+    }
+
+    /// Describes an entry of a drop table
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Copy, Clone,
+        Debug, Eq, PartialEq)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub struct LootDrop {
+        /// The reward
+        pub reward: LootReward,
+        /// Weight of the reward, relative to the other entries of the drop table
+        pub weight: u32,
+    }
+
+    /// Describes a type of loot crate
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            Debug,
+            PartialEq,
+            Eq,
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub struct CrateType {
+        /// Name, for display
+        pub name: String,
+        /// Price of a crate
+        pub price: Balance,
+        /// Weighted drop table, a single reward drops from each crate
+        pub drops: Vec<LootDrop>,
+        /// Whether the crate can be bought
+        pub enabled: bool,
+    }
+
+    /// Builds the default crate type, with the original drop rates of the loot crates.
+    ///
+    /// # Returns
+    ///
+    /// * `crate_type` - The default crate type
+    fn default_crate_type() -> CrateType {
+        CrateType {
+            name: String::from("Loot Crate"),
+            price: DEFAULT_LOOT_CRATE_PRICE,
+            drops: DEFAULT_LOOT_CRATE_WEIGHTS
+                .iter()
+                .enumerate()
+                .map(|(commander, &weight)| LootDrop {
+                    reward: LootReward::Commander {
+                        commander: commander as u8,
+                        xp: XP_PER_LOOT_CRATE,
+                    },
+                    weight,
+                })
+                .collect(),
+            enabled: true,
+        }
+    }
+
+    /// Picks the reward from a drop table, for a roll between 0 and the total weight of the table.
+    ///
+    /// # Arguments
+    ///
+    /// * `drops` - The drop table
+    /// * `roll` - Result of the roll
+    ///
+    /// # Returns
+    ///
+    /// * `reward` - The picked reward
+    pub fn pick_drop(drops: &Vec<LootDrop>, roll: u32) -> LootReward {
+        let mut window: u32 = 0;
+
+        for drop in drops.iter() {
+            window += drop.weight;
+            if roll < window {
+                return drop.reward;
+            }
+        }
+
+        drops[drops.len() - 1].reward
+    }
+
+    /// Performs the dice roll of a crate, among several opened at once. Each crate gets its own roll,
+    /// derived from the shared seed and the index of the crate.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - Seed shared by the crates
+    /// * `index` - Index of the crate
+    /// * `sides` - Number of sides on the rolled dice
+    ///
+    /// # Returns
+    ///
+    /// * `roll` - Result of the roll
+    pub fn loot_roll(seed: u64, index: u32, sides: u32) -> u32 {
+        let mut output: [u8; 32] = [0; 32];
+        ink_env::hash_encoded::<Blake2x256, _>(&(seed, index), &mut output);
+        let mut roll: [u8; 8] = [0; 8];
+        roll.copy_from_slice(&output[0..8]);
+
+        (u64::from_le_bytes(roll) % sides as u64) as u32
+    }
+
+    /// Calculates the drop rates of a drop table.
+    ///
+    /// # Arguments
+    ///
+    /// * `drops` - The drop table
+    ///
+    /// # Returns
+    ///
+    /// * `rates` - A Vec containing a tuple of (reward, drop rate in basis points)
+    pub fn drop_rates(drops: &Vec<LootDrop>) -> Vec<(LootReward, u32)> {
+        let total_weight: u32 = drops.iter().map(|drop| drop.weight).sum();
+
+        drops
+            .iter()
+            .map(|drop| (drop.reward, (drop.weight as u64 * DROP_RATE_PRECISION as u64
+                / total_weight as u64) as u32))
+            .collect()
+    }
+
+    /// Gets the rarity of the commander handed out by a reward.
+    ///
+    /// # Arguments
+    ///
+    /// * `reward` - The reward
+    /// * `commanders` - The commander definitions
+    ///
+    /// # Returns
+    ///
+    /// * `rarity` - Rarity of the commander, None for rewards which are not commanders
+    pub fn reward_rarity(reward: &LootReward, commanders: &Vec<Commander>) -> Option<u8> {
+        match *reward {
+            LootReward::Commander { commander, .. } | LootReward::CommanderToken { commander } => {
+                commanders.get(commander as usize).map(|definition| definition.rarity)
+            },
+            _ => None,
+        }
+    }
+
+    /// Gets the top rarity of the commanders which can drop from a drop table.
+    ///
+    /// # Arguments
+    ///
+    /// * `drops` - The drop table
+    /// * `commanders` - The commander definitions
+    ///
+    /// # Returns
+    ///
+    /// * `rarity` - The top rarity, None if no commanders can drop
+    pub fn top_rarity(drops: &Vec<LootDrop>, commanders: &Vec<Commander>) -> Option<u8> {
+        drops
+            .iter()
+            .filter(|drop| drop.weight > 0)
+            .filter_map(|drop| reward_rarity(&drop.reward, commanders))
+            .max()
+    }
+
+    /// Narrows a drop table down to the commanders of its top rarity, for a crate opened
+    /// once the pity timer has run out.
+    ///
+    /// # Arguments
+    ///
+    /// * `drops` - The drop table
+    /// * `commanders` - The commander definitions
+    ///
+    /// # Returns
+    ///
+    /// * `drops` - The drop table of the top rarity
+    pub fn pity_drops(drops: &Vec<LootDrop>, commanders: &Vec<Commander>) -> Vec<LootDrop> {
+        let rarity: Option<u8> = top_rarity(drops, commanders);
+
+        drops
+            .iter()
+            .filter(|drop| drop.weight > 0 && reward_rarity(&drop.reward, commanders) == rarity)
+            .cloned()
+            .collect()
+    }
+
+    /// Gets the drop table of the next crate a player opens, narrowed down to the commanders of
+    /// its top rarity if the player opened one less than the pity threshold of crates without one.
+    ///
+    /// # Arguments
+    ///
+    /// * `drops` - The drop table of the crate type
+    /// * `commanders` - The commander definitions
+    /// * `pity` - Crates of the type opened since the last commander of the top rarity
+    /// * `threshold` - The pity threshold
+    ///
+    /// # Returns
+    ///
+    /// * `drops` - The drop table of the crate
+    pub fn loot_drops(drops: &Vec<LootDrop>, commanders: &Vec<Commander>, pity: u32, threshold: u32) -> Vec<LootDrop> {
+        if top_rarity(drops, commanders).is_some() && pity.saturating_add(1) >= threshold {
+            pity_drops(drops, commanders)
+        } else {
+            drops.clone()
+        }
+    }
+
+    /// Calculates the amount paid out to a player buying loot crates: the excess of the payment
+    /// over the price of the crates, and the funds rewards of the crates.
+    /// Will panic if the number of crates is out of bounds, or the payment does not cover the price.
+    ///
+    /// # Arguments
+    ///
+    /// * `price` - Price of a crate
+    /// * `count` - Number of crates
+    /// * `payment` - The transferred amount
+    /// * `rewards` - The rewards received from the loot crates
+    ///
+    /// # Returns
+    ///
+    /// * `payout` - The amount to pay out
+    pub fn loot_payout(price: Balance, count: u32, payment: Balance, rewards: &Vec<LootReward>) -> Balance {
+        assert!(count > 0 && count <= MAX_LOOT_CRATES_PER_PURCHASE);
+        let total_price: Balance = price.checked_mul(count as Balance).expect("Loot crate price overflow");
+        assert!(payment >= total_price);
+
+        rewards
+            .iter()
+            .fold(payment - total_price, |payout, reward| match *reward {
+                LootReward::Funds { amount } => payout.checked_add(amount).expect("Loot payout overflow"),
+                _ => payout,
+            })
+    }
 
     #[ink(storage)]
     pub struct NewOmegaRewarder {
         owner: AccountId,
         new_omega_storage: NewOmegaStorage,
+        crate_types: StorageHashMap<u32, CrateType>,
+        next_crate_type: u32,
+        pity_threshold: u32,
     }
 
     impl NewOmegaRewarder {
         #[ink(constructor)]
         pub fn new(new_omega_storage: NewOmegaStorage) -> Self {
+            let mut crate_types: StorageHashMap<u32, CrateType> = StorageHashMap::default();
+            crate_types.insert(0, default_crate_type());
+
             Self {
                 owner: Self::env().caller(),
                 new_omega_storage,
+                crate_types,
+                next_crate_type: 1,
+                pity_threshold: DEFAULT_PITY_THRESHOLD,
             }
         }
 
-        /// Performs a dice roll.
+        /// Sets the number of crates of a type within which a commander of the top rarity
+        /// of the crate is guaranteed.
+        ///
+        /// # Arguments
+        ///
+        /// * `threshold` - Number of crates
+        #[ink(message)]
+        pub fn set_pity_threshold(&mut self, threshold: u32) {
+            assert_eq!(self.env().caller(), self.owner);
+            assert!(threshold > 0);
+            self.pity_threshold = threshold;
+        }
+
+        /// Gets the number of crates of a type within which a commander of the top rarity
+        /// of the crate is guaranteed.
+        ///
+        /// # Returns
+        ///
+        /// * `threshold` - Number of crates
+        #[ink(message)]
+        pub fn get_pity_threshold(&self) -> u32 {
+            self.pity_threshold
+        }
+
+        /// Gets the seed for the dice rolls of a purchase.
         /// Currently implemented in a naive way. -> IMPROVEME: MOVE TO VRF
         ///
+        /// # Returns
+        ///
+        /// * `seed` - Seed used to generate randomness
+        fn random_seed(&self) -> u64 {
+            self.env().block_timestamp()
+        }
+
+        /// Validates a crate type: it needs a non-empty drop table, with a positive total weight
+        /// and only existing commanders.
+        ///
+        /// # Arguments
+        ///
+        /// * `crate_type` - The crate type
+        fn validate_crate_type(crate_type: &CrateType) {
+            let total_weight: u64 = crate_type.drops.iter().map(|drop| drop.weight as u64).sum();
+            assert!(total_weight > 0 && total_weight <= u32::MAX as u64);
+
+            for drop in crate_type.drops.iter() {
+                match drop.reward {
+                    LootReward::Commander { commander, .. } | LootReward::CommanderToken { commander } => {
+                        assert!(commander < MAX_COMMANDERS);
+                    },
+                    _ => (),
+                }
+            }
+        }
+
+        /// Adds a new crate type.
+        ///
         /// # Arguments
         ///
-        /// * `sides` - Number of sides on the rolled dice
+        /// * `crate_type` - The crate type
         ///
         /// # Returns
         ///
-        /// * `roll` - Result of the roll
-        fn dice_roll(&self, sides: u8) -> u8 {
-            let seed: u64 = self.env().block_timestamp();
-            (seed % sides as u64) as u8
+        /// * `id` - Id of the added crate type
+        #[ink(message)]
+        pub fn add_crate_type(&mut self, crate_type: CrateType) -> u32 {
+            assert_eq!(self.env().caller(), self.owner);
+            Self::validate_crate_type(&crate_type);
+
+            let id: u32 = self.next_crate_type;
+            self.crate_types.insert(id, crate_type);
+            self.next_crate_type = id + 1;
+
+            id
+        }
+
+        /// Replaces a crate type, e.g. to change its price or drop table, or disable it.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the crate type
+        /// * `crate_type` - The crate type
+        #[ink(message)]
+        pub fn update_crate_type(&mut self, id: u32, crate_type: CrateType) {
+            assert_eq!(self.env().caller(), self.owner);
+            assert!(self.crate_types.get(&id).is_some());
+            Self::validate_crate_type(&crate_type);
+
+            self.crate_types.insert(id, crate_type);
+        }
+
+        /// Gets a crate type.
+        /// Will panic if the crate type does not exist.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the crate type
+        ///
+        /// # Returns
+        ///
+        /// * `crate_type` - The crate type
+        #[ink(message)]
+        pub fn get_crate_type(&self, id: u32) -> CrateType {
+            assert!(self.crate_types.get(&id).is_some());
+            self.crate_types.get(&id).unwrap().clone()
+        }
+
+        /// Gets all the crate types, including the disabled ones.
+        ///
+        /// # Returns
+        ///
+        /// * `crate_types` - A Vec containing a tuple of (crate type id, crate type)
+        #[ink(message)]
+        pub fn get_crate_types(&self) -> Vec<(u32, CrateType)> {
+            self.crate_types
+                .iter()
+                .map(|(&id, crate_type)| (id, crate_type.clone()))
+                .collect()
+        }
+
+        /// Gets the drop rates of a crate type.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the crate type
+        ///
+        /// # Returns
+        ///
+        /// * `rates` - A Vec containing a tuple of (reward, drop rate in basis points)
+        #[ink(message)]
+        pub fn get_drop_rates(&self, id: u32) -> Vec<(LootReward, u32)> {
+            drop_rates(&self.get_crate_type(id).drops)
+        }
+
+        /// Opens a loot crate for the player. Once the player opened one less than the pity threshold
+        /// of crates of the type without a commander of its top rarity, the reward is picked among those only.
+        /// A commander the player already owns is converted into commander shards.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - Account id of the player to open the crate for
+        /// * `id` - Id of the crate type
+        /// * `crate_type` - The crate type
+        /// * `commanders` - The commander definitions
+        /// * `seed` - Seed shared by the crates of the purchase
+        /// * `index` - Index of the crate in the purchase
+        ///
+        /// # Returns
+        ///
+        /// * `reward` - The reward received from the loot crate
+        fn open_loot_crate(&mut self, caller: AccountId, id: u32, crate_type: &CrateType,
+            commanders: &Vec<Commander>, seed: u64, index: u32) -> LootReward {
+
+            let rarity: Option<u8> = top_rarity(&crate_type.drops, commanders);
+            let pity: u32 = self.new_omega_storage.get_loot_pity(caller, id);
+            let drops: Vec<LootDrop> = loot_drops(&crate_type.drops, commanders, pity, self.pity_threshold);
+
+            let total_weight: u32 = drops.iter().map(|drop| drop.weight).sum();
+            let mut reward: LootReward = pick_drop(&drops, loot_roll(seed, index, total_weight));
+
+            /// Advance the pity timer, or reset it on a commander of the top rarity
+            if rarity.is_some() {
+                if reward_rarity(&reward, commanders) == rarity {
+                    self.new_omega_storage.reset_loot_pity(caller, id);
+                } else {
+                    self.new_omega_storage.advance_loot_pity(caller, id);
+                }
+            }
+
+            /// Hand out the commander rewards, funds are paid out by the Delegator
+            match reward {
+                LootReward::Commander { commander, xp } => {
+                    if self.new_omega_storage.has_commander(caller, commander) {
+                        let amount: u32 = SHARDS_PER_DUPLICATE[commanders[commander as usize].rarity as usize];
+                        self.new_omega_storage.add_commander_shards(caller, amount);
+                        reward = LootReward::Shards { amount };
+                    } else {
+                        self.new_omega_storage.mint_commander(caller, commander);
+                        self.new_omega_storage.add_commander_xp(caller, commander, xp);
+                    }
+                },
+                LootReward::CommanderToken { commander } => {
+                    self.new_omega_storage.mint_commander(caller, commander);
+                },
+                LootReward::Shards { amount } => {
+                    self.new_omega_storage.add_commander_shards(caller, amount);
+                },
+                _ => (),
+            }
+
+            reward
         }
 
         /// Generates a loot crate for the player.
@@ -50,34 +530,192 @@ mod newomegarewarder {
         /// # Arguments
         ///
         /// * `caller` - Account id of the player to generate the crate for
+        /// * `id` - Id of the crate type
         ///
         /// # Returns
         ///
-        /// * `commander` - Id of the commander received from the loot crate
+        /// * `reward` - The reward received from the loot crate
         #[ink(message)]
-        pub fn buy_loot_crate(&mut self, caller: AccountId) -> u8 {
+        pub fn buy_loot_crate(&mut self, caller: AccountId, id: u32) -> LootReward {
+            self.buy_loot_crates(caller, id, 1)[0]
+        }
+
+        /// Generates several loot crates of the same type for the player at once.
+        /// Each crate gets its own roll, derived from a single seed.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - Account id of the player to generate the crates for
+        /// * `id` - Id of the crate type
+        /// * `count` - Number of crates
+        ///
+        /// # Returns
+        ///
+        /// * `rewards` - The rewards received from the loot crates, in order
+        #[ink(message)]
+        pub fn buy_loot_crates(&mut self, caller: AccountId, id: u32, count: u32) -> Vec<LootReward> {
             assert_eq!(self.env().caller(), self.owner);
+            assert!(count > 0 && count <= MAX_LOOT_CRATES_PER_PURCHASE);
 
-            let mut picked_commander: u8 = 0;
-            let max_roll: u8 = 100;
-            let roll: u8 = self.dice_roll(max_roll);
-            /// Probability to get the lowest commander
-            let mut prob: u8 = 75;
+            let crate_type: CrateType = self.get_crate_type(id);
+            assert!(crate_type.enabled);
 
-            for i in 0..MAX_COMMANDERS {
-                if roll < prob {
-                    picked_commander = i as u8;
-                    break;
-                }
+            let commanders: Vec<Commander> = newomega::prepare_commanders();
+            let seed: u64 = self.random_seed();
+            let mut rewards: Vec<LootReward> = Vec::new();
+
+            for index in 0..count {
+                rewards.push(self.open_loot_crate(caller, id, &crate_type, &commanders, seed, index));
+            }
+
+            rewards
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use ink_lang as ink;
 
-                /// Define new probability window for next commander
-                prob += (max_roll - prob) / 2;
+        fn commander_drop(commander: u8, weight: u32) -> LootDrop {
+            LootDrop {
+                reward: LootReward::Commander {
+                    commander,
+                    xp: XP_PER_LOOT_CRATE,
+                },
+                weight,
             }
+        }
+
+        #[ink::test]
+        fn test_default_crate_type() {
+            let crate_type: CrateType = default_crate_type();
+
+            // Matches the original probability ladder over a 100-sided dice
+            let commanders: Vec<u8> = (0..100)
+                .map(|roll| match pick_drop(&crate_type.drops, roll) {
+                    LootReward::Commander { commander, .. } => commander,
+                    _ => MAX_COMMANDERS,
+                })
+                .collect();
+            for (commander, &weight) in DEFAULT_LOOT_CRATE_WEIGHTS.iter().enumerate() {
+                assert_eq!(commanders.iter().filter(|&&picked| picked as usize == commander).count(),
+                    weight as usize);
+            }
+            assert_eq!(crate_type.price, DEFAULT_LOOT_CRATE_PRICE);
+        }
+
+        #[ink::test]
+        fn test_drop_rates() {
+            let drops: Vec<LootDrop> = [commander_drop(0, 1), commander_drop(1, 2), LootDrop {
+                reward: LootReward::Funds { amount: 5 },
+                weight: 1,
+            }].to_vec();
+
+            assert_eq!(pick_drop(&drops, 0), drops[0].reward);
+            assert_eq!(pick_drop(&drops, 2), drops[1].reward);
+            assert_eq!(pick_drop(&drops, 3), drops[2].reward);
+            assert_eq!(drop_rates(&drops).iter().map(|&(_, rate)| rate).collect::<Vec<u32>>(),
+                [2_500, 5_000, 2_500].to_vec());
+        }
+
+        #[ink::test]
+        fn test_pity_drops() {
+            let commanders: Vec<Commander> = newomega::prepare_commanders();
+            let crate_type: CrateType = default_crate_type();
+
+            assert_eq!(top_rarity(&crate_type.drops, &commanders), Some(3));
+            assert_eq!(pity_drops(&crate_type.drops, &commanders), [crate_type.drops[3]].to_vec());
 
-            /// Adjust the commander xp
-            self.new_omega_storage.add_commander_xp(caller, picked_commander, XP_PER_LOOT_CRATE);
+            // Without the legendary commander, the pity timer guarantees the epic one
+            let drops: Vec<LootDrop> = [crate_type.drops[0], crate_type.drops[2], LootDrop {
+                reward: LootReward::Shards { amount: 5 },
+                weight: 10,
+            }].to_vec();
+            assert_eq!(pity_drops(&drops, &commanders), [crate_type.drops[2]].to_vec());
+            assert_eq!(reward_rarity(&drops[2].reward, &commanders), None);
+        }
+
+        #[ink::test]
+        fn test_pity_timer() {
+            let commanders: Vec<Commander> = newomega::prepare_commanders();
+            let crate_type: CrateType = default_crate_type();
+            let threshold: u32 = 5;
+            let mut pity: u32 = 0;
+
+            // The lowest roll never drops the legendary commander on its own
+            for _ in 0..threshold - 1 {
+                let reward: LootReward = pick_drop(&loot_drops(&crate_type.drops, &commanders, pity, threshold), 0);
+                assert_eq!(reward_rarity(&reward, &commanders), Some(0));
+                pity += 1;
+            }
+
+            // Until the threshold-th crate, which guarantees it
+            let reward: LootReward = pick_drop(&loot_drops(&crate_type.drops, &commanders, pity, threshold), 0);
+            assert_eq!(reward, crate_type.drops[3].reward);
+
+            // Drop tables without commanders are never narrowed down
+            let drops: Vec<LootDrop> = [LootDrop {
+                reward: LootReward::Shards { amount: 5 },
+                weight: 1,
+            }].to_vec();
+            assert_eq!(loot_drops(&drops, &commanders, threshold, threshold), drops);
+        }
+
+        #[ink::test]
+        fn test_loot_rolls() {
+            let rolls: Vec<u32> = (0..MAX_LOOT_CRATES_PER_PURCHASE)
+                .map(|index| loot_roll(42, index, 100))
+                .collect();
+
+            // Deterministic for a seed, but independent between the crates
+            assert_eq!(rolls, (0..MAX_LOOT_CRATES_PER_PURCHASE)
+                .map(|index| loot_roll(42, index, 100))
+                .collect::<Vec<u32>>());
+            assert!(rolls.iter().all(|&roll| roll < 100));
+            assert!(rolls.iter().any(|&roll| roll != rolls[0]));
+            assert!(loot_roll(43, 0, u32::MAX) != loot_roll(42, 0, u32::MAX));
+        }
+
+        #[ink::test]
+        fn test_loot_payout() {
+            let rewards: Vec<LootReward> = [LootReward::Funds { amount: 5 }, LootReward::Shards { amount: 3 },
+                LootReward::Funds { amount: 7 }].to_vec();
+
+            // The excess is refunded along with the funds rewards
+            assert_eq!(loot_payout(10, 3, 30, &rewards), 12);
+            assert_eq!(loot_payout(10, 3, 34, &rewards), 16);
+            assert_eq!(loot_payout(10, 1, 10, &[LootReward::Shards { amount: 3 }].to_vec()), 0);
+            assert_eq!(loot_payout(10, MAX_LOOT_CRATES_PER_PURCHASE, 100, &Vec::new()), 0);
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_loot_payout_underpaid() {
+            loot_payout(10, 3, 29, &Vec::new());
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_loot_payout_no_crates() {
+            loot_payout(10, 0, 10, &Vec::new());
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_loot_payout_too_many_crates() {
+            loot_payout(10, MAX_LOOT_CRATES_PER_PURCHASE + 1, 1_000, &Vec::new());
+        }
 
-            picked_commander
+        #[ink::test]
+        #[should_panic]
+        fn test_invalid_crate_type() {
+            NewOmegaRewarder::validate_crate_type(&CrateType {
+                name: String::from("Invalid"),
+                price: 1,
+                drops: [commander_drop(MAX_COMMANDERS, 1)].to_vec(),
+                enabled: true,
+            });
         }
     }
 }
@@ -3,7 +3,15 @@
 use ink_lang as ink;
 pub use self::newomegastorage::NewOmegaStorage;
 pub use self::newomegastorage::CommanderData;
+pub use self::newomegastorage::CommanderToken;
 pub use self::newomegastorage::PlayerData;
+pub use self::newomegastorage::SeasonRecord;
+pub use self::newomegastorage::League;
+pub use self::newomegastorage::evaluate_league;
+pub use self::newomegastorage::season_payouts;
+pub use self::newomegastorage::Guild;
+pub use self::newomegastorage::GuildRole;
+pub use self::newomegastorage::GuildStanding;
 
 /// Isolated storage for all things which should be considered player progress.
 /// This module should only ever change if a serious API change is needed, but otherwise
@@ -12,7 +20,9 @@ pub use self::newomegastorage::PlayerData;
 /// The only logic that belongs here is accessors for the storage.
 #[ink::contract]
 mod newomegastorage {
+    use newomega::Commander;
     use ink_prelude::vec::Vec;
+    use ink_prelude::string::String;
     use ink_storage::{
         collections::{
             Vec as StorageVec,
@@ -24,7 +34,8 @@ mod newomegastorage {
         },
     };
 
-    /// Holds the current progress of a commander
+    /// Holds the current progress of a commander. The level is not stored, but derived from the experience
+    /// points (see `get_commander_level`), so it follows changes of the level curve.
     #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Clone, Default,
         Copy, Debug, Eq, PartialEq)]
     #[cfg_attr(
@@ -37,6 +48,127 @@ mod newomegastorage {
     pub struct CommanderData {
         /// Experience points
         xp: u32,
+        /// Selected skills (bitmask of skill ids)
+        skills: u32,
+    }
+
+    /// A commander token. Every commander instance is a unique, transferable token
+    /// (following the PSP34 non-fungible token interface), with its own progress.
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Clone,
+        Copy, Debug, Eq, PartialEq)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub struct CommanderToken {
+        /// Id of the commander (definition) the token is an instance of
+        pub commander: u8,
+        /// Owner of the token
+        pub owner: AccountId,
+        /// Progress of the commander
+        pub data: CommanderData,
+    }
+
+    const POINTS_PER_WIN: u32 = 30;
+    const POINTS_PER_LOSS: u32 = 20;
+    const POINTS_PER_RETREAT: u32 = 10;
+    /// Points required to be promoted to each league, from Bronze to Legend
+    const LEAGUE_THRESHOLDS: [u32; 6] = [0, 100, 250, 500, 1000, 2000];
+    /// Points a player can fall below the league threshold before being relegated
+    const RELEGATION_MARGIN: u32 = 25;
+    /// Maximum number of members of a guild, and of its pending invites and applications
+    const MAX_GUILD_MEMBERS: usize = 50;
+    /// Maximum number of guilds a player can have pending invites from or applications to
+    const MAX_GUILD_REQUESTS: usize = 10;
+    /// Commander shards needed to redeem a commander, per rarity (common, rare, epic, legendary)
+    const SHARDS_PER_COMMANDER: [u32; 4] = [10, 30, 100, 300];
+
+    /// Describes a ranked league, derived from the points of a player
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Clone,
+        Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub enum League {
+        Bronze,
+        Silver,
+        Gold,
+        Platinum,
+        Diamond,
+        Legend,
+    }
+
+    impl Default for League {
+        fn default() -> Self {
+            League::Bronze
+        }
+    }
+
+    impl League {
+        /// Gets the league with a given index, from Bronze (0) to Legend (5)
+        ///
+        /// # Arguments
+        ///
+        /// * `index` - Index of the league, capped at Legend
+        ///
+        /// # Returns
+        ///
+        /// * `league` - The league
+        pub fn from_index(index: u8) -> League {
+            match index {
+                0 => League::Bronze,
+                1 => League::Silver,
+                2 => League::Gold,
+                3 => League::Platinum,
+                4 => League::Diamond,
+                _ => League::Legend,
+            }
+        }
+
+        /// Gets the number of leagues between two leagues
+        ///
+        /// # Arguments
+        ///
+        /// * `other` - The other league
+        ///
+        /// # Returns
+        ///
+        /// * `distance` - Number of leagues between the two
+        pub fn distance(&self, other: League) -> u8 {
+            ((*self as i8) - (other as i8)).abs() as u8
+        }
+    }
+
+    /// Evaluates promotion and relegation of a player.
+    /// Players are promoted as soon as they reach the threshold of a higher league,
+    /// and relegated only once they fall below their league threshold by the relegation margin.
+    ///
+    /// # Arguments
+    ///
+    /// * `current` - Current league of the player
+    /// * `points` - Current points of the player
+    ///
+    /// # Returns
+    ///
+    /// * `league` - The new league of the player
+    pub fn evaluate_league(current: League, points: u32) -> League {
+        let mut index: usize = current as usize;
+
+        while index + 1 < LEAGUE_THRESHOLDS.len() && points >= LEAGUE_THRESHOLDS[index + 1] {
+            index += 1;
+        }
+        while index > 0 && points + RELEGATION_MARGIN < LEAGUE_THRESHOLDS[index] {
+            index -= 1;
+        }
+
+        League::from_index(index as u8)
     }
 
     /// Holds the current leaderboard standing of a player
@@ -54,13 +186,260 @@ mod newomegastorage {
         ranked_wins: u32,
         /// Number of losses
         ranked_losses: u32,
+        /// Number of retreats (losses without being wiped out)
+        ranked_retreats: u32,
+        /// Points score: wins add points, losses and retreats (less) take them away
+        points: u32,
+        /// Current league
+        league: League,
+    }
+
+    impl PlayerData {
+        /// Gets the matchmaking rating of a player, which is the points score
+        ///
+        /// # Returns
+        ///
+        /// * `rating` - The rating
+        pub fn get_rating(&self) -> i32 {
+            self.points as i32
+        }
+
+        /// Gets the current league of a player
+        ///
+        /// # Returns
+        ///
+        /// * `league` - The league
+        pub fn get_league(&self) -> League {
+            self.league
+        }
+    }
+
+    /// Holds the archived final standings of a closed season
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Clone, Default,
+        Debug, Eq, PartialEq)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub struct SeasonRecord {
+        /// Season number
+        pub season: u32,
+        /// Block the season started in
+        pub started: BlockNumber,
+        /// Block the season was closed in
+        pub ended: BlockNumber,
+        /// Final leaderboard, best rating first
+        pub leaderboard: Vec<(AccountId, PlayerData)>,
+    }
+
+    /// Splits the season rewards among the final leaderboard of a season, by position.
+    /// Rewards which exceed the remaining funds are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The archived season record
+    /// * `rewards` - Rewards, indexed by final leaderboard position
+    /// * `funds` - Funds available for the rewards
+    ///
+    /// # Returns
+    ///
+    /// * `payouts` - A Vec containing a tuple of (player account id, reward)
+    pub fn season_payouts(record: &SeasonRecord, rewards: &Vec<Balance>, funds: Balance) -> Vec<(AccountId, Balance)> {
+        let mut remaining: Balance = funds;
+
+        record.leaderboard
+            .iter()
+            .zip(rewards.iter())
+            .filter_map(|((player, _), &reward)| {
+                if reward > remaining {
+                    return None;
+                }
+                remaining -= reward;
+                Some((*player, reward))
+            })
+            .collect()
+    }
+
+    /// Describes the role of a guild member, in increasing order of privileges
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Clone,
+        Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub enum GuildRole {
+        /// Regular member
+        Member,
+        /// Can invite, approve and kick members
+        Officer,
+        /// Can additionally appoint officers and kick them, one per guild
+        Leader,
+    }
+
+    /// Holds a guild, with its members and pending invites and applications
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Clone, Default,
+        Debug, Eq, PartialEq)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub struct Guild {
+        /// Name of the guild, unique
+        pub name: String,
+        /// Members, with their roles
+        pub members: Vec<(AccountId, GuildRole)>,
+        /// Players invited to join
+        pub invites: Vec<AccountId>,
+        /// Players who applied to join, waiting for approval
+        pub applications: Vec<AccountId>,
+    }
+
+    impl Guild {
+        /// Gets the role of a player in the guild
+        ///
+        /// # Arguments
+        ///
+        /// * `player` - The account id of the player
+        ///
+        /// # Returns
+        ///
+        /// * `role` - The role of the player, None if not a member
+        pub fn get_role(&self, player: AccountId) -> Option<GuildRole> {
+            self.members
+                .iter()
+                .find(|(member, _)| *member == player)
+                .map(|&(_, role)| role)
+        }
+    }
+
+    /// Holds the aggregate ranked results of a guild
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Clone, Default,
+        Debug, Eq, PartialEq)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub struct GuildStanding {
+        /// Id of the guild
+        pub guild: u32,
+        /// Name of the guild
+        pub name: String,
+        /// Number of members
+        pub members: u8,
+        /// Sum of the points of the members
+        pub points: u32,
+        /// Sum of the ranked wins of the members
+        pub ranked_wins: u32,
+        /// Sum of the ranked losses (retreats included) of the members
+        pub ranked_losses: u32,
+    }
+
+    #[ink(event)]
+    pub struct LeagueChanged {
+        #[ink(topic)]
+        player: AccountId,
+        from: League,
+        to: League,
+    }
+
+    #[ink(event)]
+    pub struct Transfer {
+        #[ink(topic)]
+        from: Option<AccountId>,
+        #[ink(topic)]
+        to: Option<AccountId>,
+        #[ink(topic)]
+        id: u32,
+    }
+
+    #[ink(event)]
+    pub struct Approval {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        spender: Option<AccountId>,
+        #[ink(topic)]
+        id: u32,
+    }
+
+    #[ink(event)]
+    pub struct GuildCreated {
+        #[ink(topic)]
+        guild: u32,
+        #[ink(topic)]
+        leader: AccountId,
+        name: String,
+    }
+
+    #[ink(event)]
+    pub struct GuildMemberJoined {
+        #[ink(topic)]
+        guild: u32,
+        #[ink(topic)]
+        player: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct GuildMemberLeft {
+        #[ink(topic)]
+        guild: u32,
+        #[ink(topic)]
+        player: AccountId,
+        kicked: bool,
+    }
+
+    #[ink(event)]
+    pub struct GuildRoleChanged {
+        #[ink(topic)]
+        guild: u32,
+        #[ink(topic)]
+        player: AccountId,
+        role: GuildRole,
+    }
+
+    #[ink(event)]
+    pub struct GuildDisbanded {
+        #[ink(topic)]
+        guild: u32,
     }
 
     #[ink(storage)]
     pub struct NewOmegaStorage {
         owners: StorageVec<AccountId>,
         players: StorageHashMap<AccountId, PlayerData>,
-        commanders: StorageHashMap<(AccountId, u8), CommanderData>,
+        commander_tokens: StorageHashMap<u32, CommanderToken>,
+        /// Token each player fights with, per commander id
+        equipped_commanders: StorageHashMap<(AccountId, u8), u32>,
+        commander_balances: StorageHashMap<AccountId, u32>,
+        /// Tokens owned by each account
+        owned_commanders: StorageHashMap<AccountId, Vec<u32>>,
+        commander_approvals: StorageHashMap<u32, AccountId>,
+        next_commander_token: u32,
+        /// Commander shards of each player, from duplicate loot
+        commander_shards: StorageHashMap<AccountId, u32>,
+        /// Loot crates of each type each player opened since the last one of the top rarity
+        loot_pity: StorageHashMap<(AccountId, u32), u32>,
+        season: u32,
+        season_started: BlockNumber,
+        season_length: BlockNumber,
+        seasons: StorageVec<SeasonRecord>,
+        guilds: StorageHashMap<u32, Guild>,
+        guild_members: StorageHashMap<AccountId, u32>,
+        /// Guilds each player has a pending invite from or application to
+        guild_requests: StorageHashMap<AccountId, Vec<u32>>,
+        next_guild_id: u32,
     }
 
     impl NewOmegaStorage {
@@ -72,7 +451,22 @@ mod newomegastorage {
             Self {
                 owners,
                 players: StorageHashMap::default(),
-                commanders: StorageHashMap::default(),
+                commander_tokens: StorageHashMap::default(),
+                equipped_commanders: StorageHashMap::default(),
+                commander_balances: StorageHashMap::default(),
+                owned_commanders: StorageHashMap::default(),
+                commander_approvals: StorageHashMap::default(),
+                next_commander_token: 0,
+                commander_shards: StorageHashMap::default(),
+                loot_pity: StorageHashMap::default(),
+                season: 0,
+                season_started: Self::env().block_number(),
+                season_length: 0,
+                seasons: StorageVec::default(),
+                guilds: StorageHashMap::default(),
+                guild_members: StorageHashMap::default(),
+                guild_requests: StorageHashMap::default(),
+                next_guild_id: 0,
             }
         }
 
@@ -113,6 +507,34 @@ mod newomegastorage {
                 .or_insert(PlayerData::default())
         }
 
+        /// Adjusts the points of a player, and evaluates promotion or relegation.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the player
+        /// * `gained` - Points gained
+        /// * `lost` - Points lost, the score doesn't go below 0
+        ///
+        /// # Events
+        ///
+        /// * LeagueChanged - when the player is promoted or relegated
+        fn adjust_points(&mut self, caller: AccountId, gained: u32, lost: u32) {
+            let player_data = self.ensure_player(caller);
+            let from: League = player_data.league;
+
+            player_data.points = (player_data.points + gained).saturating_sub(lost);
+            player_data.league = evaluate_league(from, player_data.points);
+
+            let to: League = player_data.league;
+            if from != to {
+                self.env().emit_event(LeagueChanged {
+                    player: caller,
+                    from,
+                    to,
+                });
+            }
+        }
+
         /// Marks a ranked win for a player
         ///
         /// # Arguments
@@ -123,6 +545,7 @@ mod newomegastorage {
             assert!(self.owners.iter().any(|owner| *owner == self.env().caller()));
             let player_data = self.ensure_player(caller);
             player_data.ranked_wins = player_data.ranked_wins + 1;
+            self.adjust_points(caller, POINTS_PER_WIN, 0);
         }
 
         /// Marks a ranked loss for a player
@@ -135,9 +558,127 @@ mod newomegastorage {
             assert!(self.owners.iter().any(|owner| *owner == self.env().caller()));
             let player_data = self.ensure_player(caller);
             player_data.ranked_losses = player_data.ranked_losses + 1;
+            self.adjust_points(caller, 0, POINTS_PER_LOSS);
+        }
+
+        /// Marks a ranked retreat for a player
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the player to mark
+        #[ink(message)]
+        pub fn mark_ranked_retreat(&mut self, caller: AccountId) {
+            assert!(self.owners.iter().any(|owner| *owner == self.env().caller()));
+            let player_data = self.ensure_player(caller);
+            player_data.ranked_retreats = player_data.ranked_retreats + 1;
+            self.adjust_points(caller, 0, POINTS_PER_RETREAT);
+        }
+
+        /// Mints a new commander token.
+        /// The token is equipped, unless the owner already fights with another instance of the commander.
+        ///
+        /// # Arguments
+        ///
+        /// * `owner` - The account id of the owner of the new token
+        /// * `commander_id` - The id of the commander the token is an instance of
+        ///
+        /// # Returns
+        ///
+        /// * `token` - Id of the minted token
+        ///
+        /// # Events
+        ///
+        /// * Transfer - from None, to the owner
+        fn mint_commander_token(&mut self, owner: AccountId, commander_id: u8) -> u32 {
+            let token: u32 = self.next_commander_token;
+
+            self.commander_tokens.insert(token, CommanderToken {
+                commander: commander_id,
+                owner,
+                data: CommanderData {
+                    xp: 0,
+                    skills: 0,
+                },
+            });
+            *self.commander_balances.entry(owner).or_insert(0) += 1;
+            self.owned_commanders.entry(owner).or_insert(Vec::new()).push(token);
+            self.equipped_commanders.entry((owner, commander_id)).or_insert(token);
+            self.next_commander_token = token + 1;
+
+            self.env().emit_event(Transfer {
+                from: None,
+                to: Some(owner),
+                id: token,
+            });
+
+            token
+        }
+
+        /// Gets the rarity of a commander, 0 for unknown commanders
+        ///
+        /// # Arguments
+        ///
+        /// * `commander_id` - The id of the commander
+        ///
+        /// # Returns
+        ///
+        /// * `rarity` - The rarity of the commander
+        fn commander_rarity(commander_id: u8) -> u8 {
+            match newomega::prepare_commanders().get(commander_id as usize) {
+                Some(commander) => commander.rarity,
+                _ => 0,
+            }
+        }
+
+        /// Gets the level of a commander, derived from its experience points and capped by its rarity
+        ///
+        /// # Arguments
+        ///
+        /// * `commander_id` - The id of the commander
+        /// * `commander_data` - Progress of the commander
+        ///
+        /// # Returns
+        ///
+        /// * `level` - The level of the commander
+        fn commander_data_level(commander_id: u8, commander_data: &CommanderData) -> u8 {
+            newomega::commander_level(commander_data.xp, Self::commander_rarity(commander_id))
+        }
+
+        /// Gets the progress of the commander token a player fights with, for a commander id
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the player
+        /// * `commander_id` - The id of the commander
+        ///
+        /// # Returns
+        ///
+        /// * `commander_data` - Progress of the equipped token, None if the player does not own the commander
+        fn get_equipped_data(&self, caller: AccountId, commander_id: u8) -> Option<CommanderData> {
+            match self.equipped_commanders.get(&(caller, commander_id)) {
+                Some(token) => Some(self.commander_tokens.get(token).unwrap().data),
+                _ => None,
+            }
+        }
+
+        /// Mints a new instance of a commander for a player, as a commander token
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the player receiving the commander
+        /// * `commander_id` - The id of the commander
+        ///
+        /// # Returns
+        ///
+        /// * `token` - Id of the minted token
+        #[ink(message)]
+        pub fn mint_commander(&mut self, caller: AccountId, commander_id: u8) -> u32 {
+            assert!(self.owners.iter().any(|owner| *owner == self.env().caller()));
+            self.mint_commander_token(caller, commander_id)
         }
 
-        /// Adds Experience Points to a player's commander
+        /// Adds Experience Points to the commander token a player fights with.
+        /// Nothing is added if the player does not own the commander.
         ///
         /// # Arguments
         ///
@@ -147,12 +688,13 @@ mod newomegastorage {
         #[ink(message)]
         pub fn add_commander_xp(&mut self, caller: AccountId, commander_id: u8, amount: u32) {
             assert!(self.owners.iter().any(|owner| *owner == self.env().caller()));
-            self.commanders
-                .entry((caller, commander_id))
-                .or_insert(CommanderData::default()).xp += amount;
+            if let Some(&token) = self.equipped_commanders.get(&(caller, commander_id)) {
+                let commander_data: &mut CommanderData = &mut self.commander_tokens.get_mut(&token).unwrap().data;
+                commander_data.xp = commander_data.xp + amount;
+            }
         }
 
-        /// Gets all the owned commanders for a player.
+        /// Gets the commanders a player fights with, one (equipped) token per commander id.
         ///
         /// # Arguments
         ///
@@ -160,16 +702,17 @@ mod newomegastorage {
         ///
         /// # Returns
         ///
-        /// * `commanders` - A Vec containing a tuple of (commander id, commander data)
+        /// * `commanders` - A Vec containing a tuple of (commander id, commander data, commander level)
         #[ink(message)]
-        pub fn get_commanders(&self, caller: AccountId) -> Vec<(u8, CommanderData)> {
-            self.commanders
+        pub fn get_commanders(&self, caller: AccountId) -> Vec<(u8, CommanderData, u8)> {
+            self.equipped_commanders
                 .iter()
                 .filter_map(|entry| {
-                    let (&key, &value) = entry;
+                    let (&key, &token) = entry;
                     let (account, commander_id) = key;
                     if account == caller {
-                        Some((commander_id, value))
+                        let commander_data: CommanderData = self.commander_tokens.get(&token).unwrap().data;
+                        Some((commander_id, commander_data, Self::commander_data_level(commander_id, &commander_data)))
                     } else {
                         None
                     }
@@ -177,6 +720,82 @@ mod newomegastorage {
                 .collect()
         }
 
+        /// Gets the level of a player's commander.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the player to get the commander level for
+        /// * `commander_id` - The id of the commander
+        ///
+        /// # Returns
+        ///
+        /// * `level` - The level of the commander, 0 if the player does not own it
+        #[ink(message)]
+        pub fn get_commander_level(&self, caller: AccountId, commander_id: u8) -> u8 {
+            match self.get_equipped_data(caller, commander_id) {
+                Some(commander_data) => Self::commander_data_level(commander_id, &commander_data),
+                _ => 0,
+            }
+        }
+
+        /// Selects a skill of a player's commander. The skill has to be unlocked by the level
+        /// of the commander, have its prerequisite selected, and the commander a skill point left.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the player selecting the skill
+        /// * `commander_id` - The id of the commander
+        /// * `skill` - The id of the skill in the skill tree of the commander
+        #[ink(message)]
+        pub fn select_commander_skill(&mut self, caller: AccountId, commander_id: u8, skill: u8) {
+            assert!(self.owners.iter().any(|owner| *owner == self.env().caller()));
+            assert!(self.equipped_commanders.get(&(caller, commander_id)).is_some());
+
+            let commanders: Vec<Commander> = newomega::prepare_commanders();
+            let token: u32 = *self.equipped_commanders.get(&(caller, commander_id)).unwrap();
+            let commander_data: &mut CommanderData = &mut self.commander_tokens.get_mut(&token).unwrap().data;
+            assert!((commander_id as usize) < commanders.len());
+            assert!(newomega::can_select_skill(&commanders[commander_id as usize],
+                Self::commander_data_level(commander_id, commander_data), commander_data.skills, skill));
+            commander_data.skills = commander_data.skills | (1 << skill);
+        }
+
+        /// Gets the selected skills of a player's commander.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the player to get the commander skills for
+        /// * `commander_id` - The id of the commander
+        ///
+        /// # Returns
+        ///
+        /// * `skills` - Selected skills (bitmask of skill ids), 0 if the player does not own the commander
+        #[ink(message)]
+        pub fn get_commander_skills(&self, caller: AccountId, commander_id: u8) -> u32 {
+            match self.get_equipped_data(caller, commander_id) {
+                Some(commander_data) => commander_data.skills,
+                _ => 0,
+            }
+        }
+
+        /// Gets the level and selected skills of all the commanders a player fights with,
+        /// e.g. to snapshot them for a fight resolved later.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the player
+        ///
+        /// # Returns
+        ///
+        /// * `loadouts` - A Vec containing a tuple of (commander id, commander level, selected skills)
+        #[ink(message)]
+        pub fn get_commander_loadouts(&self, caller: AccountId) -> Vec<(u8, u8, u32)> {
+            self.get_commanders(caller)
+                .iter()
+                .map(|&(commander_id, commander_data, level)| (commander_id, level, commander_data.skills))
+                .collect()
+        }
+
         /// Checks whether a player owns a commander.
         ///
         /// # Arguments
@@ -189,31 +808,814 @@ mod newomegastorage {
         /// * `has_commander` - Whether player owns the commander
         #[ink(message)]
         pub fn has_commander(&self, caller: AccountId, commander_id: u8) -> bool {
-            self.commanders.contains_key(&(caller, commander_id))
+            self.equipped_commanders.contains_key(&(caller, commander_id))
         }
 
-        /// Gets the current ranked leaderboard.
+        /// Picks the commander token a player fights with, among the instances of a commander they own
         ///
-        /// # Returns
+        /// # Arguments
         ///
-        /// * `leaderboard` - A Vec containing a tuple of (player account id, player data)
+        /// * `caller` - The account id of the player
+        /// * `token` - Id of the commander token
         #[ink(message)]
-        pub fn get_leaderboard(&self) -> Vec<(AccountId, PlayerData)> {
-            self.players
-                .iter()
-                .filter_map(|entry| {
-                    let (&key, &value) = entry;
-                    Some((key, value))
-                })
-                .collect()
+        pub fn equip_commander(&mut self, caller: AccountId, token: u32) {
+            assert!(self.owners.iter().any(|owner| *owner == self.env().caller()));
+            assert!(self.commander_tokens.get(&token).is_some());
+
+            let commander_token: CommanderToken = *self.commander_tokens.get(&token).unwrap();
+            assert_eq!(commander_token.owner, caller);
+            self.equipped_commanders.insert((caller, commander_token.commander), token);
         }
-    }
 
-    #[cfg(test)]
-    mod tests {
-        use super::*;
-        use ink_env::{
-            test,
+        /// Gets the owner of a commander token
+        ///
+        /// # Arguments
+        ///
+        /// * `token` - Id of the commander token
+        ///
+        /// # Returns
+        ///
+        /// * `owner` - The account id of the owner, None if the token does not exist
+        #[ink(message)]
+        pub fn owner_of(&self, token: u32) -> Option<AccountId> {
+            match self.commander_tokens.get(&token) {
+                Some(commander_token) => Some(commander_token.owner),
+                _ => None,
+            }
+        }
+
+        /// Gets the number of commander tokens owned by an account
+        ///
+        /// # Arguments
+        ///
+        /// * `owner` - The account id of the owner
+        ///
+        /// # Returns
+        ///
+        /// * `balance` - The number of tokens owned
+        #[ink(message)]
+        pub fn balance_of(&self, owner: AccountId) -> u32 {
+            *self.commander_balances.get(&owner).unwrap_or(&0)
+        }
+
+        /// Gets the account approved to transfer a commander token
+        ///
+        /// # Arguments
+        ///
+        /// * `token` - Id of the commander token
+        ///
+        /// # Returns
+        ///
+        /// * `spender` - The approved account id, None if there is none
+        #[ink(message)]
+        pub fn get_approved(&self, token: u32) -> Option<AccountId> {
+            self.commander_approvals.get(&token).cloned()
+        }
+
+        /// Gets a commander token, with its metadata (commander id and progress)
+        ///
+        /// # Arguments
+        ///
+        /// * `token` - Id of the commander token
+        ///
+        /// # Returns
+        ///
+        /// * `commander_token` - The commander token, None if it does not exist
+        #[ink(message)]
+        pub fn get_commander_token(&self, token: u32) -> Option<CommanderToken> {
+            self.commander_tokens.get(&token).cloned()
+        }
+
+        /// Gets all the commander tokens owned by an account
+        ///
+        /// # Arguments
+        ///
+        /// * `owner` - The account id of the owner
+        ///
+        /// # Returns
+        ///
+        /// * `tokens` - A Vec containing a tuple of (token id, commander token)
+        #[ink(message)]
+        pub fn get_commander_tokens(&self, owner: AccountId) -> Vec<(u32, CommanderToken)> {
+            match self.owned_commanders.get(&owner) {
+                Some(tokens) => tokens
+                    .iter()
+                    .map(|&token| (token, *self.commander_tokens.get(&token).unwrap()))
+                    .collect(),
+                _ => Vec::new(),
+            }
+        }
+
+        /// Approves an account to transfer a commander token on behalf of its owner,
+        /// replacing the previous approval. The approval is cleared by the next transfer.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the owner of the token
+        /// * `spender` - The account id to approve, None to clear the approval
+        /// * `token` - Id of the commander token
+        ///
+        /// # Events
+        ///
+        /// * Approval
+        #[ink(message)]
+        pub fn approve(&mut self, caller: AccountId, spender: Option<AccountId>, token: u32) {
+            assert!(self.owners.iter().any(|owner| *owner == self.env().caller()));
+            assert_eq!(self.owner_of(token), Some(caller));
+
+            match spender {
+                Some(account) => {
+                    assert!(account != caller);
+                    self.commander_approvals.insert(token, account);
+                },
+                None => {
+                    self.commander_approvals.take(&token);
+                },
+            }
+
+            self.env().emit_event(Approval {
+                owner: caller,
+                spender,
+                id: token,
+            });
+        }
+
+        /// Transfers a commander token, along with its progress. The caller has to be the owner
+        /// or the approved account. If the previous owner was fighting with the token, another instance
+        /// of the commander they own is equipped, and the new owner fights with the token
+        /// unless they already own the commander.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the owner or approved account
+        /// * `to` - The account id of the receiver
+        /// * `token` - Id of the commander token
+        ///
+        /// # Events
+        ///
+        /// * Transfer
+        #[ink(message)]
+        pub fn transfer(&mut self, caller: AccountId, to: AccountId, token: u32) {
+            assert!(self.owners.iter().any(|owner| *owner == self.env().caller()));
+            assert!(self.commander_tokens.get(&token).is_some());
+
+            let commander_token: CommanderToken = *self.commander_tokens.get(&token).unwrap();
+            let from: AccountId = commander_token.owner;
+            let commander_id: u8 = commander_token.commander;
+
+            assert!(caller == from || self.commander_approvals.get(&token) == Some(&caller));
+            assert!(to != from);
+
+            self.commander_approvals.take(&token);
+            self.commander_tokens.get_mut(&token).unwrap().owner = to;
+            *self.commander_balances.get_mut(&from).unwrap() -= 1;
+            *self.commander_balances.entry(to).or_insert(0) += 1;
+            self.owned_commanders.get_mut(&from).unwrap().retain(|&owned| owned != token);
+            self.owned_commanders.entry(to).or_insert(Vec::new()).push(token);
+
+            if self.equipped_commanders.get(&(from, commander_id)) == Some(&token) {
+                self.equipped_commanders.take(&(from, commander_id));
+                let replacement: Option<u32> = self.owned_commanders
+                    .get(&from)
+                    .unwrap()
+                    .iter()
+                    .find(|&other| self.commander_tokens.get(other).unwrap().commander == commander_id)
+                    .cloned();
+                match replacement {
+                    Some(other_token) => {
+                        self.equipped_commanders.insert((from, commander_id), other_token);
+                    },
+                    _ => (),
+                }
+            }
+            self.equipped_commanders.entry((to, commander_id)).or_insert(token);
+
+            self.env().emit_event(Transfer {
+                from: Some(from),
+                to: Some(to),
+                id: token,
+            });
+        }
+
+        /// Adds commander shards to a player, e.g. for a duplicate commander from a loot crate
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the player
+        /// * `amount` - The amount of shards to add
+        #[ink(message)]
+        pub fn add_commander_shards(&mut self, caller: AccountId, amount: u32) {
+            assert!(self.owners.iter().any(|owner| *owner == self.env().caller()));
+            *self.commander_shards.entry(caller).or_insert(0) += amount;
+        }
+
+        /// Gets the commander shards of a player
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the player
+        ///
+        /// # Returns
+        ///
+        /// * `shards` - The amount of shards
+        #[ink(message)]
+        pub fn get_commander_shards(&self, caller: AccountId) -> u32 {
+            *self.commander_shards.get(&caller).unwrap_or(&0)
+        }
+
+        /// Spends commander shards on a chosen commander, minting a new commander token for the player.
+        /// The amount of shards needed depends on the rarity of the commander.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the player
+        /// * `commander_id` - The id of the chosen commander
+        ///
+        /// # Returns
+        ///
+        /// * `token` - Id of the minted token
+        #[ink(message)]
+        pub fn redeem_commander_shards(&mut self, caller: AccountId, commander_id: u8) -> u32 {
+            assert!(self.owners.iter().any(|owner| *owner == self.env().caller()));
+            assert!((commander_id as usize) < newomega::prepare_commanders().len());
+
+            let cost: u32 = SHARDS_PER_COMMANDER[Self::commander_rarity(commander_id) as usize];
+            let shards: &mut u32 = self.commander_shards.entry(caller).or_insert(0);
+            assert!(*shards >= cost);
+            *shards -= cost;
+
+            self.mint_commander_token(caller, commander_id)
+        }
+
+        /// Counts a loot crate of a type opened by a player without a reward of the top rarity
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the player
+        /// * `crate_type` - Id of the crate type
+        ///
+        /// # Returns
+        ///
+        /// * `crates` - The number of crates since the last reward of the top rarity
+        #[ink(message)]
+        pub fn advance_loot_pity(&mut self, caller: AccountId, crate_type: u32) -> u32 {
+            assert!(self.owners.iter().any(|owner| *owner == self.env().caller()));
+            let crates: &mut u32 = self.loot_pity.entry((caller, crate_type)).or_insert(0);
+            *crates = crates.saturating_add(1);
+            *crates
+        }
+
+        /// Resets the loot crates of a type a player opened, on a reward of the top rarity
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the player
+        /// * `crate_type` - Id of the crate type
+        #[ink(message)]
+        pub fn reset_loot_pity(&mut self, caller: AccountId, crate_type: u32) {
+            assert!(self.owners.iter().any(|owner| *owner == self.env().caller()));
+            self.loot_pity.take(&(caller, crate_type));
+        }
+
+        /// Gets the number of loot crates of a type a player opened since getting a reward of the top rarity
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the player
+        /// * `crate_type` - Id of the crate type
+        ///
+        /// # Returns
+        ///
+        /// * `crates` - The number of crates
+        #[ink(message)]
+        pub fn get_loot_pity(&self, caller: AccountId, crate_type: u32) -> u32 {
+            *self.loot_pity.get(&(caller, crate_type)).unwrap_or(&0)
+        }
+
+        /// Sets the length of a season, after which it can be closed by anyone.
+        /// A length of 0 means seasons are only closed by the admin.
+        ///
+        /// # Arguments
+        ///
+        /// * `season_length` - Length of a season, in blocks
+        #[ink(message)]
+        pub fn set_season_length(&mut self, season_length: BlockNumber) {
+            assert!(self.owners.iter().any(|owner| *owner == self.env().caller()));
+            self.season_length = season_length;
+        }
+
+        /// Gets the current season number, and the block it started in.
+        ///
+        /// # Returns
+        ///
+        /// * `season` - The current season number
+        /// * `started` - The block the current season started in
+        #[ink(message)]
+        pub fn get_season(&self) -> (u32, BlockNumber) {
+            (self.season, self.season_started)
+        }
+
+        /// Checks whether the current season reached its scheduled end.
+        ///
+        /// # Returns
+        ///
+        /// * `is_due` - Whether the season can be closed on schedule
+        #[ink(message)]
+        pub fn is_season_due(&self) -> bool {
+            self.season_length > 0 &&
+                self.env().block_number() >= self.season_started + self.season_length
+        }
+
+        /// Closes the current season: archives the final leaderboard, resets the ranked
+        /// stats of all players and starts the next season. Commanders are kept.
+        ///
+        /// # Returns
+        ///
+        /// * `record` - The archived season record
+        #[ink(message)]
+        pub fn close_season(&mut self) -> SeasonRecord {
+            assert!(self.owners.iter().any(|owner| *owner == self.env().caller()));
+
+            let mut leaderboard: Vec<(AccountId, PlayerData)> = self.get_leaderboard();
+            leaderboard.sort_by(|(account_a, data_a), (account_b, data_b)|
+                data_b.get_rating().cmp(&data_a.get_rating()).then(account_a.cmp(account_b)));
+
+            for (account, _) in leaderboard.iter() {
+                self.players.take(account);
+            }
+
+            let record: SeasonRecord = SeasonRecord {
+                season: self.season,
+                started: self.season_started,
+                ended: self.env().block_number(),
+                leaderboard,
+            };
+
+            self.seasons.push(record.clone());
+            self.season = self.season + 1;
+            self.season_started = self.env().block_number();
+
+            record
+        }
+
+        /// Gets an archived season record.
+        /// Will panic if the season has not been closed yet.
+        ///
+        /// # Arguments
+        ///
+        /// * `season` - The season number
+        ///
+        /// # Returns
+        ///
+        /// * `record` - The archived season record
+        #[ink(message)]
+        pub fn get_season_record(&self, season: u32) -> SeasonRecord {
+            assert!(self.seasons.get(season).is_some());
+            self.seasons.get(season).unwrap().clone()
+        }
+
+        /// Gets the current league of a player. Players who didn't fight yet are in Bronze.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the player
+        ///
+        /// # Returns
+        ///
+        /// * `league` - The league of the player
+        #[ink(message)]
+        pub fn get_league(&self, caller: AccountId) -> League {
+            self.players
+                .get(&caller)
+                .map_or(League::default(), |player_data| player_data.league)
+        }
+
+        /// Gets the current ranked leaderboard.
+        ///
+        /// # Returns
+        ///
+        /// * `leaderboard` - A Vec containing a tuple of (player account id, player data)
+        #[ink(message)]
+        pub fn get_leaderboard(&self) -> Vec<(AccountId, PlayerData)> {
+            self.players
+                .iter()
+                .filter_map(|entry| {
+                    let (&key, &value) = entry;
+                    Some((key, value))
+                })
+                .collect()
+        }
+
+        /// Gets the guild of a member, asserting the member holds at least the given role.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the member
+        /// * `role` - The minimum role
+        ///
+        /// # Returns
+        ///
+        /// * `guild` - Id of the guild
+        fn ensure_guild_role(&self, caller: AccountId, role: GuildRole) -> u32 {
+            assert!(self.guild_members.get(&caller).is_some());
+            let guild: u32 = *self.guild_members.get(&caller).unwrap();
+            assert!(self.guilds.get(&guild).unwrap().get_role(caller).unwrap() >= role);
+            guild
+        }
+
+        /// Records a pending invite or application of a player to a guild.
+        ///
+        /// # Arguments
+        ///
+        /// * `player` - The account id of the player
+        /// * `guild` - Id of the guild
+        fn add_guild_request(&mut self, player: AccountId, guild: u32) {
+            let requests: &mut Vec<u32> = self.guild_requests.entry(player).or_insert(Vec::new());
+            if !requests.contains(&guild) {
+                assert!(requests.len() < MAX_GUILD_REQUESTS);
+                requests.push(guild);
+            }
+        }
+
+        /// Clears all the pending invites and applications of a player, in every guild.
+        ///
+        /// # Arguments
+        ///
+        /// * `player` - The account id of the player
+        fn clear_guild_requests(&mut self, player: AccountId) {
+            for guild in self.guild_requests.take(&player).unwrap_or_default().into_iter() {
+                if let Some(guild_data) = self.guilds.get_mut(&guild) {
+                    guild_data.invites.retain(|invited| *invited != player);
+                    guild_data.applications.retain(|applicant| *applicant != player);
+                }
+            }
+        }
+
+        /// Adds a player to a guild, clearing their pending invites and applications in every guild.
+        ///
+        /// # Arguments
+        ///
+        /// * `guild` - Id of the guild
+        /// * `player` - The account id of the player
+        ///
+        /// # Events
+        ///
+        /// * GuildMemberJoined - when the player joins
+        fn add_guild_member(&mut self, guild: u32, player: AccountId) {
+            assert!(self.guild_members.get(&player).is_none());
+
+            let guild_data: &mut Guild = self.guilds.get_mut(&guild).unwrap();
+            assert!(guild_data.members.len() < MAX_GUILD_MEMBERS);
+            guild_data.members.push((player, GuildRole::Member));
+            self.guild_members.insert(player, guild);
+            self.clear_guild_requests(player);
+
+            self.env().emit_event(GuildMemberJoined {
+                guild,
+                player,
+            });
+        }
+
+        /// Removes a member from their guild. If the leader leaves, the longest standing officer
+        /// (or member, if there are no officers) takes over; the last member leaving disbands the guild.
+        ///
+        /// # Arguments
+        ///
+        /// * `player` - The account id of the member
+        /// * `kicked` - Whether the member was kicked
+        ///
+        /// # Events
+        ///
+        /// * GuildMemberLeft - when the member leaves
+        /// * GuildRoleChanged - when the leadership passes on
+        /// * GuildDisbanded - when the last member leaves
+        fn remove_guild_member(&mut self, player: AccountId, kicked: bool) {
+            let guild: u32 = self.guild_members.take(&player).unwrap();
+            let guild_data: &mut Guild = self.guilds.get_mut(&guild).unwrap();
+            let role: GuildRole = guild_data.get_role(player).unwrap();
+            guild_data.members.retain(|(member, _)| *member != player);
+
+            let successor: Option<usize> = if role == GuildRole::Leader && !guild_data.members.is_empty() {
+                guild_data.members
+                    .iter()
+                    .position(|(_, member_role)| *member_role == GuildRole::Officer)
+                    .or(Some(0))
+            } else {
+                None
+            };
+            let successor: Option<AccountId> = successor.map(|index| {
+                guild_data.members[index].1 = GuildRole::Leader;
+                guild_data.members[index].0
+            });
+            let disbanded: bool = guild_data.members.is_empty();
+
+            self.env().emit_event(GuildMemberLeft {
+                guild,
+                player,
+                kicked,
+            });
+            match successor {
+                Some(leader) => self.env().emit_event(GuildRoleChanged {
+                    guild,
+                    player: leader,
+                    role: GuildRole::Leader,
+                }),
+                _ => (),
+            }
+            if disbanded {
+                let guild_data: Guild = self.guilds.take(&guild).unwrap();
+                for player in guild_data.invites.iter().chain(guild_data.applications.iter()) {
+                    if let Some(requests) = self.guild_requests.get_mut(player) {
+                        requests.retain(|requested| *requested != guild);
+                    }
+                }
+                self.env().emit_event(GuildDisbanded {
+                    guild,
+                });
+            }
+        }
+
+        /// Creates a guild, led by its creator. Pending invites and applications of the creator are cleared.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the player creating the guild
+        /// * `name` - Name of the guild, has to be unique
+        ///
+        /// # Returns
+        ///
+        /// * `guild` - Id of the created guild
+        ///
+        /// # Events
+        ///
+        /// * GuildCreated - when the guild is created
+        #[ink(message)]
+        pub fn create_guild(&mut self, caller: AccountId, name: String) -> u32 {
+            assert!(self.owners.iter().any(|owner| *owner == self.env().caller()));
+            assert!(self.guild_members.get(&caller).is_none());
+            assert!(!name.is_empty());
+            assert!(self.guilds.iter().all(|(_, guild_data)| guild_data.name != name));
+
+            let guild: u32 = self.next_guild_id;
+            self.guilds.insert(guild, Guild {
+                name: name.clone(),
+                members: [(caller, GuildRole::Leader)].to_vec(),
+                invites: Vec::new(),
+                applications: Vec::new(),
+            });
+            self.guild_members.insert(caller, guild);
+            self.next_guild_id = guild + 1;
+            self.clear_guild_requests(caller);
+
+            self.env().emit_event(GuildCreated {
+                guild,
+                leader: caller,
+                name,
+            });
+
+            guild
+        }
+
+        /// Invites a player to the guild of an officer. A guild holds up to MAX_GUILD_MEMBERS invites,
+        /// and a player up to MAX_GUILD_REQUESTS pending invites and applications.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the inviting officer (or leader)
+        /// * `player` - The account id of the invited player
+        #[ink(message)]
+        pub fn invite_to_guild(&mut self, caller: AccountId, player: AccountId) {
+            assert!(self.owners.iter().any(|owner| *owner == self.env().caller()));
+            assert!(self.guild_members.get(&player).is_none());
+
+            let guild: u32 = self.ensure_guild_role(caller, GuildRole::Officer);
+            let guild_data: &mut Guild = self.guilds.get_mut(&guild).unwrap();
+            if !guild_data.invites.contains(&player) {
+                assert!(guild_data.invites.len() < MAX_GUILD_MEMBERS);
+                guild_data.invites.push(player);
+                self.add_guild_request(player, guild);
+            }
+        }
+
+        /// Joins a guild. Invited players join right away, otherwise an application
+        /// is filed, waiting for the approval of an officer. A guild holds up to MAX_GUILD_MEMBERS
+        /// applications, and a player up to MAX_GUILD_REQUESTS pending invites and applications.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the player
+        /// * `guild` - Id of the guild
+        ///
+        /// # Returns
+        ///
+        /// * `joined` - Whether the player joined, false if applied
+        ///
+        /// # Events
+        ///
+        /// * GuildMemberJoined - when the player joins
+        #[ink(message)]
+        pub fn join_guild(&mut self, caller: AccountId, guild: u32) -> bool {
+            assert!(self.owners.iter().any(|owner| *owner == self.env().caller()));
+            assert!(self.guild_members.get(&caller).is_none());
+            assert!(self.guilds.get(&guild).is_some());
+
+            let guild_data: &mut Guild = self.guilds.get_mut(&guild).unwrap();
+            if guild_data.invites.contains(&caller) {
+                self.add_guild_member(guild, caller);
+                true
+            } else {
+                if !guild_data.applications.contains(&caller) {
+                    assert!(guild_data.applications.len() < MAX_GUILD_MEMBERS);
+                    guild_data.applications.push(caller);
+                    self.add_guild_request(caller, guild);
+                }
+                false
+            }
+        }
+
+        /// Approves the application of a player to the guild of an officer.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the approving officer (or leader)
+        /// * `player` - The account id of the applicant
+        ///
+        /// # Events
+        ///
+        /// * GuildMemberJoined - when the player joins
+        #[ink(message)]
+        pub fn approve_guild_member(&mut self, caller: AccountId, player: AccountId) {
+            assert!(self.owners.iter().any(|owner| *owner == self.env().caller()));
+
+            let guild: u32 = self.ensure_guild_role(caller, GuildRole::Officer);
+            assert!(self.guilds.get(&guild).unwrap().applications.contains(&player));
+            self.add_guild_member(guild, player);
+        }
+
+        /// Leaves the guild of a player.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the player
+        ///
+        /// # Events
+        ///
+        /// * GuildMemberLeft - when the player leaves
+        /// * GuildRoleChanged - when the leader leaves, and the leadership passes on
+        /// * GuildDisbanded - when the last member leaves
+        #[ink(message)]
+        pub fn leave_guild(&mut self, caller: AccountId) {
+            assert!(self.owners.iter().any(|owner| *owner == self.env().caller()));
+            assert!(self.guild_members.get(&caller).is_some());
+            self.remove_guild_member(caller, false);
+        }
+
+        /// Kicks a member out of the guild. Officers can kick members, the leader can kick officers as well.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the kicking officer (or leader)
+        /// * `player` - The account id of the kicked member
+        ///
+        /// # Events
+        ///
+        /// * GuildMemberLeft - when the member is kicked
+        #[ink(message)]
+        pub fn kick_guild_member(&mut self, caller: AccountId, player: AccountId) {
+            assert!(self.owners.iter().any(|owner| *owner == self.env().caller()));
+
+            let guild: u32 = self.ensure_guild_role(caller, GuildRole::Officer);
+            let guild_data: &Guild = self.guilds.get(&guild).unwrap();
+            assert!(guild_data.get_role(player).is_some());
+            assert!(guild_data.get_role(caller).unwrap() > guild_data.get_role(player).unwrap());
+            self.remove_guild_member(player, true);
+        }
+
+        /// Sets the role of a guild member, by the leader. Appointing another leader
+        /// hands over the leadership, making the previous leader an officer.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the leader
+        /// * `player` - The account id of the member
+        /// * `role` - The new role of the member
+        ///
+        /// # Events
+        ///
+        /// * GuildRoleChanged - for every member whose role changes
+        #[ink(message)]
+        pub fn set_guild_role(&mut self, caller: AccountId, player: AccountId, role: GuildRole) {
+            assert!(self.owners.iter().any(|owner| *owner == self.env().caller()));
+            assert!(caller != player);
+
+            let guild: u32 = self.ensure_guild_role(caller, GuildRole::Leader);
+            let guild_data: &mut Guild = self.guilds.get_mut(&guild).unwrap();
+            assert!(guild_data.get_role(player).is_some());
+
+            let mut changes: Vec<(AccountId, GuildRole)> = [(player, role)].to_vec();
+            if role == GuildRole::Leader {
+                changes.push((caller, GuildRole::Officer));
+            }
+            for (member, member_role) in guild_data.members.iter_mut() {
+                match changes.iter().find(|(changed, _)| *changed == *member) {
+                    Some(&(_, new_role)) => *member_role = new_role,
+                    _ => (),
+                }
+            }
+
+            for (member, new_role) in changes.into_iter() {
+                self.env().emit_event(GuildRoleChanged {
+                    guild,
+                    player: member,
+                    role: new_role,
+                });
+            }
+        }
+
+        /// Gets a guild.
+        /// Will panic if the guild does not exist.
+        ///
+        /// # Arguments
+        ///
+        /// * `guild` - Id of the guild
+        ///
+        /// # Returns
+        ///
+        /// * `guild` - The guild
+        #[ink(message)]
+        pub fn get_guild(&self, guild: u32) -> Guild {
+            assert!(self.guilds.get(&guild).is_some());
+            self.guilds.get(&guild).unwrap().clone()
+        }
+
+        /// Gets all the guilds.
+        ///
+        /// # Returns
+        ///
+        /// * `guilds` - A Vec containing a tuple of (guild id, guild)
+        #[ink(message)]
+        pub fn get_guilds(&self) -> Vec<(u32, Guild)> {
+            self.guilds
+                .iter()
+                .map(|(&guild, guild_data)| (guild, guild_data.clone()))
+                .collect()
+        }
+
+        /// Gets the guild of a player.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the player
+        ///
+        /// # Returns
+        ///
+        /// * `guild` - Id of the guild, None if the player is not in a guild
+        #[ink(message)]
+        pub fn get_player_guild(&self, caller: AccountId) -> Option<u32> {
+            self.guild_members.get(&caller).cloned()
+        }
+
+        /// Gets the guild leaderboard, aggregated from the current ranked results of the members.
+        ///
+        /// # Returns
+        ///
+        /// * `leaderboard` - The guild standings, most points first
+        #[ink(message)]
+        pub fn get_guild_leaderboard(&self) -> Vec<GuildStanding> {
+            let mut leaderboard: Vec<GuildStanding> = self.guilds
+                .iter()
+                .map(|(&guild, guild_data)| {
+                    let mut standing: GuildStanding = GuildStanding {
+                        guild,
+                        name: guild_data.name.clone(),
+                        members: guild_data.members.len() as u8,
+                        ..GuildStanding::default()
+                    };
+                    for (member, _) in guild_data.members.iter() {
+                        match self.players.get(member) {
+                            Some(player_data) => {
+                                standing.points += player_data.points;
+                                standing.ranked_wins += player_data.ranked_wins;
+                                standing.ranked_losses += player_data.ranked_losses + player_data.ranked_retreats;
+                            },
+                            _ => (),
+                        }
+                    }
+                    standing
+                })
+                .collect();
+
+            leaderboard.sort_by(|standing_a, standing_b|
+                standing_b.points.cmp(&standing_a.points).then(standing_a.guild.cmp(&standing_b.guild)));
+            leaderboard
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use ink_env::{
+            test,
         };
         use ink_lang as ink;
         type Accounts = test::DefaultAccounts<Environment>;
@@ -240,29 +1642,542 @@ mod newomegastorage {
             assert_eq!(leaderboard[1].1.ranked_losses, 1);
         }
 
+        #[ink::test]
+        fn test_ranked_retreats_and_rating() {
+            let mut contract = NewOmegaStorage::default();
+            let accounts = default_accounts();
+
+            contract.mark_ranked_win(accounts.alice);
+            contract.mark_ranked_loss(accounts.bob);
+            contract.mark_ranked_retreat(accounts.bob);
+
+            let leaderboard: Vec<(AccountId, PlayerData)> = contract.get_leaderboard();
+
+            assert_eq!(leaderboard[0].1.ranked_retreats, 0);
+            assert_eq!(leaderboard[1].1.ranked_retreats, 1);
+            assert_eq!(leaderboard[0].1.get_rating(), 30);
+            assert_eq!(leaderboard[1].1.get_rating(), 0);
+        }
+
+        #[ink::test]
+        fn test_league_evaluation() {
+            // Promotion as soon as the threshold is reached, possibly skipping leagues
+            assert_eq!(evaluate_league(League::Bronze, 99), League::Bronze);
+            assert_eq!(evaluate_league(League::Bronze, 100), League::Silver);
+            assert_eq!(evaluate_league(League::Bronze, 600), League::Platinum);
+            assert_eq!(evaluate_league(League::Diamond, 5000), League::Legend);
+
+            // Relegation only below the margin
+            assert_eq!(evaluate_league(League::Gold, 249), League::Gold);
+            assert_eq!(evaluate_league(League::Gold, 225), League::Gold);
+            assert_eq!(evaluate_league(League::Gold, 224), League::Silver);
+            assert_eq!(evaluate_league(League::Gold, 0), League::Bronze);
+
+            assert_eq!(League::Gold.distance(League::Silver), 1);
+            assert_eq!(League::Bronze.distance(League::Legend), 5);
+        }
+
+        #[ink::test]
+        fn test_promotion_and_relegation() {
+            let mut contract = NewOmegaStorage::default();
+            let accounts = default_accounts();
+
+            assert_eq!(contract.get_league(accounts.alice), League::Bronze);
+
+            // 4 wins reach Silver
+            for _ in 0..4 {
+                contract.mark_ranked_win(accounts.alice);
+            }
+            assert_eq!(contract.get_league(accounts.alice), League::Silver);
+            assert_eq!(test::recorded_events().count(), 1);
+
+            // 120 - 10 - 20 = 90 points, still within the relegation margin
+            contract.mark_ranked_retreat(accounts.alice);
+            contract.mark_ranked_loss(accounts.alice);
+            assert_eq!(contract.get_league(accounts.alice), League::Silver);
+
+            // 70 points, relegated
+            contract.mark_ranked_loss(accounts.alice);
+            assert_eq!(contract.get_league(accounts.alice), League::Bronze);
+            assert_eq!(test::recorded_events().count(), 2);
+
+            // Points don't go below 0
+            for _ in 0..10 {
+                contract.mark_ranked_loss(accounts.bob);
+            }
+            assert_eq!(contract.get_leaderboard()[1].1.get_rating(), 0);
+            contract.mark_ranked_win(accounts.bob);
+            assert_eq!(contract.get_leaderboard()[1].1.get_rating(), 30);
+        }
+
+        #[ink::test]
+        fn test_season_rollover() {
+            let mut contract = NewOmegaStorage::default();
+            let accounts = default_accounts();
+
+            contract.mint_commander(accounts.alice, 0);
+            contract.add_commander_xp(accounts.alice, 0, 100);
+            contract.mark_ranked_win(accounts.bob);
+            contract.mark_ranked_win(accounts.bob);
+            contract.mark_ranked_win(accounts.alice);
+            contract.mark_ranked_loss(accounts.alice);
+            contract.mark_ranked_loss(accounts.charlie);
+
+            // No schedule, only the admin closes seasons
+            contract.set_season_length(0);
+            assert!(!contract.is_season_due());
+
+            contract.set_season_length(3);
+            for _ in 0..2 {
+                test::advance_block::<Environment>().expect("Cannot advance block");
+            }
+            assert!(!contract.is_season_due());
+            test::advance_block::<Environment>().expect("Cannot advance block");
+            assert!(contract.is_season_due());
+
+            let (season, started) = contract.get_season();
+            assert_eq!(season, 0);
+
+            let record: SeasonRecord = contract.close_season();
+
+            // The final leaderboard is archived, best rating first
+            assert_eq!(record.season, 0);
+            assert_eq!(record.started, started);
+            assert_eq!(record.ended, started + 3);
+            assert_eq!(record.leaderboard.len(), 3);
+            assert_eq!(record.leaderboard[0].0, accounts.bob);
+            assert_eq!(record.leaderboard[0].1.ranked_wins, 2);
+            assert_eq!(record.leaderboard[1].0, accounts.alice);
+            assert_eq!(record.leaderboard[2].0, accounts.charlie);
+            assert_eq!(contract.get_season_record(0), record);
+
+            // Ranked stats are reset, commanders are kept, and the next season starts
+            assert_eq!(contract.get_leaderboard().len(), 0);
+            assert_eq!(contract.get_commanders(accounts.alice).len(), 1);
+            assert_eq!(contract.get_season(), (1, started + 3));
+            assert!(!contract.is_season_due());
+
+            contract.mark_ranked_win(accounts.charlie);
+            test::advance_block::<Environment>().expect("Cannot advance block");
+            let second_record: SeasonRecord = contract.close_season();
+
+            assert_eq!(second_record.season, 1);
+            assert_eq!(second_record.leaderboard.len(), 1);
+            assert_eq!(second_record.leaderboard[0].0, accounts.charlie);
+            assert_eq!(contract.get_season_record(0), record);
+            assert_eq!(contract.get_season().0, 2);
+        }
+
+        #[ink::test]
+        fn test_season_payouts() {
+            let accounts = default_accounts();
+            let record: SeasonRecord = SeasonRecord {
+                season: 0,
+                started: 0,
+                ended: 10,
+                leaderboard: [accounts.bob, accounts.alice, accounts.charlie]
+                    .iter()
+                    .map(|player| (*player, PlayerData::default()))
+                    .collect(),
+            };
+
+            // Rewards go by final position, positions without a reward get nothing
+            assert_eq!(season_payouts(&record, &[100, 50].to_vec(), 1000),
+                [(accounts.bob, 100), (accounts.alice, 50)].to_vec());
+
+            // Rewards beyond the number of players are not paid out
+            assert_eq!(season_payouts(&record, &[100, 50, 20, 10].to_vec(), 1000).len(), 3);
+
+            // Rewards exceeding the remaining funds are skipped
+            assert_eq!(season_payouts(&record, &[100, 50, 20].to_vec(), 130),
+                [(accounts.bob, 100), (accounts.charlie, 20)].to_vec());
+            assert_eq!(season_payouts(&record, &[100, 50, 20].to_vec(), 0).len(), 0);
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_season_record_missing() {
+            let contract = NewOmegaStorage::default();
+            contract.get_season_record(0);
+        }
+
         #[ink::test]
         fn test_commanders() {
             let mut contract = NewOmegaStorage::default();
             let accounts = default_accounts();
 
+            contract.mint_commander(accounts.alice, 0);
+            contract.mint_commander(accounts.bob, 1);
             contract.add_commander_xp(accounts.alice, 0, 100);
             contract.add_commander_xp(accounts.bob, 1, 50);
 
-            let commanders_alice: Vec<(u8, CommanderData)> = contract.get_commanders(accounts.alice);
-            let commanders_bob: Vec<(u8, CommanderData)> = contract.get_commanders(accounts.bob);
-            let commanders_eve: Vec<(u8, CommanderData)> = contract.get_commanders(accounts.eve);
+            let commanders_alice: Vec<(u8, CommanderData, u8)> = contract.get_commanders(accounts.alice);
+            let commanders_bob: Vec<(u8, CommanderData, u8)> = contract.get_commanders(accounts.bob);
+            let commanders_eve: Vec<(u8, CommanderData, u8)> = contract.get_commanders(accounts.eve);
 
             assert_eq!(commanders_alice.len(), 1);
             assert_eq!(commanders_bob.len(), 1);
             assert_eq!(commanders_eve.len(), 0);
 
-            let (commander_index_alice, commander_data_alice) = commanders_alice[0];
+            let (commander_index_alice, commander_data_alice, _) = commanders_alice[0];
             assert_eq!(commander_index_alice, 0);
             assert_eq!(commander_data_alice.xp, 100);
 
-            let (commander_index_bob, commander_data_bob) = commanders_bob[0];
+            let (commander_index_bob, commander_data_bob, _) = commanders_bob[0];
             assert_eq!(commander_index_bob, 1);
             assert_eq!(commander_data_bob.xp, 50);
         }
+
+        #[ink::test]
+        fn test_commander_xp_unowned() {
+            let mut contract = NewOmegaStorage::default();
+            let accounts = default_accounts();
+
+            // A ranked or war win with a commander the player does not own mints nothing
+            contract.add_commander_xp(accounts.alice, 3, 100);
+            assert!(!contract.has_commander(accounts.alice, 3));
+            assert_eq!(contract.balance_of(accounts.alice), 0);
+            assert_eq!(contract.get_commanders(accounts.alice).len(), 0);
+            assert_eq!(contract.get_commander_level(accounts.alice, 3), 0);
+        }
+
+        #[ink::test]
+        fn test_commander_levels() {
+            let mut contract = NewOmegaStorage::default();
+            let accounts = default_accounts();
+
+            assert_eq!(contract.get_commander_level(accounts.alice, 0), 0);
+
+            contract.mint_commander(accounts.alice, 0);
+            contract.mint_commander(accounts.alice, 3);
+            contract.add_commander_xp(accounts.alice, 0, 5);
+            assert_eq!(contract.get_commander_level(accounts.alice, 0), 1);
+            contract.add_commander_xp(accounts.alice, 0, 5);
+            assert_eq!(contract.get_commander_level(accounts.alice, 0), 2);
+
+            // The level is capped by the rarity of the commander
+            contract.add_commander_xp(accounts.alice, 0, 100_000);
+            contract.add_commander_xp(accounts.alice, 3, 100_000);
+            assert_eq!(contract.get_commander_level(accounts.alice, 0), 10);
+            assert_eq!(contract.get_commander_level(accounts.alice, 3), 25);
+
+            let commanders: Vec<(u8, CommanderData, u8)> = contract.get_commanders(accounts.alice);
+            assert!(commanders.iter().any(|&(id, _, level)| id == 3 && level == 25));
+        }
+
+        #[ink::test]
+        fn test_commander_skills() {
+            let mut contract = NewOmegaStorage::default();
+            let accounts = default_accounts();
+
+            // Level 5 gives a skill point, spent on the first skill of the tree
+            contract.mint_commander(accounts.alice, 3);
+            contract.add_commander_xp(accounts.alice, 3, 100);
+            contract.select_commander_skill(accounts.alice, 3, 0);
+            assert_eq!(contract.get_commander_skills(accounts.alice, 3), 0b1);
+            assert_eq!(contract.get_commander_skills(accounts.bob, 3), 0);
+
+            // Level 10 gives another one, and unlocks the skill requiring the first one
+            contract.add_commander_xp(accounts.alice, 3, 350);
+            contract.select_commander_skill(accounts.alice, 3, 2);
+            assert_eq!(contract.get_commander_skills(accounts.alice, 3), 0b101);
+            assert_eq!(contract.get_commander_loadouts(accounts.alice), [(3, 10, 0b101)].to_vec());
+            assert_eq!(contract.get_commander_loadouts(accounts.bob), Vec::new());
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_commander_skill_locked() {
+            let mut contract = NewOmegaStorage::default();
+            let accounts = default_accounts();
+
+            // Level 5 doesn't unlock the second tier
+            contract.mint_commander(accounts.alice, 3);
+            contract.add_commander_xp(accounts.alice, 3, 100);
+            contract.select_commander_skill(accounts.alice, 3, 0);
+            contract.select_commander_skill(accounts.alice, 3, 2);
+        }
+
+        #[ink::test]
+        fn test_commander_tokens() {
+            let mut contract = NewOmegaStorage::default();
+            let accounts = default_accounts();
+
+            contract.mint_commander(accounts.alice, 0);
+            contract.add_commander_xp(accounts.alice, 0, 100);
+            let token: u32 = contract.mint_commander(accounts.alice, 0);
+            assert_eq!(token, 1);
+            assert_eq!(contract.balance_of(accounts.alice), 2);
+            assert_eq!(contract.owner_of(token), Some(accounts.alice));
+            assert_eq!(contract.owner_of(2), None);
+            assert_eq!(contract.get_commander_tokens(accounts.alice).len(), 2);
+
+            // The first instance stays equipped, the new one starts from scratch
+            assert_eq!(contract.get_commanders(accounts.alice).len(), 1);
+            assert_eq!(contract.get_commander_level(accounts.alice, 0), 5);
+            assert_eq!(contract.get_commander_token(token).unwrap().data.xp, 0);
+
+            contract.equip_commander(accounts.alice, token);
+            assert_eq!(contract.get_commander_level(accounts.alice, 0), 1);
+        }
+
+        #[ink::test]
+        fn test_commander_transfer() {
+            let mut contract = NewOmegaStorage::default();
+            let accounts = default_accounts();
+
+            contract.mint_commander(accounts.alice, 0);
+            contract.add_commander_xp(accounts.alice, 0, 100);
+            contract.mint_commander(accounts.alice, 0);
+
+            // The progress goes along with the token, the sender falls back to its other instance
+            contract.transfer(accounts.alice, accounts.bob, 0);
+            assert_eq!(contract.owner_of(0), Some(accounts.bob));
+            assert_eq!(contract.balance_of(accounts.alice), 1);
+            assert_eq!(contract.balance_of(accounts.bob), 1);
+            assert_eq!(contract.get_commander_level(accounts.bob, 0), 5);
+            assert_eq!(contract.get_commander_level(accounts.alice, 0), 1);
+
+            contract.transfer(accounts.alice, accounts.bob, 1);
+            assert!(!contract.has_commander(accounts.alice, 0));
+            assert_eq!(contract.get_commander_level(accounts.bob, 0), 5);
+
+            // The tokens of both accounts follow the transfers
+            assert_eq!(contract.get_commander_tokens(accounts.alice).len(), 0);
+            assert_eq!(contract.get_commander_tokens(accounts.bob).iter().map(|&(token, _)| token)
+                .collect::<Vec<u32>>(), [0, 1].to_vec());
+            assert!(contract.get_commander_tokens(accounts.bob).iter()
+                .all(|(_, commander_token)| commander_token.owner == accounts.bob));
+        }
+
+        #[ink::test]
+        fn test_commander_transfer_fallback() {
+            let mut contract = NewOmegaStorage::default();
+            let accounts = default_accounts();
+
+            contract.mint_commander(accounts.alice, 0);
+            contract.mint_commander(accounts.alice, 1);
+            let token: u32 = contract.mint_commander(accounts.alice, 0);
+            contract.equip_commander(accounts.alice, token);
+            contract.add_commander_xp(accounts.alice, 0, 100);
+            contract.equip_commander(accounts.alice, 0);
+
+            // The sender falls back to its other instance of the same commander, not another commander
+            contract.transfer(accounts.alice, accounts.bob, 0);
+            assert_eq!(contract.get_commander_tokens(accounts.alice).len(), 2);
+            assert_eq!(contract.get_commander_level(accounts.alice, 0), 5);
+            assert_eq!(contract.get_commander_level(accounts.alice, 1), 1);
+        }
+
+        #[ink::test]
+        fn test_commander_approval() {
+            let mut contract = NewOmegaStorage::default();
+            let accounts = default_accounts();
+
+            let token: u32 = contract.mint_commander(accounts.alice, 2);
+            contract.approve(accounts.alice, Some(accounts.bob), token);
+            assert_eq!(contract.get_approved(token), Some(accounts.bob));
+
+            contract.transfer(accounts.bob, accounts.eve, token);
+            assert_eq!(contract.owner_of(token), Some(accounts.eve));
+            assert_eq!(contract.get_approved(token), None);
+            assert!(contract.has_commander(accounts.eve, 2));
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_commander_transfer_unapproved() {
+            let mut contract = NewOmegaStorage::default();
+            let accounts = default_accounts();
+
+            let token: u32 = contract.mint_commander(accounts.alice, 2);
+            contract.transfer(accounts.bob, accounts.eve, token);
+        }
+
+        #[ink::test]
+        fn test_commander_shards() {
+            let mut contract = NewOmegaStorage::default();
+            let accounts = default_accounts();
+
+            contract.add_commander_shards(accounts.alice, 25);
+            contract.add_commander_shards(accounts.alice, 10);
+            assert_eq!(contract.get_commander_shards(accounts.alice), 35);
+            assert_eq!(contract.get_commander_shards(accounts.bob), 0);
+
+            // Shards are spent on the chosen commander, depending on its rarity
+            let token: u32 = contract.redeem_commander_shards(accounts.alice, 1);
+            assert_eq!(contract.get_commander_shards(accounts.alice), 5);
+            assert_eq!(contract.owner_of(token), Some(accounts.alice));
+            assert!(contract.has_commander(accounts.alice, 1));
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_commander_shards_insufficient() {
+            let mut contract = NewOmegaStorage::default();
+            let accounts = default_accounts();
+
+            contract.add_commander_shards(accounts.alice, 100);
+            contract.redeem_commander_shards(accounts.alice, 3);
+        }
+
+        #[ink::test]
+        fn test_loot_pity() {
+            let mut contract = NewOmegaStorage::default();
+            let accounts = default_accounts();
+
+            assert_eq!(contract.get_loot_pity(accounts.alice, 0), 0);
+            assert_eq!(contract.advance_loot_pity(accounts.alice, 0), 1);
+            assert_eq!(contract.advance_loot_pity(accounts.alice, 0), 2);
+            assert_eq!(contract.advance_loot_pity(accounts.alice, 1), 1);
+            assert_eq!(contract.get_loot_pity(accounts.alice, 0), 2);
+            assert_eq!(contract.get_loot_pity(accounts.bob, 0), 0);
+
+            // Resetting one crate type keeps the count of the others
+            contract.reset_loot_pity(accounts.alice, 0);
+            assert_eq!(contract.get_loot_pity(accounts.alice, 0), 0);
+            assert_eq!(contract.get_loot_pity(accounts.alice, 1), 1);
+        }
+
+        #[ink::test]
+        fn test_guilds() {
+            let mut contract = NewOmegaStorage::default();
+            let accounts = default_accounts();
+
+            let guild: u32 = contract.create_guild(accounts.alice, String::from("Omega"));
+            assert_eq!(contract.get_player_guild(accounts.alice), Some(guild));
+
+            // Invited players join right away, others apply
+            contract.invite_to_guild(accounts.alice, accounts.bob);
+            assert!(contract.join_guild(accounts.bob, guild));
+            assert!(!contract.join_guild(accounts.charlie, guild));
+            assert_eq!(contract.get_guild(guild).applications, [accounts.charlie].to_vec());
+
+            // Officers approve applications
+            contract.set_guild_role(accounts.alice, accounts.bob, GuildRole::Officer);
+            contract.approve_guild_member(accounts.bob, accounts.charlie);
+            assert_eq!(contract.get_guild(guild).members.len(), 3);
+            assert_eq!(contract.get_guild(guild).applications.len(), 0);
+            assert_eq!(contract.get_guild(guild).get_role(accounts.charlie), Some(GuildRole::Member));
+
+            let other_guild: u32 = contract.create_guild(accounts.eve, String::from("Other"));
+            contract.mark_ranked_win(accounts.alice);
+            contract.mark_ranked_win(accounts.bob);
+            contract.mark_ranked_loss(accounts.charlie);
+            for _ in 0..3 {
+                contract.mark_ranked_win(accounts.eve);
+            }
+
+            let leaderboard: Vec<GuildStanding> = contract.get_guild_leaderboard();
+            assert_eq!(leaderboard.len(), 2);
+            assert_eq!(leaderboard[0].guild, other_guild);
+            assert_eq!(leaderboard[0].points, 90);
+            assert_eq!(leaderboard[1], GuildStanding {
+                guild,
+                name: String::from("Omega"),
+                members: 3,
+                points: 60,
+                ranked_wins: 2,
+                ranked_losses: 1,
+            });
+
+            // Officers kick members
+            contract.kick_guild_member(accounts.bob, accounts.charlie);
+            assert_eq!(contract.get_player_guild(accounts.charlie), None);
+            assert_eq!(contract.get_guild(guild).members.len(), 2);
+        }
+
+        #[ink::test]
+        fn test_guild_leadership() {
+            let mut contract = NewOmegaStorage::default();
+            let accounts = default_accounts();
+
+            let guild: u32 = contract.create_guild(accounts.alice, String::from("Omega"));
+            for player in [accounts.bob, accounts.charlie].iter() {
+                contract.invite_to_guild(accounts.alice, *player);
+                contract.join_guild(*player, guild);
+            }
+            contract.set_guild_role(accounts.alice, accounts.charlie, GuildRole::Officer);
+
+            // An officer takes over from a leaving leader
+            contract.leave_guild(accounts.alice);
+            assert_eq!(contract.get_guild(guild).get_role(accounts.charlie), Some(GuildRole::Leader));
+
+            // Appointing a leader hands over the leadership
+            contract.set_guild_role(accounts.charlie, accounts.bob, GuildRole::Leader);
+            assert_eq!(contract.get_guild(guild).get_role(accounts.bob), Some(GuildRole::Leader));
+            assert_eq!(contract.get_guild(guild).get_role(accounts.charlie), Some(GuildRole::Officer));
+
+            // The last member leaving disbands the guild, freeing up the name
+            contract.leave_guild(accounts.bob);
+            contract.leave_guild(accounts.charlie);
+            assert_eq!(contract.get_guilds().len(), 0);
+            contract.create_guild(accounts.charlie, String::from("Omega"));
+        }
+
+        #[ink::test]
+        fn test_guild_requests_cleared() {
+            let mut contract = NewOmegaStorage::default();
+            let accounts = default_accounts();
+
+            let guild: u32 = contract.create_guild(accounts.alice, String::from("Omega"));
+            let other_guild: u32 = contract.create_guild(accounts.bob, String::from("Other"));
+            contract.invite_to_guild(accounts.bob, accounts.charlie);
+            assert!(!contract.join_guild(accounts.charlie, guild));
+            contract.invite_to_guild(accounts.alice, accounts.django);
+            contract.invite_to_guild(accounts.bob, accounts.django);
+
+            // Joining a guild clears the pending invites and applications everywhere else
+            assert!(contract.join_guild(accounts.charlie, other_guild));
+            assert_eq!(contract.get_guild(guild).applications.len(), 0);
+            assert_eq!(contract.get_guild(other_guild).invites, [accounts.django].to_vec());
+
+            // So does creating one
+            contract.create_guild(accounts.django, String::from("Third"));
+            assert_eq!(contract.get_guild(guild).invites.len(), 0);
+            assert_eq!(contract.get_guild(other_guild).invites.len(), 0);
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_guild_invites_capped() {
+            let mut contract = NewOmegaStorage::default();
+            let accounts = default_accounts();
+
+            contract.create_guild(accounts.alice, String::from("Omega"));
+            for index in 0..=MAX_GUILD_MEMBERS {
+                contract.invite_to_guild(accounts.alice, AccountId::from([100 + index as u8; 32]));
+            }
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_guild_requests_capped() {
+            let mut contract = NewOmegaStorage::default();
+            let accounts = default_accounts();
+
+            for index in 0..=MAX_GUILD_REQUESTS {
+                let guild: u32 = contract.create_guild(AccountId::from([100 + index as u8; 32]),
+                    format!("Guild {}", index));
+                contract.join_guild(accounts.alice, guild);
+            }
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_guild_kick_officer() {
+            let mut contract = NewOmegaStorage::default();
+            let accounts = default_accounts();
+
+            let guild: u32 = contract.create_guild(accounts.alice, String::from("Omega"));
+            for player in [accounts.bob, accounts.charlie].iter() {
+                contract.invite_to_guild(accounts.alice, *player);
+                contract.join_guild(*player, guild);
+                contract.set_guild_role(accounts.alice, *player, GuildRole::Officer);
+            }
+
+            // Officers can't kick other officers
+            contract.kick_guild_member(accounts.bob, accounts.charlie);
+        }
     }
 }
@@ -0,0 +1,866 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use ink_lang as ink;
+pub use self::newomegatournament::NewOmegaTournament;
+pub use self::newomegatournament::Tournament;
+pub use self::newomegatournament::TournamentFormat;
+pub use self::newomegatournament::TournamentState;
+pub use self::newomegatournament::TournamentEntry;
+pub use self::newomegatournament::TournamentMatch;
+
+/// Runs bracket tournaments between players: signups with locked fleets, single elimination
+/// or Swiss brackets, and rounds resolved by the Game Engine through Fight Management.
+/// This contract does not deal with payments, it only keeps track of the entry fees
+/// and computes the payouts, leaving the transfers to the Delegator.
+#[ink::contract]
+mod newomegatournament {
+    use newomegagame::NewOmegaGame;
+    use newomega::MAX_SHIPS;
+    use newomega::Reserve;
+    use newomega::is_reserve_valid;
+    use newomega::FightResult;
+    use ink_prelude::vec::Vec;
+    use ink_storage::{
+        collections::{
+            HashMap as StorageHashMap,
+        },
+        traits::{
+            PackedLayout,
+            SpreadLayout,
+        },
+    };
+
+    const MAX_ENTRIES: u8 = 64;
+    const MAX_PAIRING_STEPS: u32 = 1024;
+
+    /// Describes the bracket of a tournament
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Copy, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            Debug,
+            PartialEq,
+            Eq,
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub enum TournamentFormat {
+        /// Losers are eliminated, until one player is left
+        SingleElimination,
+        /// Everybody plays a fixed number of rounds against players with a similar score
+        Swiss { rounds: u8 },
+    }
+
+    /// Describes the stage a tournament is in
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Copy, Clone,
+        Debug, Eq, PartialEq)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub enum TournamentState {
+        /// Accepting signups
+        Signup,
+        /// Rounds are being played
+        Running,
+        /// All rounds played, prizes paid out
+        Finished,
+        /// Cancelled during signup, entry fees refunded
+        Cancelled,
+    }
+
+    /// Describes a tournament entry, with the fleet locked at signup
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            Debug,
+            PartialEq,
+            Eq,
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub struct TournamentEntry {
+        /// Account id of the player
+        pub player: AccountId,
+        /// Fleet composition
+        pub selection: [u8; MAX_SHIPS],
+        /// Fleet variants (fittings)
+        pub variants: [u8; MAX_SHIPS],
+        /// Commander index
+        pub commander: u8,
+        /// Commander level, as of the signup
+        pub commander_level: u8,
+        /// Commander skills (bitmask of skill ids), as of the signup
+        pub commander_skills: u32,
+        /// Reserve group, entering the battle later
+        pub reserve: Reserve,
+    }
+
+    /// Describes a played tournament match. A bye is recorded as a match of an entry against itself.
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            Debug,
+            PartialEq,
+            Eq,
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub struct TournamentMatch {
+        /// Round the match was played in
+        pub round: u8,
+        /// Index of the entry playing as the attacker
+        pub lhs: u8,
+        /// Index of the entry playing as the defender
+        pub rhs: u8,
+        /// Whether the attacker won
+        pub lhs_won: bool,
+        /// Commitment hash of the fight, zeroed for byes
+        pub hash: [u8; 32],
+    }
+
+    /// Describes a tournament
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            Debug,
+            PartialEq,
+            Eq,
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub struct Tournament {
+        /// Bracket format
+        pub format: TournamentFormat,
+        /// Current stage
+        pub state: TournamentState,
+        /// Entry fee, paid at signup
+        pub entry_fee: Balance,
+        /// Maximum number of entries
+        pub max_entries: u8,
+        /// Percentages of the prize pool paid out, by final standing
+        pub prize_shares: Vec<u8>,
+        /// Sum of the entry fees
+        pub prize_pool: Balance,
+        /// Entries, in signup order
+        pub entries: Vec<TournamentEntry>,
+        /// Number of rounds played
+        pub round: u8,
+        /// Matches won (including byes), per entry
+        pub scores: Vec<u8>,
+        /// Whether the entry was eliminated (single elimination only)
+        pub eliminated: Vec<bool>,
+        /// All the played matches
+        pub matches: Vec<TournamentMatch>,
+        /// Final standings, once finished
+        pub standings: Vec<AccountId>,
+    }
+
+    /// Generates the pairings of a single elimination round. In the first round, the first
+    /// entries receive byes to fill the bracket up to a power of two, so no byes are needed later.
+    ///
+    /// # Arguments
+    ///
+    /// * `eliminated` - Whether each entry was eliminated
+    /// * `round` - The round to generate pairings for
+    ///
+    /// # Returns
+    ///
+    /// * `pairs` - Pairs of entry indexes to play against each other
+    /// * `byes` - Entry indexes advancing without playing
+    pub fn elimination_pairings(eliminated: &Vec<bool>, round: u8) -> (Vec<(u8, u8)>, Vec<u8>) {
+        let alive: Vec<u8> = (0..eliminated.len())
+            .filter(|&i| !eliminated[i])
+            .map(|i| i as u8)
+            .collect();
+        let bye_count: usize = if round == 0 {
+            alive.len().next_power_of_two() - alive.len()
+        } else {
+            alive.len() % 2
+        };
+
+        let byes: Vec<u8> = alive[..bye_count].to_vec();
+        let pairs: Vec<(u8, u8)> = alive[bye_count..]
+            .chunks(2)
+            .map(|pair| (pair[0], pair[1]))
+            .collect();
+
+        (pairs, byes)
+    }
+
+    /// Generates the pairings of a Swiss round. Entries are ordered by score, and paired with the
+    /// closest entry in order they haven't played yet, such that everyone else can still be paired
+    /// without a rematch. With an odd number of entries, the lowest ranked entry which hasn't had
+    /// a bye yet receives one. If no such pairing is found within MAX_PAIRING_STEPS attempts,
+    /// entries are paired greedily, and those which already played everyone left to pair
+    /// receive a bye instead of a rematch.
+    ///
+    /// # Arguments
+    ///
+    /// * `scores` - Matches won, per entry
+    /// * `matches` - All the matches played so far
+    ///
+    /// # Returns
+    ///
+    /// * `pairs` - Pairs of entry indexes to play against each other
+    /// * `byes` - Entry indexes receiving a win without playing
+    pub fn swiss_pairings(scores: &Vec<u8>, matches: &Vec<TournamentMatch>) -> (Vec<(u8, u8)>, Vec<u8>) {
+        let has_played = |a: u8, b: u8| -> bool {
+            matches.iter().any(|m| (m.lhs == a && m.rhs == b) || (m.lhs == b && m.rhs == a))
+        };
+
+        let mut remaining: Vec<u8> = (0..scores.len()).map(|i| i as u8).collect();
+        remaining.sort_by(|&a, &b| scores[b as usize].cmp(&scores[a as usize]).then(a.cmp(&b)));
+
+        let mut byes: Vec<u8> = Vec::new();
+        if remaining.len() % 2 == 1 {
+            let position: usize = remaining
+                .iter()
+                .rposition(|&entry| !has_played(entry, entry))
+                .unwrap_or(remaining.len() - 1);
+            byes.push(remaining.remove(position));
+        }
+
+        let mut steps: u32 = MAX_PAIRING_STEPS;
+        if let Some(pairs) = pair_without_rematches(&remaining, &has_played, &mut steps) {
+            return (pairs, byes);
+        }
+
+        let mut pairs: Vec<(u8, u8)> = Vec::new();
+        while !remaining.is_empty() {
+            let lhs: u8 = remaining.remove(0);
+            match remaining.iter().position(|&rhs| !has_played(lhs, rhs)) {
+                Some(position) => pairs.push((lhs, remaining.remove(position))),
+                None => byes.push(lhs),
+            }
+        }
+
+        (pairs, byes)
+    }
+
+    /// Searches for a pairing of all the entries without rematches, trying the opponents
+    /// of every entry in order.
+    ///
+    /// # Arguments
+    ///
+    /// * `remaining` - Entry indexes to pair, in order
+    /// * `has_played` - Whether two entries have already played each other
+    /// * `steps` - Attempts left before giving up (mutable)
+    ///
+    /// # Returns
+    ///
+    /// * `pairs` - Pairs of entry indexes, or None if none was found
+    fn pair_without_rematches<F>(remaining: &[u8], has_played: &F, steps: &mut u32) -> Option<Vec<(u8, u8)>>
+        where F: Fn(u8, u8) -> bool {
+
+        if remaining.is_empty() {
+            return Some(Vec::new());
+        }
+
+        let lhs: u8 = remaining[0];
+        for &rhs in remaining[1..].iter() {
+            if has_played(lhs, rhs) {
+                continue;
+            }
+            if *steps == 0 {
+                return None;
+            }
+            *steps -= 1;
+
+            let rest: Vec<u8> = remaining[1..]
+                .iter()
+                .filter(|&&entry| entry != rhs)
+                .cloned()
+                .collect();
+            if let Some(mut pairs) = pair_without_rematches(&rest, has_played, steps) {
+                pairs.insert(0, (lhs, rhs));
+                return Some(pairs);
+            }
+        }
+
+        None
+    }
+
+    /// Records the results of a round: updates the scores and eliminations, and finishes
+    /// the tournament with the final standings if it was the last round.
+    ///
+    /// # Arguments
+    ///
+    /// * `tournament` - The tournament (mutable)
+    /// * `results` - The matches of the round, byes included
+    ///
+    /// # Returns
+    ///
+    /// * `finished` - Whether the tournament is finished
+    pub fn record_round(tournament: &mut Tournament, results: Vec<TournamentMatch>) -> bool {
+        for result in results.into_iter() {
+            let (winner, loser) = if result.lhs_won {
+                (result.lhs, result.rhs)
+            } else {
+                (result.rhs, result.lhs)
+            };
+
+            tournament.scores[winner as usize] += 1;
+            match tournament.format {
+                TournamentFormat::SingleElimination =>
+                    if winner != loser {
+                        tournament.eliminated[loser as usize] = true;
+                    },
+                _ => (),
+            }
+            tournament.matches.push(result);
+        }
+
+        tournament.round += 1;
+
+        let finished: bool = match tournament.format {
+            TournamentFormat::SingleElimination =>
+                tournament.eliminated.iter().filter(|&&eliminated| !eliminated).count() <= 1,
+            TournamentFormat::Swiss { rounds } => tournament.round >= rounds,
+        };
+
+        if finished {
+            let mut order: Vec<usize> = (0..tournament.entries.len()).collect();
+            order.sort_by(|&a, &b| tournament.scores[b].cmp(&tournament.scores[a]).then(a.cmp(&b)));
+
+            tournament.standings = order
+                .into_iter()
+                .map(|i| tournament.entries[i].player)
+                .collect();
+            tournament.state = TournamentState::Finished;
+        }
+
+        finished
+    }
+
+    /// Resolves the next round of a tournament: generates the pairings, fights the matches,
+    /// records the results, and calculates the prize payouts if it was the last round.
+    /// Byes are recorded as won matches of an entry against itself, without fighting.
+    ///
+    /// # Arguments
+    ///
+    /// * `tournament` - The tournament (mutable)
+    /// * `fight` - Fights a match, given the index of the match in the round and the entries
+    ///     of the attacker and the defender, returning whether the attacker won and the fight hash
+    ///
+    /// # Returns
+    ///
+    /// * `matches` - The matches of the round, byes included
+    /// * `payouts` - Prize payouts, if the tournament finished with this round, empty otherwise
+    pub fn resolve_round<F>(tournament: &mut Tournament, mut fight: F)
+        -> (Vec<TournamentMatch>, Vec<(AccountId, Balance)>)
+        where F: FnMut(usize, &TournamentEntry, &TournamentEntry) -> (bool, [u8; 32]) {
+
+        let (pairs, byes) = match tournament.format {
+            TournamentFormat::SingleElimination =>
+                elimination_pairings(&tournament.eliminated, tournament.round),
+            TournamentFormat::Swiss { rounds: _ } =>
+                swiss_pairings(&tournament.scores, &tournament.matches),
+        };
+        let mut results: Vec<TournamentMatch> = Vec::new();
+
+        for bye in byes.into_iter() {
+            results.push(TournamentMatch {
+                round: tournament.round,
+                lhs: bye,
+                rhs: bye,
+                lhs_won: true,
+                hash: [0; 32],
+            });
+        }
+
+        for (index, (lhs, rhs)) in pairs.into_iter().enumerate() {
+            let (lhs_won, hash) = fight(index, &tournament.entries[lhs as usize],
+                &tournament.entries[rhs as usize]);
+
+            results.push(TournamentMatch {
+                round: tournament.round,
+                lhs,
+                rhs,
+                lhs_won,
+                hash,
+            });
+        }
+
+        let matches: Vec<TournamentMatch> = results.clone();
+        let payouts: Vec<(AccountId, Balance)> = if record_round(tournament, results) {
+            prize_payouts(tournament.prize_pool, &tournament.prize_shares, &tournament.standings)
+        } else {
+            Vec::new()
+        };
+
+        (matches, payouts)
+    }
+
+    /// Calculates the prize payouts of a finished tournament.
+    /// Shares for standings without a player are not paid out, the remainder left by rounding
+    /// goes to first place.
+    ///
+    /// # Arguments
+    ///
+    /// * `prize_pool` - The prize pool
+    /// * `prize_shares` - Percentages of the prize pool paid out, by final standing
+    /// * `standings` - The final standings
+    ///
+    /// # Returns
+    ///
+    /// * `payouts` - A Vec containing a tuple of (player account id, prize)
+    pub fn prize_payouts(prize_pool: Balance, prize_shares: &Vec<u8>,
+        standings: &Vec<AccountId>) -> Vec<(AccountId, Balance)> {
+
+        let mut payouts: Vec<(AccountId, Balance)> = prize_shares
+            .iter()
+            .zip(standings.iter())
+            .filter(|&(&share, _)| share > 0)
+            .map(|(&share, &player)| (player, prize_pool * share as Balance / 100))
+            .collect();
+
+        let paid_shares: Balance = prize_shares
+            .iter()
+            .take(standings.len())
+            .map(|&share| share as Balance)
+            .sum();
+        let paid: Balance = payouts.iter().map(|&(_, prize)| prize).sum();
+        let remainder: Balance = prize_pool * paid_shares / 100 - paid;
+        if remainder > 0 && !standings.is_empty() {
+            match payouts.iter_mut().find(|(player, _)| *player == standings[0]) {
+                Some(payout) => payout.1 += remainder,
+                None => payouts.insert(0, (standings[0], remainder)),
+            }
+        }
+
+        payouts
+    }
+
+    #[ink(storage)]
+    pub struct NewOmegaTournament {
+        owner: AccountId,
+        new_omega_game: NewOmegaGame,
+        tournaments: StorageHashMap<u32, Tournament>,
+        next_tournament_id: u32,
+    }
+
+    impl NewOmegaTournament {
+        #[ink(constructor)]
+        pub fn new(new_omega_game: NewOmegaGame) -> Self {
+            Self {
+                owner: Self::env().caller(),
+                new_omega_game,
+                tournaments: StorageHashMap::default(),
+                next_tournament_id: 0,
+            }
+        }
+
+        /// Creates a tournament, open for signups.
+        ///
+        /// # Arguments
+        ///
+        /// * `format` - Bracket format
+        /// * `entry_fee` - Entry fee, paid at signup
+        /// * `max_entries` - Maximum number of entries
+        /// * `prize_shares` - Percentages of the prize pool paid out, by final standing
+        ///
+        /// # Returns
+        ///
+        /// * `id` - Id of the created tournament
+        #[ink(message)]
+        pub fn create_tournament(&mut self, format: TournamentFormat, entry_fee: Balance,
+            max_entries: u8, prize_shares: Vec<u8>) -> u32 {
+
+            assert_eq!(self.env().caller(), self.owner);
+            assert!(max_entries >= 2 && max_entries <= MAX_ENTRIES);
+            assert!(prize_shares.iter().map(|&share| share as u32).sum::<u32>() <= 100);
+            match format {
+                TournamentFormat::Swiss { rounds } => assert!(rounds > 0),
+                _ => (),
+            }
+
+            let id: u32 = self.next_tournament_id;
+            self.tournaments.insert(id, Tournament {
+                format,
+                state: TournamentState::Signup,
+                entry_fee,
+                max_entries,
+                prize_shares,
+                prize_pool: 0,
+                entries: Vec::new(),
+                round: 0,
+                scores: Vec::new(),
+                eliminated: Vec::new(),
+                matches: Vec::new(),
+                standings: Vec::new(),
+            });
+            self.next_tournament_id = id + 1;
+
+            id
+        }
+
+        /// Signs a player up for a tournament. The fleet, including the commander level and skills,
+        /// is locked for the whole tournament. The entry fee is expected to be collected by the caller.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the tournament
+        /// * `caller` - Account id of the player signing up
+        /// * `selection` - Fleet composition (array with ship quantities)
+        /// * `variants` - An array that holds variants of the fleet
+        /// * `commander` - Index of the commander leading the fleet
+        /// * `commander_level` - Level of the commander leading the fleet
+        /// * `commander_skills` - Selected skills of the commander leading the fleet
+        /// * `reserve` - The reserve of the fleet
+        #[ink(message)]
+        pub fn sign_up(&mut self, id: u32, caller: AccountId, selection: [u8; MAX_SHIPS],
+            variants: [u8; MAX_SHIPS], commander: u8, commander_level: u8, commander_skills: u32,
+            reserve: Reserve) {
+
+            assert_eq!(self.env().caller(), self.owner);
+            assert!(self.tournaments.get(&id).is_some());
+            assert!(is_reserve_valid(selection, reserve));
+
+            let tournament: &mut Tournament = self.tournaments.get_mut(&id).unwrap();
+
+            assert_eq!(tournament.state, TournamentState::Signup);
+            assert!(tournament.entries.len() < tournament.max_entries as usize);
+            assert!(tournament.entries.iter().all(|entry| entry.player != caller));
+
+            tournament.entries.push(TournamentEntry {
+                player: caller,
+                selection,
+                variants,
+                commander,
+                commander_level,
+                commander_skills,
+                reserve,
+            });
+            tournament.scores.push(0);
+            tournament.eliminated.push(false);
+            tournament.prize_pool += tournament.entry_fee;
+        }
+
+        /// Closes the signups and starts a tournament.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the tournament
+        #[ink(message)]
+        pub fn start_tournament(&mut self, id: u32) {
+            assert_eq!(self.env().caller(), self.owner);
+            assert!(self.tournaments.get(&id).is_some());
+
+            let tournament: &mut Tournament = self.tournaments.get_mut(&id).unwrap();
+
+            assert_eq!(tournament.state, TournamentState::Signup);
+            assert!(tournament.entries.len() >= 2);
+            tournament.state = TournamentState::Running;
+        }
+
+        /// Cancels a tournament during signup.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the tournament
+        ///
+        /// # Returns
+        ///
+        /// * `refunds` - A Vec containing a tuple of (player account id, entry fee to refund)
+        #[ink(message)]
+        pub fn cancel_tournament(&mut self, id: u32) -> Vec<(AccountId, Balance)> {
+            assert_eq!(self.env().caller(), self.owner);
+            assert!(self.tournaments.get(&id).is_some());
+
+            let tournament: &mut Tournament = self.tournaments.get_mut(&id).unwrap();
+
+            assert_eq!(tournament.state, TournamentState::Signup);
+            tournament.state = TournamentState::Cancelled;
+            tournament.prize_pool = 0;
+
+            let entry_fee: Balance = tournament.entry_fee;
+            tournament.entries
+                .iter()
+                .map(|entry| (entry.player, entry_fee))
+                .collect()
+        }
+
+        /// Plays the next round of a tournament. Each match is fought with the entry listed first
+        /// as the attacker, who has to destroy or rout the defender to win.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the tournament
+        ///
+        /// # Returns
+        ///
+        /// * `matches` - The matches of the round, byes included
+        /// * `payouts` - Prize payouts, if the tournament finished with this round, empty otherwise
+        #[ink(message)]
+        pub fn play_round(&mut self, id: u32) -> (Vec<TournamentMatch>, Vec<(AccountId, Balance)>) {
+            assert_eq!(self.env().caller(), self.owner);
+            assert!(self.tournaments.get(&id).is_some());
+
+            let mut tournament: Tournament = self.tournaments.get(&id).unwrap().clone();
+
+            assert_eq!(tournament.state, TournamentState::Running);
+
+            // Determine the seed, in a naive way -> IMPROVEME: MOVE TO VRF
+            let seed: u64 = self.env().block_timestamp();
+            let new_omega_game: &NewOmegaGame = &self.new_omega_game;
+
+            let (matches, payouts) = resolve_round(&mut tournament, |index, entry_lhs, entry_rhs| {
+                let match_seed: u64 = seed.wrapping_add(index as u64);
+                let map: u8 = new_omega_game.pick_map(match_seed);
+                let (result, _lhs_moves, _rhs_moves): (FightResult, _, _) =
+                    new_omega_game.fight(
+                        match_seed,
+                        false,
+                        false,
+                        entry_lhs.selection,
+                        entry_rhs.selection,
+                        entry_lhs.variants,
+                        entry_rhs.variants,
+                        entry_lhs.commander,
+                        entry_rhs.commander,
+                        entry_lhs.commander_level,
+                        entry_rhs.commander_level,
+                        entry_lhs.commander_skills,
+                        entry_rhs.commander_skills,
+                        entry_lhs.reserve,
+                        entry_rhs.reserve,
+                        map);
+
+                (result.rhs_dead || result.rhs_retreated, result.hash)
+            });
+
+            self.tournaments.insert(id, tournament);
+
+            (matches, payouts)
+        }
+
+        /// Gets a tournament.
+        /// Will panic if the tournament does not exist.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the tournament
+        ///
+        /// # Returns
+        ///
+        /// * `tournament` - The tournament
+        #[ink(message)]
+        pub fn get_tournament(&self, id: u32) -> Tournament {
+            assert!(self.tournaments.get(&id).is_some());
+            self.tournaments.get(&id).unwrap().clone()
+        }
+
+        /// Gets all the tournaments, with their ids.
+        ///
+        /// # Returns
+        ///
+        /// * `tournaments` - A Vec containing a tuple of (tournament id, tournament)
+        #[ink(message)]
+        pub fn get_tournaments(&self) -> Vec<(u32, Tournament)> {
+            self.tournaments
+                .iter()
+                .map(|(&id, tournament)| (id, tournament.clone()))
+                .collect()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use ink_env::{
+            call::FromAccountId,
+            test,
+        };
+        use ink_lang as ink;
+        type Accounts = test::DefaultAccounts<Environment>;
+
+        fn default_accounts() -> Accounts {
+            test::default_accounts()
+                .expect("Test environment is expected to be initialized.")
+        }
+
+        fn new_contract() -> NewOmegaTournament {
+            NewOmegaTournament::new(NewOmegaGame::from_account_id(default_accounts().django))
+        }
+
+        fn sign_up(contract: &mut NewOmegaTournament, id: u32, players: &Vec<AccountId>) {
+            for player in players.iter() {
+                contract.sign_up(id, *player, [1, 1, 1, 1], [0, 0, 0, 0], 0, 1, 0, Reserve::default());
+            }
+        }
+
+        /// Resolves a round without fighting, the player with the lower account id wins
+        fn play_round_offchain(tournament: &mut Tournament) -> bool {
+            resolve_round(tournament, |_, lhs, rhs| (lhs.player < rhs.player, [0; 32]));
+
+            tournament.state == TournamentState::Finished
+        }
+
+        #[ink::test]
+        fn test_signup_and_cancel() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+            let players: Vec<AccountId> = [accounts.alice, accounts.bob, accounts.charlie].to_vec();
+
+            let id: u32 = contract.create_tournament(TournamentFormat::SingleElimination, 10, 4,
+                [70, 30].to_vec());
+            sign_up(&mut contract, id, &players);
+
+            let tournament: Tournament = contract.get_tournament(id);
+            assert_eq!(tournament.state, TournamentState::Signup);
+            assert_eq!(tournament.entries.len(), 3);
+            assert_eq!(tournament.prize_pool, 30);
+
+            let refunds: Vec<(AccountId, Balance)> = contract.cancel_tournament(id);
+            assert_eq!(refunds, players.iter().map(|&player| (player, 10)).collect::<Vec<_>>());
+            assert_eq!(contract.get_tournament(id).state, TournamentState::Cancelled);
+            assert_eq!(contract.get_tournaments().len(), 1);
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_signup_twice() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+
+            let id: u32 = contract.create_tournament(TournamentFormat::SingleElimination, 0, 4, Vec::new());
+            sign_up(&mut contract, id, &[accounts.alice, accounts.alice].to_vec());
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_signup_full() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+
+            let id: u32 = contract.create_tournament(TournamentFormat::SingleElimination, 0, 2, Vec::new());
+            sign_up(&mut contract, id, &[accounts.alice, accounts.bob, accounts.charlie].to_vec());
+        }
+
+        #[ink::test]
+        fn test_single_elimination() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+            let players: Vec<AccountId> = [accounts.alice, accounts.bob, accounts.charlie,
+                accounts.django, accounts.eve].to_vec();
+
+            let id: u32 = contract.create_tournament(TournamentFormat::SingleElimination, 10, 8,
+                [70, 30].to_vec());
+            sign_up(&mut contract, id, &players);
+            contract.start_tournament(id);
+
+            let mut tournament: Tournament = contract.get_tournament(id);
+            assert_eq!(tournament.state, TournamentState::Running);
+
+            // 5 entries fill a bracket of 8, the first 3 get byes
+            let (pairs, byes) = elimination_pairings(&tournament.eliminated, 0);
+            assert_eq!(byes, [0, 1, 2].to_vec());
+            assert_eq!(pairs, [(3, 4)].to_vec());
+
+            assert!(!play_round_offchain(&mut tournament));
+            assert_eq!(tournament.eliminated, [false, false, false, false, true].to_vec());
+
+            // No more byes after the first round
+            let (pairs, byes) = elimination_pairings(&tournament.eliminated, 1);
+            assert_eq!(byes.len(), 0);
+            assert_eq!(pairs, [(0, 1), (2, 3)].to_vec());
+
+            assert!(!play_round_offchain(&mut tournament));
+            assert!(play_round_offchain(&mut tournament));
+            assert_eq!(tournament.state, TournamentState::Finished);
+            assert_eq!(tournament.round, 3);
+            assert_eq!(tournament.standings[0], accounts.alice);
+            assert_eq!(tournament.standings[1], accounts.charlie);
+
+            assert_eq!(prize_payouts(tournament.prize_pool, &tournament.prize_shares, &tournament.standings),
+                [(accounts.alice, 35), (accounts.charlie, 15)].to_vec());
+        }
+
+        #[ink::test]
+        fn test_swiss() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+            let players: Vec<AccountId> = [accounts.alice, accounts.bob, accounts.charlie,
+                accounts.django, accounts.eve].to_vec();
+
+            let id: u32 = contract.create_tournament(TournamentFormat::Swiss { rounds: 3 }, 0, 8,
+                [100].to_vec());
+            sign_up(&mut contract, id, &players);
+            contract.start_tournament(id);
+
+            let mut tournament: Tournament = contract.get_tournament(id);
+
+            // The lowest ranked entry gets the bye
+            let (pairs, byes) = swiss_pairings(&tournament.scores, &tournament.matches);
+            assert_eq!(byes, [4].to_vec());
+            assert_eq!(pairs, [(0, 1), (2, 3)].to_vec());
+
+            assert!(!play_round_offchain(&mut tournament));
+            assert_eq!(tournament.scores, [1, 0, 1, 0, 1].to_vec());
+
+            // Nobody plays the same opponent twice, or gets a second bye, when avoidable
+            let (pairs, byes) = swiss_pairings(&tournament.scores, &tournament.matches);
+            assert_eq!(byes, [3].to_vec());
+            assert_eq!(pairs, [(0, 2), (4, 1)].to_vec());
+
+            assert!(!play_round_offchain(&mut tournament));
+
+            // Greedy pairing would leave 1 and 4 to a rematch, 0 takes 4 instead
+            let (pairs, byes) = swiss_pairings(&tournament.scores, &tournament.matches);
+            assert_eq!(byes, [2].to_vec());
+            assert_eq!(pairs, [(0, 4), (1, 3)].to_vec());
+
+            assert!(play_round_offchain(&mut tournament));
+            assert_eq!(tournament.state, TournamentState::Finished);
+            assert_eq!(tournament.matches.iter().filter(|m| m.lhs == m.rhs).count(), 3);
+            assert_eq!(tournament.standings[0], accounts.alice);
+            assert_eq!(tournament.standings.len(), 5);
+        }
+
+        #[ink::test]
+        fn test_prize_payouts() {
+            let accounts = default_accounts();
+            let standings: Vec<AccountId> = [accounts.alice, accounts.bob, accounts.charlie].to_vec();
+
+            // 24.5 and 10.5, the rounding remainder goes to first place
+            assert_eq!(prize_payouts(35, &[70, 30].to_vec(), &standings),
+                [(accounts.alice, 25), (accounts.bob, 10)].to_vec());
+            assert_eq!(prize_payouts(10, &[33, 33, 34].to_vec(), &standings),
+                [(accounts.alice, 4), (accounts.bob, 3), (accounts.charlie, 3)].to_vec());
+            // Shares for standings without a player are not paid out
+            assert_eq!(prize_payouts(35, &[70, 30].to_vec(), &[accounts.alice].to_vec()),
+                [(accounts.alice, 24)].to_vec());
+        }
+
+        #[ink::test]
+        fn test_swiss_no_rematch() {
+            let played = |lhs: u8, rhs: u8| TournamentMatch {
+                round: 0,
+                lhs,
+                rhs,
+                lhs_won: true,
+                hash: [0; 32],
+            };
+            // Entry 0 has already played everyone else, so no pairing avoids a rematch
+            let matches: Vec<TournamentMatch> = [played(0, 1), played(0, 2), played(0, 3)].to_vec();
+            let scores: Vec<u8> = [3, 0, 0, 0].to_vec();
+
+            let (pairs, byes) = swiss_pairings(&scores, &matches);
+            assert_eq!(pairs, [(1, 2)].to_vec());
+            assert_eq!(byes, [0, 3].to_vec());
+            assert!(pairs.iter().all(|&(lhs, rhs)| !matches.iter().any(|m|
+                (m.lhs == lhs && m.rhs == rhs) || (m.lhs == rhs && m.rhs == lhs))));
+        }
+    }
+}
@@ -0,0 +1,869 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use ink_lang as ink;
+pub use self::newomegawar::NewOmegaWar;
+pub use self::newomegawar::War;
+pub use self::newomegawar::WarTeam;
+pub use self::newomegawar::WarDefence;
+pub use self::newomegawar::WarAttack;
+pub use self::newomegawar::WarState;
+
+/// Scheduled wars between two guilds. During the preparation, members of both guilds
+/// nominate their defences; during the battle window, members attack the defences
+/// of the opposing guild, earning stars. The guild with more stars wins the war reward.
+/// The rosters of both guilds are taken when the war is scheduled, so joining or leaving
+/// a guild afterwards does not change the teams.
+/// Connected to Fight Management in order to run fights, and to Storage in order to
+/// look up and award commander xp.
+/// This contract does not deal with payments, it only computes the payouts,
+/// leaving the transfers to the Delegator.
+#[ink::contract]
+mod newomegawar {
+    use newomegagame::NewOmegaGame;
+    use newomegastorage::NewOmegaStorage;
+    use newomega::MAX_SHIPS;
+    use newomega::Reserve;
+    use newomega::is_reserve_valid;
+    use newomega::FightResult;
+    use ink_prelude::vec::Vec;
+    use ink_env::hash::Blake2x256;
+    use ink_storage::{
+        collections::{
+            HashMap as StorageHashMap,
+        },
+        traits::{
+            PackedLayout,
+            SpreadLayout,
+        },
+    };
+
+    const MAX_WAR_SLOTS: u8 = 20;
+    /// Attacks each member can make during a war
+    const MAX_WAR_ATTACKS: u8 = 2;
+    const XP_PER_WAR_STAR: u32 = 1;
+
+    /// Describes the phase a war is in
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Copy, Clone,
+        Debug, Eq, PartialEq)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub enum WarState {
+        /// Before the battle window, defences are being nominated
+        Preparation,
+        /// Within the battle window, defences are being attacked
+        Battle,
+        /// After the battle window, waiting to be finished
+        Ended,
+        /// Finished, reward paid out
+        Finished,
+    }
+
+    /// Describes a defence nominated for a war
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            Debug,
+            PartialEq,
+            Eq,
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub struct WarDefence {
+        /// Account id of the defending member
+        pub player: AccountId,
+        /// Fleet composition
+        pub selection: [u8; MAX_SHIPS],
+        /// Fleet variants (fittings)
+        pub variants: [u8; MAX_SHIPS],
+        /// Commander index
+        pub commander: u8,
+        /// Reserve group, entering the battle later
+        pub reserve: Reserve,
+        /// Most stars any attack earned against this defence
+        pub stars: u8,
+    }
+
+    /// Describes one of the two guilds fighting a war
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            Debug,
+            PartialEq,
+            Eq,
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub struct WarTeam {
+        /// Id of the guild
+        pub guild: u32,
+        /// Members of the guild when the war was scheduled
+        pub members: Vec<AccountId>,
+        /// Nominated defences
+        pub defences: Vec<WarDefence>,
+        /// Stars earned, counting only the best attack on each opposing defence
+        pub stars: u32,
+    }
+
+    /// Describes an attack made during a war
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            Debug,
+            PartialEq,
+            Eq,
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub struct WarAttack {
+        /// Account id of the attacking member
+        pub attacker: AccountId,
+        /// Index of the attacking team
+        pub team: u8,
+        /// Index of the attacked defence, in the opposing team
+        pub defence: u8,
+        /// Stars earned
+        pub stars: u8,
+        /// Commitment hash of the fight
+        pub hash: [u8; 32],
+    }
+
+    /// Describes a war between two guilds
+    #[derive(scale::Encode, scale::Decode, SpreadLayout, PackedLayout, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(
+            Debug,
+            PartialEq,
+            Eq,
+            scale_info::TypeInfo,
+            ink_storage::traits::StorageLayout
+        )
+    )]
+    pub struct War {
+        /// The two fighting guilds
+        pub teams: Vec<WarTeam>,
+        /// Number of defences each guild can nominate
+        pub slots: u8,
+        /// Block the battle window opens in
+        pub start: BlockNumber,
+        /// Block the battle window closes in
+        pub end: BlockNumber,
+        /// Reward, split among the members of the winning guild who attacked
+        pub reward: Balance,
+        /// Attacks made, in order
+        pub attacks: Vec<WarAttack>,
+        /// Whether the war was finished and the reward paid out
+        pub finished: bool,
+        /// Id of the winning guild, None for a draw or an unfinished war
+        pub winner: Option<u32>,
+        /// Seed drawn when the war was scheduled, the seeds of the attacks are derived from it
+        pub seed: u64,
+    }
+
+    /// Gets the phase of a war.
+    ///
+    /// # Arguments
+    ///
+    /// * `war` - The war
+    /// * `block` - The current block
+    ///
+    /// # Returns
+    ///
+    /// * `state` - The phase of the war
+    pub fn war_state(war: &War, block: BlockNumber) -> WarState {
+        if war.finished {
+            WarState::Finished
+        } else if block < war.start {
+            WarState::Preparation
+        } else if block < war.end {
+            WarState::Battle
+        } else {
+            WarState::Ended
+        }
+    }
+
+    /// Gets the index of the team of a player in a war, from the rosters taken when the war was scheduled.
+    ///
+    /// # Arguments
+    ///
+    /// * `war` - The war
+    /// * `player` - The account id of the player
+    ///
+    /// # Returns
+    ///
+    /// * `team` - Index of the team, None if the player is in neither roster
+    pub fn war_team(war: &War, player: AccountId) -> Option<u8> {
+        war.teams
+            .iter()
+            .position(|team| team.members.contains(&player))
+            .map(|team| team as u8)
+    }
+
+    /// Derives the seed of an attack from the seed of the war, the attacker, the number of attacks
+    /// they already made and the attacked defence. The seed does not depend on the block the attack
+    /// is made in, so the attacker cannot wait for a block with a better one.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Id of the war
+    /// * `war` - The war
+    /// * `attacker` - The account id of the attacker
+    /// * `defence` - Index of the attacked defence
+    ///
+    /// # Returns
+    ///
+    /// * `seed` - Seed of the fight
+    pub fn war_attack_seed(id: u32, war: &War, attacker: AccountId, defence: u8) -> u64 {
+        let attacks: u8 = war.attacks.iter().filter(|attack| attack.attacker == attacker).count() as u8;
+        let mut output: [u8; 32] = [0; 32];
+        ink_env::hash_encoded::<Blake2x256, _>(&(id, war.seed, attacker, attacks, defence), &mut output);
+        let mut seed: [u8; 8] = [0; 8];
+        seed.copy_from_slice(&output[0..8]);
+
+        u64::from_le_bytes(seed)
+    }
+
+    /// Checks an attack on a war before it is fought: the war has to be within the battle window,
+    /// the attacker in one of the teams with attacks left, and the defence nominated by the other team.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Id of the war
+    /// * `war` - The war
+    /// * `block` - The current block
+    /// * `attacker` - The account id of the attacker
+    /// * `defence` - Index of the attacked defence
+    ///
+    /// # Returns
+    ///
+    /// * `team` - Index of the attacking team
+    /// * `target` - The attacked defence
+    /// * `seed` - Seed of the fight
+    pub fn prepare_war_attack(id: u32, war: &War, block: BlockNumber, attacker: AccountId,
+        defence: u8) -> (u8, WarDefence, u64) {
+
+        assert_eq!(war_state(war, block), WarState::Battle);
+
+        let team: Option<u8> = war_team(war, attacker);
+        assert!(team.is_some());
+        let team: u8 = team.unwrap();
+        assert!(war.attacks.iter().filter(|attack| attack.attacker == attacker).count() <
+            MAX_WAR_ATTACKS as usize);
+        assert!((defence as usize) < war.teams[1 - team as usize].defences.len());
+
+        let target: WarDefence = war.teams[1 - team as usize].defences[defence as usize].clone();
+        (team, target, war_attack_seed(id, war, attacker, defence))
+    }
+
+    /// Calculates the stars earned by an attack: 3 for destroying the defence, 2 for routing it,
+    /// 1 if both fleets survived and none if the attacker was destroyed or routed.
+    ///
+    /// # Arguments
+    ///
+    /// * `lhs_lost` - Whether the attacker was destroyed or routed
+    /// * `rhs_dead` - Whether the defence was destroyed
+    /// * `rhs_retreated` - Whether the defence was routed
+    ///
+    /// # Returns
+    ///
+    /// * `stars` - The stars earned
+    pub fn war_stars(lhs_lost: bool, rhs_dead: bool, rhs_retreated: bool) -> u8 {
+        if lhs_lost {
+            0
+        } else if rhs_dead {
+            3
+        } else if rhs_retreated {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Records an attack in a war. Only improving on the best attack against a defence
+    /// earns the attacking team more stars.
+    ///
+    /// # Arguments
+    ///
+    /// * `war` - The war
+    /// * `attack` - The attack
+    pub fn record_war_attack(war: &mut War, attack: WarAttack) {
+        let defending: usize = 1 - attack.team as usize;
+        let defence: &mut WarDefence = &mut war.teams[defending].defences[attack.defence as usize];
+
+        if attack.stars > defence.stars {
+            let gained: u8 = attack.stars - defence.stars;
+            defence.stars = attack.stars;
+            war.teams[attack.team as usize].stars += gained as u32;
+        }
+
+        war.attacks.push(attack);
+    }
+
+    /// Determines the winner of a war, and splits the reward equally among the members of the winning
+    /// guild who attacked at least once. In a draw, the reward is split among the attackers of both guilds.
+    /// Whatever is not paid out (the whole reward if nobody attacked, the remainder of the split
+    /// otherwise) is refunded.
+    ///
+    /// # Arguments
+    ///
+    /// * `war` - The war
+    ///
+    /// # Returns
+    ///
+    /// * `winner` - Id of the winning guild, None for a draw
+    /// * `payouts` - A Vec containing a tuple of (player account id, amount to pay out)
+    /// * `refund` - Amount of the reward to refund
+    pub fn war_payouts(war: &War) -> (Option<u32>, Vec<(AccountId, Balance)>, Balance) {
+        let winning_team: Option<u8> = if war.teams[0].stars > war.teams[1].stars {
+            Some(0)
+        } else if war.teams[1].stars > war.teams[0].stars {
+            Some(1)
+        } else {
+            None
+        };
+
+        let mut participants: Vec<AccountId> = Vec::new();
+        for attack in war.attacks.iter() {
+            if winning_team.map_or(true, |team| team == attack.team) && !participants.contains(&attack.attacker) {
+                participants.push(attack.attacker);
+            }
+        }
+
+        let share: Balance = if participants.is_empty() {
+            0
+        } else {
+            war.reward / participants.len() as Balance
+        };
+        let payouts: Vec<(AccountId, Balance)> = participants
+            .into_iter()
+            .map(|player| (player, share))
+            .filter(|&(_, amount)| amount > 0)
+            .collect();
+        let refund: Balance = war.reward - payouts.iter().map(|&(_, amount)| amount).sum::<Balance>();
+
+        (winning_team.map(|team| war.teams[team as usize].guild), payouts, refund)
+    }
+
+    #[ink(storage)]
+    pub struct NewOmegaWar {
+        owner: AccountId,
+        new_omega_game: newomegagame::NewOmegaGame,
+        new_omega_storage: newomegastorage::NewOmegaStorage,
+        wars: StorageHashMap<u32, War>,
+        next_war_id: u32,
+    }
+
+    impl NewOmegaWar {
+        #[ink(constructor)]
+        pub fn new(new_omega_game: NewOmegaGame, new_omega_storage: NewOmegaStorage) -> Self {
+            Self {
+                owner: Self::env().caller(),
+                new_omega_game,
+                new_omega_storage,
+                wars: StorageHashMap::default(),
+                next_war_id: 0,
+            }
+        }
+
+        /// Schedules a war between two guilds, taking the rosters of both.
+        /// The reward is expected to be collected by the caller.
+        ///
+        /// # Arguments
+        ///
+        /// * `guild_lhs` - Id of the first guild
+        /// * `members_lhs` - Current members of the first guild
+        /// * `guild_rhs` - Id of the second guild
+        /// * `members_rhs` - Current members of the second guild
+        /// * `slots` - Number of defences each guild can nominate
+        /// * `start` - Block the battle window opens in
+        /// * `end` - Block the battle window closes in
+        /// * `reward` - Reward for the winning guild
+        ///
+        /// # Returns
+        ///
+        /// * `id` - Id of the scheduled war
+        #[ink(message)]
+        pub fn schedule_war(&mut self, guild_lhs: u32, members_lhs: Vec<AccountId>, guild_rhs: u32,
+            members_rhs: Vec<AccountId>, slots: u8, start: BlockNumber, end: BlockNumber,
+            reward: Balance) -> u32 {
+
+            assert_eq!(self.env().caller(), self.owner);
+            assert!(guild_lhs != guild_rhs);
+            assert!(members_lhs.iter().all(|member| !members_rhs.contains(member)));
+            assert!(slots > 0 && slots <= MAX_WAR_SLOTS);
+            assert!(start > self.env().block_number() && end > start);
+
+            let id: u32 = self.next_war_id;
+            self.wars.insert(id, War {
+                teams: [(guild_lhs, members_lhs), (guild_rhs, members_rhs)]
+                    .to_vec()
+                    .into_iter()
+                    .map(|(guild, members)| WarTeam {
+                        guild,
+                        members,
+                        defences: Vec::new(),
+                        stars: 0,
+                    })
+                    .collect(),
+                slots,
+                start,
+                end,
+                reward,
+                attacks: Vec::new(),
+                finished: false,
+                winner: None,
+                // Drawn before any defence is nominated, in a naive way -> IMPROVEME: MOVE TO VRF
+                seed: self.env().block_timestamp(),
+            });
+            self.next_war_id = id + 1;
+
+            id
+        }
+
+        /// Nominates the defence of a member for a war, during the preparation.
+        /// Replaces the previous nomination of the member.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the member
+        /// * `id` - Id of the war
+        /// * `selection` - The fleet composition of the defence
+        /// * `variants` - The variants (fittings) of the defence
+        /// * `commander` - Index of the commander leading the defence
+        /// * `reserve` - The reserve of the defence
+        #[ink(message)]
+        pub fn nominate_defence(&mut self, caller: AccountId, id: u32, selection: [u8; MAX_SHIPS],
+            variants: [u8; MAX_SHIPS], commander: u8, reserve: Reserve) {
+
+            assert_eq!(self.env().caller(), self.owner);
+            assert!(self.wars.get(&id).is_some());
+            assert!(is_reserve_valid(selection, reserve));
+
+            let mut war: War = self.wars.get(&id).unwrap().clone();
+            assert_eq!(war_state(&war, self.env().block_number()), WarState::Preparation);
+
+            let team: Option<u8> = war_team(&war, caller);
+            assert!(team.is_some());
+            let team: u8 = team.unwrap();
+            let slots: usize = war.slots as usize;
+            let defences: &mut Vec<WarDefence> = &mut war.teams[team as usize].defences;
+            let defence: WarDefence = WarDefence {
+                player: caller,
+                selection,
+                variants,
+                commander,
+                reserve,
+                stars: 0,
+            };
+
+            match defences.iter_mut().find(|nominated| nominated.player == caller) {
+                Some(nominated) => *nominated = defence,
+                _ => {
+                    assert!(defences.len() < slots);
+                    defences.push(defence);
+                },
+            }
+
+            self.wars.insert(id, war);
+        }
+
+        /// Attacks a defence of the opposing guild, during the battle window.
+        ///
+        /// # Arguments
+        ///
+        /// * `caller` - The account id of the attacking member
+        /// * `id` - Id of the war
+        /// * `defence` - Index of the attacked defence
+        /// * `selection` - Attacker fleet composition (array with ship quantities)
+        /// * `variants` - An array that holds variants of the attacker fleet
+        /// * `commander` - The attacker commander
+        /// * `reserve` - The attacker reserve
+        ///
+        /// # Returns
+        ///
+        /// * `stars` - The stars earned by the attack
+        /// * `result` - The fight result
+        #[ink(message)]
+        pub fn attack(&mut self, caller: AccountId, id: u32, defence: u8, selection: [u8; MAX_SHIPS],
+            variants: [u8; MAX_SHIPS], commander: u8, reserve: Reserve) -> (u8, FightResult) {
+
+            assert_eq!(self.env().caller(), self.owner);
+            assert!(self.wars.get(&id).is_some());
+
+            let mut war: War = self.wars.get(&id).unwrap().clone();
+            let (team, target, seed) = prepare_war_attack(id, &war, self.env().block_number(), caller, defence);
+            let map: u8 = self.new_omega_game.pick_map(seed);
+            let (result, _lhs_moves, _rhs_moves): (FightResult, _, _) =
+                self.new_omega_game.fight(
+                    seed,
+                    false,
+                    true,
+                    selection,
+                    target.selection,
+                    variants,
+                    target.variants,
+                    commander,
+                    target.commander,
+                    self.new_omega_storage.get_commander_level(caller, commander),
+                    self.new_omega_storage.get_commander_level(target.player, target.commander),
+                    self.new_omega_storage.get_commander_skills(caller, commander),
+                    self.new_omega_storage.get_commander_skills(target.player, target.commander),
+                    reserve,
+                    target.reserve,
+                    map);
+
+            let stars: u8 = war_stars(result.lhs_dead || result.lhs_retreated, result.rhs_dead,
+                result.rhs_retreated);
+            record_war_attack(&mut war, WarAttack {
+                attacker: caller,
+                team,
+                defence,
+                stars,
+                hash: result.hash,
+            });
+            self.wars.insert(id, war);
+
+            if stars > 0 {
+                self.new_omega_storage.add_commander_xp(caller, commander, stars as u32 * XP_PER_WAR_STAR);
+            }
+
+            (stars, result)
+        }
+
+        /// Finishes a war after the battle window, determining the winner.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the war
+        ///
+        /// # Returns
+        ///
+        /// * `winner` - Id of the winning guild, None for a draw
+        /// * `payouts` - A Vec containing a tuple of (player account id, amount to pay out)
+        /// * `refund` - Amount of the reward left unpaid, to refund
+        #[ink(message)]
+        pub fn finish_war(&mut self, id: u32) -> (Option<u32>, Vec<(AccountId, Balance)>, Balance) {
+            assert_eq!(self.env().caller(), self.owner);
+            assert!(self.wars.get(&id).is_some());
+
+            let block: BlockNumber = self.env().block_number();
+            let war: &mut War = self.wars.get_mut(&id).unwrap();
+            assert_eq!(war_state(war, block), WarState::Ended);
+
+            let (winner, payouts, refund) = war_payouts(war);
+            war.finished = true;
+            war.winner = winner;
+
+            (winner, payouts, refund)
+        }
+
+        /// Gets a war.
+        /// Will panic if the war does not exist.
+        ///
+        /// # Arguments
+        ///
+        /// * `id` - Id of the war
+        ///
+        /// # Returns
+        ///
+        /// * `war` - The war
+        #[ink(message)]
+        pub fn get_war(&self, id: u32) -> War {
+            assert!(self.wars.get(&id).is_some());
+            self.wars.get(&id).unwrap().clone()
+        }
+
+        /// Gets all the wars of a guild.
+        ///
+        /// # Arguments
+        ///
+        /// * `guild` - Id of the guild
+        ///
+        /// # Returns
+        ///
+        /// * `wars` - A Vec containing a tuple of (war id, war)
+        #[ink(message)]
+        pub fn get_wars(&self, guild: u32) -> Vec<(u32, War)> {
+            self.wars
+                .iter()
+                .filter(|(_, war)| war.teams.iter().any(|team| team.guild == guild))
+                .map(|(&id, war)| (id, war.clone()))
+                .collect()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use ink_env::{
+            call::FromAccountId,
+            test,
+        };
+        use ink_lang as ink;
+        type Accounts = test::DefaultAccounts<Environment>;
+
+        fn default_accounts() -> Accounts {
+            test::default_accounts()
+                .expect("Test environment is expected to be initialized.")
+        }
+
+        fn new_contract() -> NewOmegaWar {
+            let accounts = default_accounts();
+            NewOmegaWar::new(NewOmegaGame::from_account_id(accounts.django),
+                NewOmegaStorage::from_account_id(accounts.frank))
+        }
+
+        /// Schedules a war between alice and bob, and charlie and eve, with the battle window
+        /// opening in the next block
+        fn schedule(contract: &mut NewOmegaWar, slots: u8) -> u32 {
+            let accounts = default_accounts();
+            contract.schedule_war(0, [accounts.alice, accounts.bob].to_vec(), 1,
+                [accounts.charlie, accounts.eve].to_vec(), slots, 1, 3, 100)
+        }
+
+        fn nominate(contract: &mut NewOmegaWar, id: u32, player: AccountId, commander: u8) {
+            contract.nominate_defence(player, id, [1, 1, 1, 1], [0, 0, 0, 0], commander, Reserve::default());
+        }
+
+        /// Makes an attack within the battle window without fighting, earning the given stars
+        fn attack(war: &mut War, id: u32, attacker: AccountId, defence: u8, stars: u8) {
+            let (team, _target, _seed) = prepare_war_attack(id, war, war.start, attacker, defence);
+            record_war_attack(war, WarAttack {
+                attacker,
+                team,
+                defence,
+                stars,
+                hash: [0; 32],
+            });
+        }
+
+        #[ink::test]
+        fn test_war_schedule() {
+            let mut contract = new_contract();
+
+            let id: u32 = contract.schedule_war(0, Vec::new(), 1, Vec::new(), 3, 10, 20, 100);
+            let war: War = contract.get_war(id);
+            assert_eq!(war.teams.len(), 2);
+            assert_eq!(war.teams[1].guild, 1);
+
+            assert_eq!(war_state(&war, 0), WarState::Preparation);
+            assert_eq!(war_state(&war, 10), WarState::Battle);
+            assert_eq!(war_state(&war, 20), WarState::Ended);
+
+            contract.schedule_war(2, Vec::new(), 3, Vec::new(), 3, 10, 20, 100);
+            assert_eq!(contract.get_wars(1).len(), 1);
+            assert_eq!(contract.get_wars(4).len(), 0);
+        }
+
+        #[ink::test]
+        fn test_war_rosters() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+
+            let id: u32 = contract.schedule_war(0, [accounts.alice, accounts.bob].to_vec(), 1,
+                [accounts.charlie].to_vec(), 2, 10, 20, 100);
+            let war: War = contract.get_war(id);
+            assert_eq!(war_team(&war, accounts.bob), Some(0));
+            assert_eq!(war_team(&war, accounts.charlie), Some(1));
+            assert_eq!(war_team(&war, accounts.eve), None);
+
+            // Membership is checked against the rosters, not the current guilds
+            contract.nominate_defence(accounts.charlie, id, [1, 1, 1, 1], [0, 0, 0, 0], 0, Reserve::default());
+            assert_eq!(contract.get_war(id).teams[1].defences.len(), 1);
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_war_nominate_outside_roster() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+
+            let id: u32 = contract.schedule_war(0, [accounts.alice].to_vec(), 1, [accounts.charlie].to_vec(),
+                2, 10, 20, 100);
+            contract.nominate_defence(accounts.eve, id, [1, 1, 1, 1], [0, 0, 0, 0], 0, Reserve::default());
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_war_overlapping_rosters() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+
+            contract.schedule_war(0, [accounts.alice].to_vec(), 1, [accounts.alice].to_vec(), 2, 10, 20, 100);
+        }
+
+        #[ink::test]
+        fn test_war_attack_seed() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+
+            let id: u32 = schedule(&mut contract, 2);
+            nominate(&mut contract, id, accounts.charlie, 0);
+            let mut war: War = contract.get_war(id);
+            let seed: u64 = war_attack_seed(id, &war, accounts.alice, 0);
+
+            // The seed stays the same whenever the attack is made
+            test::advance_block::<Environment>().expect("Cannot advance block");
+            assert_eq!(war_attack_seed(id, &war, accounts.alice, 0), seed);
+
+            // But differs between wars, attackers, defences and attacks
+            assert_ne!(war_attack_seed(id + 1, &war, accounts.alice, 0), seed);
+            assert_ne!(war_attack_seed(id, &war, accounts.charlie, 0), seed);
+            assert_ne!(war_attack_seed(id, &war, accounts.alice, 1), seed);
+            attack(&mut war, id, accounts.alice, 0, 0);
+            assert_ne!(war_attack_seed(id, &war, accounts.alice, 0), seed);
+        }
+
+        #[ink::test]
+        fn test_war_nomination() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+
+            let id: u32 = schedule(&mut contract, 1);
+            nominate(&mut contract, id, accounts.alice, 0);
+            nominate(&mut contract, id, accounts.charlie, 0);
+
+            // Nominating again replaces the previous defence
+            nominate(&mut contract, id, accounts.alice, 1);
+            let war: War = contract.get_war(id);
+            assert_eq!(war.teams[0].defences.len(), 1);
+            assert_eq!(war.teams[0].defences[0].commander, 1);
+            assert_eq!(war.teams[1].defences[0].player, accounts.charlie);
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_war_nomination_slots() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+
+            let id: u32 = schedule(&mut contract, 1);
+            nominate(&mut contract, id, accounts.alice, 0);
+            nominate(&mut contract, id, accounts.bob, 0);
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_war_nomination_late() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+
+            let id: u32 = schedule(&mut contract, 2);
+            test::advance_block::<Environment>().expect("Cannot advance block");
+            nominate(&mut contract, id, accounts.alice, 0);
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_war_attack_early() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+
+            let id: u32 = schedule(&mut contract, 2);
+            nominate(&mut contract, id, accounts.charlie, 0);
+            prepare_war_attack(id, &contract.get_war(id), 0, accounts.alice, 0);
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_war_attack_missing_defence() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+
+            let id: u32 = schedule(&mut contract, 2);
+            nominate(&mut contract, id, accounts.charlie, 0);
+            let mut war: War = contract.get_war(id);
+            attack(&mut war, id, accounts.alice, 1, 3);
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_war_attack_limit() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+
+            let id: u32 = schedule(&mut contract, 2);
+            nominate(&mut contract, id, accounts.charlie, 0);
+            let mut war: War = contract.get_war(id);
+            for _ in 0..=MAX_WAR_ATTACKS {
+                attack(&mut war, id, accounts.alice, 0, 1);
+            }
+        }
+
+        #[ink::test]
+        fn test_war_finish() {
+            let mut contract = new_contract();
+
+            let id: u32 = schedule(&mut contract, 2);
+            for _ in 0..3 {
+                test::advance_block::<Environment>().expect("Cannot advance block");
+            }
+
+            // Nobody attacked, the whole reward is refunded
+            assert_eq!(contract.finish_war(id), (None, Vec::new(), 100));
+            assert!(contract.get_war(id).finished);
+            assert_eq!(war_state(&contract.get_war(id), 3), WarState::Finished);
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_war_finish_early() {
+            let mut contract = new_contract();
+
+            let id: u32 = schedule(&mut contract, 2);
+            contract.finish_war(id);
+        }
+
+        #[ink::test]
+        #[should_panic]
+        fn test_war_schedule_invalid() {
+            let mut contract = new_contract();
+            contract.schedule_war(0, Vec::new(), 1, Vec::new(), 3, 20, 10, 100);
+        }
+
+        #[ink::test]
+        fn test_war_scoring() {
+            let mut contract = new_contract();
+            let accounts = default_accounts();
+
+            assert_eq!(war_stars(true, true, false), 0);
+            assert_eq!(war_stars(false, true, false), 3);
+            assert_eq!(war_stars(false, false, true), 2);
+            assert_eq!(war_stars(false, false, false), 1);
+
+            let id: u32 = schedule(&mut contract, 2);
+            for player in [accounts.alice, accounts.bob, accounts.charlie, accounts.eve].iter() {
+                nominate(&mut contract, id, *player, 0);
+            }
+            let mut war: War = contract.get_war(id);
+
+            // Only improving on the best attack against a defence earns stars
+            attack(&mut war, id, accounts.charlie, 0, 2);
+            attack(&mut war, id, accounts.eve, 0, 3);
+            attack(&mut war, id, accounts.eve, 0, 1);
+            assert_eq!(war.teams[1].stars, 3);
+            assert_eq!(war.teams[0].defences[0].stars, 3);
+
+            // A draw splits the reward among all the attackers, the remainder is refunded
+            attack(&mut war, id, accounts.alice, 1, 3);
+            assert_eq!(war_payouts(&war), (None, [(accounts.charlie, 33), (accounts.eve, 33),
+                (accounts.alice, 33)].to_vec(), 1));
+
+            // The winner's attackers split the reward
+            attack(&mut war, id, accounts.bob, 0, 1);
+            assert_eq!(war.teams[0].stars, 4);
+            assert_eq!(war_payouts(&war), (Some(0), [(accounts.alice, 50), (accounts.bob, 50)].to_vec(), 0));
+        }
+    }
+}